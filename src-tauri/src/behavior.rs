@@ -9,3 +9,12 @@ pub use self::support_behavior::SupportBehavior;
 
 mod shout_behavior;
 pub use self::shout_behavior::ShoutBehavior;
+
+mod scripted_behavior;
+pub use self::scripted_behavior::ScriptedBehavior;
+
+mod bot_runner;
+pub use self::bot_runner::{BotRunner, Iteration};
+
+#[cfg(test)]
+pub mod simulation;
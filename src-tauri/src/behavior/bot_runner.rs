@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use slog::Logger;
+
+use super::{Behavior, FarmingBehavior, ScriptedBehavior, ShoutBehavior, SupportBehavior};
+use crate::{
+    data::{ConnectionRecovery, LifeState, RecoveryAction, Target},
+    image_analyzer::ImageAnalyzer,
+    ipc::{BotConfig, BotMode, DeathPolicy, FrontendAlert, FrontendInfo},
+    movement::MovementAccessor,
+    platform::{InputSink, KeyMode},
+    utils::Clock,
+};
+
+/// How an iteration of the main loop went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Iteration {
+    /// The behavior of the current mode ran
+    Ran,
+    /// Waited for the client to reconnect or the character to come back
+    Waited,
+    /// Waited, with news for the frontend
+    Alerted,
+    /// The character died and the client has to be closed
+    Exit,
+}
+
+/// Runs the behavior of the current mode along with what all modes share:
+/// reconnecting, dealing with death and publishing system messages.
+/// Both the main loop and session replays go through it.
+pub struct BotRunner<'a> {
+    logger: &'a Logger,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
+    farming_behavior: FarmingBehavior<'a>,
+    shout_behavior: ShoutBehavior<'a>,
+    support_behavior: SupportBehavior<'a>,
+    scripted_behavior: ScriptedBehavior<'a>,
+    last_mode: Option<BotMode>,
+    connection_recovery: ConnectionRecovery,
+}
+
+impl<'a> BotRunner<'a> {
+    pub fn new(
+        logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
+        Self {
+            logger,
+            input,
+            clock,
+            farming_behavior: FarmingBehavior::new(logger, movement, input, clock),
+            shout_behavior: ShoutBehavior::new(logger, movement, input, clock),
+            support_behavior: SupportBehavior::new(logger, movement, input, clock),
+            scripted_behavior: ScriptedBehavior::new(logger, movement, input, clock),
+            last_mode: None,
+            connection_recovery: ConnectionRecovery::default(),
+        }
+    }
+
+    /// Runs on config change
+    pub fn update(&mut self, config: &BotConfig) {
+        self.farming_behavior.update(config);
+        self.shout_behavior.update(config);
+        self.support_behavior.update(config);
+        self.scripted_behavior.update(config);
+    }
+
+    /// Run one iteration on the frame the analyzer captured last.
+    pub fn run_iteration(
+        &mut self,
+        mode: &BotMode,
+        config: &BotConfig,
+        frontend_info: &mut FrontendInfo,
+        image_analyzer: &mut ImageAnalyzer,
+    ) -> Iteration {
+        // Start the behavior of the mode when it changes
+        if self.last_mode.as_ref() != Some(mode) {
+            if let Some(last_mode) = &self.last_mode {
+                slog::info!(self.logger, "Mode changed"; "old_mode" => last_mode.to_string(), "new_mode" => mode.to_string());
+
+                // Stop all behaviors
+                self.farming_behavior.stop(config);
+                self.support_behavior.stop(config);
+                self.shout_behavior.stop(config);
+                self.scripted_behavior.stop(config);
+            }
+            self.behavior(mode).start(config);
+            self.last_mode = Some(mode.clone());
+        }
+
+        // Clear markers drawn during the last iteration
+        self.input.clear_overlay();
+
+        // Update stats
+        image_analyzer.update_client_stats(self.logger);
+
+        // Reconnect
+        let connection_state = image_analyzer.detect_connection_state(self.logger);
+        let settings = config.connection_config().recovery_settings();
+        match self
+            .connection_recovery
+            .update(connection_state, &settings, self.clock.now())
+        {
+            RecoveryAction::None => {}
+            RecoveryAction::Restored => {
+                slog::info!(self.logger, "Connection restored");
+                frontend_info.set_alert(None);
+
+                // Start the current behavior again from a clean state
+                let behavior = self.behavior(mode);
+                behavior.stop(config);
+                behavior.start(config);
+            }
+            RecoveryAction::Wait => {
+                self.clock.sleep(Duration::from_millis(500));
+                return Iteration::Waited;
+            }
+            RecoveryAction::PressEnter => {
+                slog::info!(self.logger, "Reconnecting"; "state" => format!("{:?}", connection_state));
+                self.input.send_key("Enter", KeyMode::Press);
+                self.clock.sleep(Duration::from_millis(1500));
+                return Iteration::Waited;
+            }
+            RecoveryAction::SelectCharacter(slot) => {
+                slog::info!(self.logger, "Selecting character"; "slot" => slot);
+                self.select_character(slot);
+                self.clock.sleep(Duration::from_millis(1500));
+                return Iteration::Waited;
+            }
+            RecoveryAction::GiveUp => {
+                slog::error!(self.logger, "Could not reconnect, giving up"; "state" => format!("{:?}", connection_state));
+                frontend_info.set_alert(Some(FrontendAlert::ConnectionLost));
+                return Iteration::Alerted;
+            }
+        }
+
+        // Stop bot in case of death
+        let life_state = image_analyzer.update_life_state(self.input);
+        if life_state == LifeState::Dead {
+            if frontend_info.is_alive() {
+                slog::warn!(self.logger, "Character died"; "policy" => format!("{:?}", death_policy(mode, config)));
+                if should_disconnect(mode, config) {
+                    return Iteration::Exit;
+                }
+
+                frontend_info.set_is_alive(false);
+                if death_policy(mode, config) == DeathPolicy::StopAndAlert {
+                    frontend_info.set_alert(Some(FrontendAlert::Died));
+                }
+                return Iteration::Alerted;
+            }
+
+            match death_policy(mode, config) {
+                DeathPolicy::ReviveAtLodestar => {
+                    self.input.send_key("Enter", KeyMode::Press);
+                    self.clock.sleep(Duration::from_millis(500));
+                }
+                // Leave the revive dialog alone
+                DeathPolicy::WaitForResurrection | DeathPolicy::StopAndAlert => {
+                    self.clock.sleep(Duration::from_millis(500));
+                }
+            }
+            return Iteration::Waited;
+        } else if life_state == LifeState::Reviving {
+            // Wait for the stat tray to come back before acting again
+            self.clock.sleep(Duration::from_millis(250));
+            return Iteration::Waited;
+        } else if !frontend_info.is_alive() {
+            slog::info!(self.logger, "Character is alive again");
+            frontend_info.set_is_alive(true);
+            frontend_info.set_alert(None);
+            if !should_disconnect(mode, config) {
+                self.input.send_key("Escape", KeyMode::Press);
+            }
+        }
+
+        // Publish system messages to the current behavior
+        let behavior = self.behavior(mode);
+        for event in image_analyzer.read_game_events() {
+            behavior.on_game_event(config, &event);
+        }
+        behavior.run_iteration(frontend_info, config, image_analyzer);
+        Iteration::Ran
+    }
+
+    /// The mobs around and what the behavior of the mode is doing, for session recordings.
+    pub fn describe(
+        &self,
+        mode: &BotMode,
+        config: &BotConfig,
+        image_analyzer: &ImageAnalyzer,
+    ) -> (Vec<Target>, String) {
+        // Mobs are identified again since behaviors don't keep them around
+        let targets = match mode {
+            BotMode::Farming | BotMode::Scripted => {
+                image_analyzer.identify_mobs(&config.farming_config().mob_name_settings())
+            }
+            BotMode::AutoShout | BotMode::Support => vec![],
+        };
+        let state = match mode {
+            BotMode::Farming => self.farming_behavior.describe_state(),
+            BotMode::AutoShout => self.shout_behavior.describe_state(),
+            BotMode::Support => self.support_behavior.describe_state(),
+            BotMode::Scripted => self.scripted_behavior.describe_state(),
+        };
+        (targets, state)
+    }
+
+    fn behavior(&mut self, mode: &BotMode) -> &mut dyn Behavior<'a> {
+        match mode {
+            BotMode::Farming => &mut self.farming_behavior,
+            BotMode::AutoShout => &mut self.shout_behavior,
+            BotMode::Support => &mut self.support_behavior,
+            BotMode::Scripted => &mut self.scripted_behavior,
+        }
+    }
+
+    /// Go to the first character of the selection screen, move to `slot` and start playing.
+    fn select_character(&self, slot: u32) {
+        for key in ["ArrowLeft"; 5]
+            .into_iter()
+            .chain((0..slot).map(|_| "ArrowRight"))
+        {
+            self.input.send_key(key, KeyMode::Press);
+            self.clock.sleep(Duration::from_millis(200));
+        }
+        self.input.send_key("Enter", KeyMode::Press);
+    }
+}
+
+fn should_disconnect(mode: &BotMode, config: &BotConfig) -> bool {
+    match mode {
+        BotMode::Farming => config.farming_config().on_death_disconnect(),
+        BotMode::Support => config.support_config().on_death_disconnect(),
+        BotMode::AutoShout => true,
+        // Scripts farm with the farming config
+        BotMode::Scripted => config.farming_config().on_death_disconnect(),
+    }
+}
+
+fn death_policy(mode: &BotMode, config: &BotConfig) -> DeathPolicy {
+    match mode {
+        BotMode::Farming => config.farming_config().on_death(),
+        BotMode::Support => config.support_config().on_death(),
+        BotMode::AutoShout => DeathPolicy::default(),
+        BotMode::Scripted => config.farming_config().on_death(),
+    }
+}
@@ -1,23 +1,30 @@
 use std::time::{Duration, Instant};
 
+use guard::guard;
 use rand::prelude::SliceRandom;
 use slog::Logger;
-use tauri::{Manager, Window};
 
 use super::Behavior;
 use crate::{
-    data::{Bounds, MobType, Point, Target, TargetType},
-    image_analyzer::{Color, ImageAnalyzer},
-    ipc::{BotConfig, FarmingConfig, FrontendInfo, SlotType},
-    movement::MovementAccessor,
-    platform::{
-        draw_bounds_rect, eval_mob_click, eval_send_key, remove_all_markers, send_slot_eval,
-        KeyMode,
+    data::{
+        Bounds, ExperienceTracker, GameEvent, MobRank, MobTracker, MobType, Point, Target,
+        TargetType, TrackId,
     },
+    image_analyzer::{
+        BuffAction, Color, ImageAnalyzer, ScoringContext, SlotObservations, WeightedScorer,
+    },
+    ipc::{BotConfig, FarmingConfig, FrontendInfo, GiantPolicy, SlotType},
+    movement::{
+        MovementAccessor, MovementHandle, MovementPriority, Patrol, PatrolMove, PatrolSight,
+    },
+    platform::{InputSink, KeyMode},
     play,
-    utils::DateTime,
+    utils::{Clock, DateTime},
 };
 
+/// Giants closer than this to the character can hit it, measured in an 800x600 client.
+const GIANT_REACH: i32 = 100;
+
 #[derive(Debug, Clone, Copy)]
 enum State {
     NoEnemyFound,
@@ -31,13 +38,22 @@ pub struct FarmingBehavior<'a> {
     rng: rand::rngs::ThreadRng,
     logger: &'a Logger,
     movement: &'a MovementAccessor,
-    window: &'a Window,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
     state: State,
     slots_usage_last_time: [[Option<Instant>; 10]; 9],
+    /// Slot bar shown since the last slot key press
+    shown_slot_bar: Option<usize>,
+    slot_observations: SlotObservations,
     last_initial_attack_time: Instant,
     last_kill_time: Instant,
     avoided_bounds: Vec<(Bounds, Instant, u128)>,
+    avoided_tracks: Vec<(TrackId, Instant, u128)>,
+    tracker: MobTracker,
     rotation_movement_tries: u32,
+    /// Circle walked while looking for mobs
+    search_movement: Option<MovementHandle>,
+    patrol: Patrol,
     is_attacking: bool,
     kill_count: u32,
     obstacle_avoidance_count: u32,
@@ -49,39 +65,59 @@ pub struct FarmingBehavior<'a> {
     last_click_pos: Option<Point>,
     stealed_target_count: u32,
     last_no_ennemy_time: Option<Instant>,
+    experience: ExperienceTracker,
+    inventory_full: bool,
 }
 
 impl<'a> Behavior<'a> for FarmingBehavior<'a> {
-    fn new(logger: &'a Logger, movement: &'a MovementAccessor, window: &'a Window) -> Self {
+    fn new(
+        logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
         Self {
             logger,
             movement,
-            window,
+            input,
+            clock,
             rng: rand::thread_rng(),
             state: State::SearchingForEnemy,
             slots_usage_last_time: [[None; 10]; 9],
-            last_initial_attack_time: Instant::now(),
-            last_kill_time: Instant::now(),
+            shown_slot_bar: None,
+            slot_observations: [[None; 10]; 9],
+            last_initial_attack_time: clock.now(),
+            last_kill_time: clock.now(),
             avoided_bounds: vec![],
+            avoided_tracks: vec![],
+            tracker: MobTracker::default(),
             is_attacking: false,
             rotation_movement_tries: 0,
+            search_movement: None,
+            patrol: Patrol::default(),
             kill_count: 0,
             obstacle_avoidance_count: 0,
             last_summon_pet_time: None,
             last_killed_type: MobType::Passive,
-            start_time: Instant::now(),
+            start_time: clock.now(),
             already_attack_count: 0,
-            last_buff_usage: Instant::now(),
+            last_buff_usage: clock.now(),
             last_click_pos: None,
             stealed_target_count: 0,
             last_no_ennemy_time: None,
+            experience: ExperienceTracker::default(),
+            inventory_full: false,
         }
     }
 
-    fn start(&mut self, _config: &BotConfig) {}
+    fn start(&mut self, _config: &BotConfig) {
+        self.inventory_full = false;
+    }
     fn update(&mut self, _config: &BotConfig) {}
     fn stop(&mut self, _config: &BotConfig) {
         self.slots_usage_last_time = [[None; 10]; 9];
+        self.stop_search_movement();
+        self.patrol = Patrol::default();
     }
 
     fn run_iteration(
@@ -93,13 +129,15 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
         let config = config.farming_config();
         // Update all needed timestamps
         self.update_timestamps(config);
+        self.slot_observations =
+            image.slot_observations(self.shown_slot_bar, &self.slots_usage_last_time);
 
         // Check whether something should be restored
         self.check_restorations(config, image);
 
         // Check state machine
         self.state = match self.state {
-            State::NoEnemyFound => self.on_no_enemy_found(config),
+            State::NoEnemyFound => self.on_no_enemy_found(config, image),
             State::SearchingForEnemy => self.on_searching_for_enemy(config, image),
             State::EnemyFound(mob) => self.on_enemy_found(mob),
             State::Attacking(mob) => self.on_attacking(config, mob, image),
@@ -108,6 +146,34 @@ impl<'a> Behavior<'a> for FarmingBehavior<'a> {
 
         frontend_info.set_is_attacking(self.is_attacking);
     }
+
+    fn on_game_event(&mut self, config: &BotConfig, event: &GameEvent) {
+        let config = config.farming_config();
+        slog::debug!(self.logger, "Game event"; "event" => format!("{:?}", event));
+
+        match event {
+            GameEvent::InventoryFull => {
+                if !self.inventory_full {
+                    slog::warn!(self.logger, "Inventory is full, items won't be picked up");
+                }
+                self.inventory_full = true;
+            }
+            // Something got picked up so there's room again
+            GameEvent::ItemObtained(_) => self.inventory_full = false,
+            GameEvent::TargetTooFar | GameEvent::CannotAttack => {
+                if let State::Attacking(mob) = self.state {
+                    self.state = self.skip_target(mob);
+                }
+            }
+            GameEvent::NotEnoughMp => {
+                self.get_slot_for(config, None, SlotType::MpRestorer, true);
+            }
+        }
+    }
+
+    fn describe_state(&self) -> String {
+        format!("{:?}", self.state)
+    }
 }
 
 impl FarmingBehavior<'_> {
@@ -124,27 +190,29 @@ impl FarmingBehavior<'_> {
         let mut result: Vec<(Bounds, Instant, u128)> = vec![];
         for n in 0..self.avoided_bounds.len() {
             let current = self.avoided_bounds[n];
-            if current.1.elapsed().as_millis() < current.2 {
+            if self.clock.elapsed(current.1).as_millis() < current.2 {
                 result.push(current);
             }
         }
         self.avoided_bounds = result;
+
+        let clock = self.clock;
+        self.avoided_tracks
+            .retain(|(_, since, duration)| clock.elapsed(*since).as_millis() < *duration);
     }
 
     /// Check whether pickup pet should be unsummoned
     fn update_pickup_pet(&mut self, config: &FarmingConfig) {
         if let Some(pickup_pet_slot_index) = config.slot_index(SlotType::PickupPet) {
             if let Some(last_time) = self.last_summon_pet_time {
-                if last_time.elapsed().as_millis()
+                if self.clock.elapsed(last_time).as_millis()
                     > config
                         .get_slot_cooldown(pickup_pet_slot_index.0, pickup_pet_slot_index.1)
                         .unwrap_or(3000) as u128
                 {
-                    send_slot_eval(
-                        self.window,
-                        pickup_pet_slot_index.0,
-                        pickup_pet_slot_index.1,
-                    );
+                    self.input
+                        .send_slot(pickup_pet_slot_index.0, pickup_pet_slot_index.1);
+                    self.shown_slot_bar = Some(pickup_pet_slot_index.0);
                     self.last_summon_pet_time = None;
                 }
             }
@@ -161,7 +229,7 @@ impl FarmingBehavior<'_> {
                     .try_into();
                 if let Some(last_time) = last_time {
                     if let Ok(cooldown) = cooldown {
-                        let slot_last_time = last_time.elapsed().as_millis();
+                        let slot_last_time = self.clock.elapsed(last_time).as_millis();
                         if slot_last_time > cooldown {
                             self.slots_usage_last_time[slotbar_index][slot_index] = None;
                         }
@@ -178,9 +246,12 @@ impl FarmingBehavior<'_> {
         slot_type: SlotType,
         send: bool,
     ) -> Option<(usize, usize)> {
-        if let Some(slot_index) =
-            config.get_usable_slot_index(slot_type, threshold, self.slots_usage_last_time)
-        {
+        if let Some(slot_index) = config.get_usable_slot_index(
+            slot_type,
+            threshold,
+            self.slots_usage_last_time,
+            &self.slot_observations,
+        ) {
             if send {
                 //slog::debug!(self.logger, "Slot usage"; "slot_type" => slot_type.to_string(), "value" => threshold);
                 self.send_slot(slot_index);
@@ -199,8 +270,12 @@ impl FarmingBehavior<'_> {
         send: bool,
     ) -> Vec<(usize, usize)> {
         let mut indexes = Vec::new();
-        let slot_indexes =
-            config.get_usable_slot_indexes(slot_type, threshold, self.slots_usage_last_time);
+        let slot_indexes = config.get_usable_slot_indexes(
+            slot_type,
+            threshold,
+            self.slots_usage_last_time,
+            &self.slot_observations,
+        );
         if !slot_indexes.is_empty() {
             for slot_index in slot_indexes {
                 if send {
@@ -215,28 +290,35 @@ impl FarmingBehavior<'_> {
 
     fn send_slot(&mut self, slot_index: (usize, usize)) {
         // Send keystroke for first slot mapped to pill
-        send_slot_eval(self.window, slot_index.0, slot_index.1);
+        self.input.send_slot(slot_index.0, slot_index.1);
+        self.shown_slot_bar = Some(slot_index.0);
         // Update usage last time
-        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(Instant::now());
+        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(self.clock.now());
     }
 
     /// Pickup items on the ground.
     fn pickup_items(&mut self, config: &FarmingConfig, image: &mut ImageAnalyzer) {
+        if self.inventory_full {
+            return;
+        }
+
         let slot = self.get_slot_for(config, None, SlotType::PickupPet, false);
         if let Some(index) = slot {
             if self.last_summon_pet_time.is_none() {
-                send_slot_eval(self.window, index.0, index.1);
-                self.last_summon_pet_time = Some(Instant::now());
+                self.input.send_slot(index.0, index.1);
+                self.shown_slot_bar = Some(index.0);
+                self.last_summon_pet_time = Some(self.clock.now());
             } else {
                 // if pet is already out, just reset it's timer
-                self.last_summon_pet_time = Some(Instant::now());
+                self.last_summon_pet_time = Some(self.clock.now());
             }
         } else {
             let slot = self.get_slot_for(config, None, SlotType::PickupMotion, false);
             if let Some(index) = slot {
                 for _i in 1..(config.pickup_duration() / 150) {
-                    send_slot_eval(self.window, index.0, index.1);
-                    std::thread::sleep(Duration::from_millis(150));
+                    self.input.send_slot(index.0, index.1);
+                    self.shown_slot_bar = Some(index.0);
+                    self.clock.sleep(Duration::from_millis(150));
                     self.check_restorations(config, image);
                 }
             }
@@ -264,23 +346,52 @@ impl FarmingBehavior<'_> {
         }
     }
 
-    fn check_buffs(&mut self, config: &FarmingConfig) {
-        if self.last_buff_usage.elapsed().as_millis() > config.interval_between_buffs() {
-            self.last_buff_usage = Instant::now();
-            self.get_slot_for(config, None, SlotType::BuffSkill, true);
-            std::thread::sleep(Duration::from_millis(100));
+    fn check_buffs(&mut self, config: &FarmingConfig, image: &mut ImageAnalyzer) {
+        if self.clock.elapsed(self.last_buff_usage).as_millis() <= config.interval_between_buffs() {
+            return;
+        }
+
+        // Only recast buffs missing from the buff tray
+        let slot_indexes = config.get_usable_slot_indexes(
+            SlotType::BuffSkill,
+            None,
+            self.slots_usage_last_time,
+            &self.slot_observations,
+        );
+        match image.next_buff_action(slot_indexes) {
+            Some(BuffAction::ShowSlotBar(slot_bar)) => {
+                self.input
+                    .send_key(&format!("F{}", slot_bar + 1), KeyMode::Press);
+                self.shown_slot_bar = Some(slot_bar);
+            }
+            Some(BuffAction::Cast(slot_index)) => self.send_slot(slot_index),
+            None => return,
         }
+        self.last_buff_usage = self.clock.now();
+        self.clock.sleep(Duration::from_millis(100));
     }
 
-    fn on_no_enemy_found(&mut self, config: &FarmingConfig) -> State {
+    fn on_no_enemy_found(&mut self, config: &FarmingConfig, image: &ImageAnalyzer) -> State {
         if let Some(last_no_ennemy_time) = self.last_no_ennemy_time {
             if config.mobs_timeout() > 0
-                && last_no_ennemy_time.elapsed().as_millis() > config.mobs_timeout()
+                && self.clock.elapsed(last_no_ennemy_time).as_millis() > config.mobs_timeout()
             {
-                self.window.app_handle().exit(0);
+                self.input.exit_app();
             }
         } else {
-            self.last_no_ennemy_time = Some(Instant::now());
+            self.last_no_ennemy_time = Some(self.clock.now());
+        }
+        // Keep looking for mobs while the last circle is being walked
+        if self
+            .search_movement
+            .as_ref()
+            .map_or(false, |movement| !movement.is_finished())
+        {
+            return State::SearchingForEnemy;
+        }
+        // Head back right away when a chase took us out of the patrol zone
+        if self.rotation_movement_tries == 0 && self.is_outside_leash(config, image) {
+            self.rotation_movement_tries = 30;
         }
         use crate::movement::prelude::*;
         // Try rotating first in order to locate nearby enemies
@@ -292,15 +403,28 @@ impl FarmingBehavior<'_> {
                 Wait(dur::Fixed(50)),
             ]);
             self.rotation_movement_tries += 1;
+            // Turning changed the heading the last patrol walk told
+            self.patrol.interrupt();
 
             // Transition to next state
             return State::SearchingForEnemy;
         }
 
+        // Walk the patrol route, or back to the anchor when too far from it
+        if let Some(movement) = self.patrol(config, image) {
+            // Mobs are looked for while walking, the next leg follows without turning around
+            self.search_movement = Some(movement);
+            return State::SearchingForEnemy;
+        }
+
         // Check whether bot should stay in area
         let circle_pattern_rotation_duration = config.circle_pattern_rotation_duration();
         if circle_pattern_rotation_duration > 0 {
-            self.move_circle_pattern(circle_pattern_rotation_duration);
+            let movement = match self.start_sequence(config.circle_pattern_sequence()) {
+                Some(movement) => movement,
+                None => self.move_circle_pattern(circle_pattern_rotation_duration),
+            };
+            self.search_movement = Some(movement);
         } else {
             self.rotation_movement_tries = 0;
             return self.state;
@@ -309,17 +433,90 @@ impl FarmingBehavior<'_> {
         State::SearchingForEnemy
     }
 
-    fn move_circle_pattern(&self, rotation_duration: u64) {
+    /// Start a movement sequence of the profile, `None` if none is set or it doesn't exist.
+    fn start_sequence(&self, name: Option<&str>) -> Option<MovementHandle> {
+        let name = name?;
+        let movement = self.movement.play_sequence(name);
+        if movement.is_none() {
+            slog::warn!(self.logger, "Movement sequence not found"; "name" => name);
+        }
+        movement
+    }
+
+    fn move_circle_pattern(&self, rotation_duration: u64) -> MovementHandle {
         // low rotation duration means big circle, high means little circle
         use crate::movement::prelude::*;
-        play!(self.movement => [
-            HoldKeys(vec!["W", "Space", "D"]),
-            Wait(dur::Fixed(rotation_duration)),
-            ReleaseKey("D"),
-            Wait(dur::Fixed(20)),
-            ReleaseKeys(vec!["Space", "W"]),
-            HoldKeyFor("S", dur::Fixed(50)),
-        ]);
+        self.movement.start(
+            [
+                HoldKeys(vec!["W", "Space", "D"]),
+                Wait(dur::Fixed(rotation_duration)),
+                ReleaseKey("D"),
+                Wait(dur::Fixed(20)),
+                ReleaseKeys(vec!["Space", "W"]),
+                HoldKeyFor("S", dur::Fixed(50)),
+            ],
+            MovementPriority::Normal,
+        )
+    }
+
+    /// Start the next patrol leg, `None` without a route or anchor to walk to.
+    /// Mobs shown on the minimap are walked to first.
+    fn patrol(&mut self, config: &FarmingConfig, image: &ImageAnalyzer) -> Option<MovementHandle> {
+        let route = config.patrol_route();
+        let leash = config.patrol_radius();
+        if route.is_empty() && leash.is_none() {
+            return None;
+        }
+
+        let position = image.read_position();
+        let minimap = image.read_minimap();
+        let sight = PatrolSight {
+            position,
+            heading: minimap.as_ref().and_then(|minimap| minimap.heading),
+            mobs: minimap
+                .zip(position)
+                .map(|(minimap, position)| {
+                    minimap.mob_positions(position, config.minimap_units_per_pixel())
+                })
+                .unwrap_or_default(),
+        };
+        match self
+            .patrol
+            .next(&route, config.patrol_anchor(), leash, &sight)?
+        {
+            PatrolMove::Sequence(name) => self.start_sequence(Some(name)),
+            walk => Some(
+                self.movement
+                    .start(walk.movements(), MovementPriority::Normal),
+            ),
+        }
+    }
+
+    /// Whether a giant stands next to us or walks towards us, so it's likely the one hitting us.
+    fn is_giant_closing_in(&self, mobs: &[Target], image: &ImageAnalyzer) -> bool {
+        let center = image.screen_center();
+        let reach = image.frame_layout().scale_distance(GIANT_REACH) as f32;
+        mobs.iter()
+            .filter(|mob| mob.rank == MobRank::Giant)
+            .filter_map(|mob| self.tracker.track(mob.track_id?))
+            .any(|track| track.is_closing_in(center, reach))
+    }
+
+    /// Whether the character is further from the patrol anchor than the patrol radius.
+    fn is_outside_leash(&self, config: &FarmingConfig, image: &ImageAnalyzer) -> bool {
+        guard!(let Some(leash) = config.patrol_radius() else { return false });
+        image.read_position().map_or(false, |position| {
+            self.patrol
+                .is_outside(position, config.patrol_anchor(), leash)
+        })
+    }
+
+    /// Stop walking the search circle, releasing its keys.
+    fn stop_search_movement(&mut self) {
+        if let Some(movement) = self.search_movement.take() {
+            movement.cancel();
+            movement.wait();
+        }
     }
 
     fn on_searching_for_enemy(
@@ -328,116 +525,90 @@ impl FarmingBehavior<'_> {
         image: &mut ImageAnalyzer,
     ) -> State {
         if config.auto_bird_view() {
-            eval_send_key(&self.window, "ArrowUp", KeyMode::Hold);
-            eval_send_key(&self.window, "PageDown", KeyMode::Hold);
-            std::thread::sleep(Duration::from_millis(20));
-            eval_send_key(&self.window, "ArrowUp", KeyMode::Release);
-            eval_send_key(&self.window, "PageDown", KeyMode::Release);
+            self.input.send_key("ArrowUp", KeyMode::Hold);
+            self.input.send_key("PageDown", KeyMode::Hold);
+            self.clock.sleep(Duration::from_millis(20));
+            self.input.send_key("ArrowUp", KeyMode::Release);
+            self.input.send_key("PageDown", KeyMode::Release);
         }
         if config.is_stop_fighting() {
             return State::Attacking(Target::default());
         }
-        let mobs = image.identify_mobs(config);
+        let mut mobs = image.identify_mobs(&config.mob_name_settings());
+        self.tracker.update(&mut mobs, self.clock.now());
+
+        // Run away from giants while they're hitting us
+        if config.giant_policy() == GiantPolicy::FleeIfAggroed
+            && image.client_stats.hp.value < image.client_stats.hp.last_value
+            && self.is_giant_closing_in(&mobs, image)
+        {
+            return self.flee();
+        }
+        let allow_giants = config
+            .giant_policy()
+            .allows_attack(image.client_stats.hp.value);
+
         if mobs.is_empty() {
             // Transition to next state
             State::NoEnemyFound
         } else {
-            // Calculate max distance of mobs
-            let max_distance = match config.circle_pattern_rotation_duration() == 0 {
-                true => 325,
-                false => 1000,
-            };
-            remove_all_markers(self.window);
-            // Get aggressive mobs to prioritize them, but divide max_distance by 2 for aggressive mobs to avoid uncontrolable moving
-            let mut mob_list = mobs
+            // Calculate max distance of mobs, measured in an 800x600 client
+            let max_distance = image.frame_layout().scale_distance(
+                match config.circle_pattern_rotation_duration() == 0 {
+                    true => 325,
+                    false => 1000,
+                },
+            );
+            self.input.clear_overlay();
+
+            // Passive mobs need enough HP, and the name of an aggressive mob we just killed may still be shown
+            let aggressive_count = mobs
                 .iter()
                 .filter(|m| m.target_type == TargetType::Mob(MobType::Aggressive))
+                .count();
+            let skip_aggressive = self.last_killed_type == MobType::Aggressive
+                && aggressive_count == 1
+                && self.clock.elapsed(self.last_kill_time).as_millis() < 5000;
+            // Out of the patrol zone only mobs coming after us are fought
+            let attack_passive = image.client_stats.hp.value >= config.min_hp_attack()
+                && !self.is_outside_leash(config, image);
+            let candidates = mobs
+                .iter()
+                .filter(|m| match m.target_type {
+                    TargetType::Mob(MobType::Aggressive) => !skip_aggressive,
+                    _ => attack_passive,
+                })
                 .cloned()
                 .collect::<Vec<_>>();
 
-            for mob in mob_list.clone() {
-                draw_bounds_rect(
-                    self.window,
-                    mob.bounds.x,
-                    mob.bounds.y,
-                    mob.bounds.w,
-                    mob.bounds.h,
-                    Color::new(255, 0, 255),
-                );
-            }
-
-            // Check again
-            if !mob_list.is_empty()
-                && !(self.last_killed_type == MobType::Aggressive
-                    && mob_list.len() == 1
-                    && self.last_kill_time.elapsed().as_millis() < 5000)
-            {
-                self.rotation_movement_tries = 0;
-                //slog::debug!(self.logger, "Found mobs"; "mob_type" => mob_type, "mob_count" => mob_list.len());
-                if let Some(mob) = {
-                    // Try avoiding detection of last killed mob
-                    if !self.avoided_bounds.is_empty() {
-                        image.find_closest_mob(
-                            mob_list.as_slice(),
-                            Some(&self.avoided_bounds),
-                            max_distance,
-                            self.logger,
-                        )
-                    } else {
-                        image.find_closest_mob(mob_list.as_slice(), None, max_distance, self.logger)
-                    }
-                } {
-                    // Transition to next state
-                    return State::EnemyFound(*mob);
-                }
-            }
-            mob_list.clear();
-            // Check if there's aggressive mobs otherwise collect passive mobs
-            if image.client_stats.hp.value >= config.min_hp_attack() {
-                mob_list = mobs
-                    .iter()
-                    .filter(|m| m.target_type == TargetType::Mob(MobType::Passive))
-                    .cloned()
-                    .collect::<Vec<_>>();
+            for mob in &candidates {
+                self.input.draw_bounds(mob.bounds, Color::new(255, 0, 255));
             }
 
-            for mob in mob_list.clone() {
-                draw_bounds_rect(
-                    self.window,
-                    mob.bounds.x,
-                    mob.bounds.y,
-                    mob.bounds.w,
-                    mob.bounds.h,
-                    Color::new(255, 0, 255),
-                );
+            if candidates.is_empty() {
+                // Transition to next state
+                return State::NoEnemyFound;
             }
+            self.rotation_movement_tries = 0;
 
-            // Check again
-            if !mob_list.is_empty() {
-                self.rotation_movement_tries = 0;
-                //slog::debug!(self.logger, "Found mobs"; "mob_type" => mob_type, "mob_count" => mob_list.len());
-                if let Some(mob) = {
-                    // Try avoiding detection of last killed mob
-                    if !self.avoided_bounds.is_empty() {
-                        image.find_closest_mob(
-                            mob_list.as_slice(),
-                            Some(&self.avoided_bounds),
-                            max_distance,
-                            self.logger,
-                        )
-                    } else {
-                        image.find_closest_mob(mob_list.as_slice(), None, max_distance, self.logger)
-                    }
-                } {
-                    // Transition to next state
-                    State::EnemyFound(*mob)
-                } else {
-                    // Transition to next state
-                    State::SearchingForEnemy
-                }
+            let scorer = WeightedScorer::new(config.scoring_weights());
+            let context = ScoringContext {
+                center: image.screen_center(),
+                mobs: &mobs,
+                avoided_bounds: &self.avoided_bounds,
+                avoided_tracks: &self.avoided_tracks,
+                max_distance,
+                limit_range: true,
+                allow_giants,
+                hp: image.client_stats.hp.value,
+                mp: image.client_stats.mp.value,
+            };
+            if let Some(mob) = image.find_best_mob(&candidates, &scorer, &context) {
+                // Transition to next state
+                State::EnemyFound(*mob)
             } else {
                 // Transition to next state
-                State::NoEnemyFound
+                State::SearchingForEnemy
             }
         }
     }
@@ -445,21 +616,24 @@ impl FarmingBehavior<'_> {
     fn avoid_last_click(&mut self) {
         if let Some(point) = self.last_click_pos {
             let marker = Bounds::new(point.x - 1, point.y - 1, 2, 2);
-            self.avoided_bounds.push((marker, Instant::now(), 5000));
+            self.avoided_bounds.push((marker, self.clock.now(), 5000));
         }
     }
 
     fn on_enemy_found(&mut self, mob: Target) -> State {
+        self.stop_search_movement();
+        self.patrol.interrupt();
+
         // Transform attack coords into local window coords
         let point = mob.get_attack_coords();
 
         self.last_click_pos = Some(point);
 
         // Set cursor position and simulate a click
-        eval_mob_click(self.window, point);
+        self.input.mouse_click(point);
 
         // Wait a few ms before transitioning state
-        std::thread::sleep(Duration::from_millis(500));
+        self.clock.sleep(Duration::from_millis(500));
         State::Attacking(mob)
     }
 
@@ -472,7 +646,7 @@ impl FarmingBehavior<'_> {
             if let Some(marker) = image.identify_target_marker(false) {
                 self.avoided_bounds.push((
                     marker.bounds.grow_by(self.already_attack_count * 10),
-                    Instant::now(),
+                    self.clock.now(),
                     2000,
                 ));
                 self.already_attack_count += 1;
@@ -487,10 +661,59 @@ impl FarmingBehavior<'_> {
         State::SearchingForEnemy
     }
 
-    fn avoid_obstacle(&mut self, image: &mut ImageAnalyzer, max_avoid: u32) -> bool {
+    /// Unselect a mob we don't want and avoid it while it's still around.
+    fn skip_target(&mut self, mob: Target) -> State {
+        use crate::movement::prelude::*;
+        self.is_attacking = false;
+
+        if let Some(id) = mob.track_id {
+            self.avoided_tracks.push((id, self.clock.now(), 10000));
+        } else {
+            // Grow enough to cover the attack coords below the name
+            self.avoided_bounds
+                .push((mob.bounds.grow_by(40), self.clock.now(), 10000));
+        }
+        self.avoid_last_click();
+        play!(self.movement => [
+            PressKey("Escape"),
+        ]);
+        State::SearchingForEnemy
+    }
+
+    /// Turn around and run away.
+    fn flee(&mut self) -> State {
+        use crate::movement::prelude::*;
+        slog::info!(self.logger, "Fleeing from giant");
+        self.is_attacking = false;
+
+        // Takes over whatever movement is playing
+        self.movement
+            .start(
+                [
+                    PressKey("Escape"),
+                    HoldKeys(vec!["W", "Space"]),
+                    HoldKeyFor("A", dur::Fixed(1000)),
+                    Wait(dur::Fixed(3000)),
+                    ReleaseKeys(vec!["Space", "W"]),
+                ],
+                MovementPriority::Urgent,
+            )
+            .wait();
+        State::SearchingForEnemy
+    }
+
+    fn avoid_obstacle(
+        &mut self,
+        config: &FarmingConfig,
+        image: &mut ImageAnalyzer,
+        max_avoid: u32,
+    ) -> bool {
         if self.obstacle_avoidance_count < max_avoid {
             use crate::movement::prelude::*;
-            if self.obstacle_avoidance_count == 0 {
+            if let Some(movement) = self.start_sequence(config.obstacle_avoidance_sequence()) {
+                // Play the unstuck routine of the profile instead
+                movement.wait();
+            } else if self.obstacle_avoidance_count == 0 {
                 play!(self.movement => [
                     PressKey("Z"),
                     HoldKeys(vec!["W", "Space"]),
@@ -509,7 +732,10 @@ impl FarmingBehavior<'_> {
                 ]);
             }
 
-            image.client_stats.target_hp.reset_last_update_time();
+            image
+                .client_stats
+                .target_hp
+                .reset_last_update_time(self.clock);
             self.obstacle_avoidance_count += 1;
             false
         } else {
@@ -533,14 +759,8 @@ impl FarmingBehavior<'_> {
             || image.client_stats.target_mp.value > 0
             || image.client_stats.target_hp.value > 0;
         if let Some(target) = image.identify_target_marker(false) {
-            draw_bounds_rect(
-                self.window,
-                target.bounds.x,
-                target.bounds.y,
-                target.bounds.w,
-                target.bounds.h,
-                Color::new(200, 100, 200),
-            );
+            self.input
+                .draw_bounds(target.bounds, Color::new(200, 100, 200));
             // println!("x:{} y:{}, w:{} h:{}", target.bounds.x, target.bounds.y, target.bounds.w, target.bounds.h);
         }
         // println!("is_mob_alive:{} mp.value:{} hp.value:{}", is_mob_alive, image.client_stats.target_mp.value, image.client_stats.target_hp.value);
@@ -551,12 +771,31 @@ impl FarmingBehavior<'_> {
                 return State::SearchingForEnemy;
             } else if is_mob {
                 self.rotation_movement_tries = 0;
+
+                // Skip giants we shouldn't fight, and mobs filtered out by name or level
+                let allow_giants = config
+                    .giant_policy()
+                    .allows_attack(image.client_stats.hp.value);
+                if mob.rank == MobRank::Giant && !allow_giants {
+                    slog::info!(self.logger, "Skipping giant target");
+                    return self.skip_target(mob);
+                }
+                if config.has_target_filters() {
+                    if let Some(info) = image.read_target_info() {
+                        let is_giant = config.is_giant_name(&info.name);
+                        if !config.is_target_allowed(&info) || (is_giant && !allow_giants) {
+                            slog::info!(self.logger, "Skipping filtered target"; "name" => &info.name, "level" => info.level);
+                            return self.skip_target(mob);
+                        }
+                    }
+                }
+
                 let hp_last_update = image.client_stats.hp.last_update_time.unwrap();
 
                 // Detect if mob was attacked
                 if image.client_stats.target_hp.value < 100 && config.prevent_already_attacked() {
                     // If we didn't took any damages abort attack
-                    if hp_last_update.elapsed().as_millis() > 5000 {
+                    if self.clock.elapsed(hp_last_update).as_millis() > 5000 {
                         println!("2");
                         return self.abort_attack(image);
                     } else if self.stealed_target_count > 5 {
@@ -577,19 +816,16 @@ impl FarmingBehavior<'_> {
             // Engagin combat
             if !self.is_attacking {
                 self.obstacle_avoidance_count = 0;
-                self.last_initial_attack_time = Instant::now();
+                self.last_initial_attack_time = self.clock.now();
                 self.is_attacking = true;
                 self.already_attack_count = 0;
             }
             // Use buffs only when target is found so we don't waste them
-            self.check_buffs(config);
+            self.check_buffs(config, image);
 
-            let last_target_hp_update = image
-                .client_stats
-                .target_hp
-                .last_update_time
-                .unwrap()
-                .elapsed()
+            let last_target_hp_update = self
+                .clock
+                .elapsed(image.client_stats.target_hp.last_update_time.unwrap())
                 .as_millis();
 
             // Obstacle avoidance
@@ -597,10 +833,10 @@ impl FarmingBehavior<'_> {
                 || last_target_hp_update > config.obstacle_avoidance_cooldown()
             {
                 if image.client_stats.target_hp.value == 100 {
-                    if self.avoid_obstacle(image, 2) {
+                    if self.avoid_obstacle(config, image, 2) {
                         return State::SearchingForEnemy;
                     }
-                } else if self.avoid_obstacle(image, config.obstacle_avoidance_max_try()) {
+                } else if self.avoid_obstacle(config, image, config.obstacle_avoidance_max_try()) {
                     return State::SearchingForEnemy;
                 }
             }
@@ -632,11 +868,11 @@ impl FarmingBehavior<'_> {
 
     fn after_enemy_kill_debug(&mut self, frontend_info: &mut FrontendInfo) {
         // Let's introduce some stats
-        let started_elapsed = self.start_time.elapsed();
+        let started_elapsed = self.clock.elapsed(self.start_time);
         let started_formatted = DateTime::format_time(started_elapsed);
 
-        let elapsed_time_to_kill = self.last_initial_attack_time.elapsed();
-        let elapsed_search_time = self.last_kill_time.elapsed() - elapsed_time_to_kill;
+        let elapsed_time_to_kill = self.clock.elapsed(self.last_initial_attack_time);
+        let elapsed_search_time = self.clock.elapsed(self.last_kill_time) - elapsed_time_to_kill;
 
         let search_time_as_secs = {
             if self.kill_count > 0 {
@@ -671,6 +907,19 @@ impl FarmingBehavior<'_> {
         )
     }
 
+    fn update_experience(&mut self, frontend_info: &mut FrontendInfo, image: &ImageAnalyzer) {
+        if let Some(exp) = image.read_experience() {
+            let now = self.clock.now();
+            if self.experience.update(exp, now) {
+                slog::info!(self.logger, "Level up"; "exp" => exp);
+            }
+
+            let stats = self.experience.stats(now);
+            slog::debug!(self.logger, "Experience"; "exp" => stats.current, "gained" => stats.gained, "per hour" => stats.per_hour);
+            frontend_info.set_exp_stats(stats);
+        }
+    }
+
     fn after_enemy_kill(
         &mut self,
         frontend_info: &mut FrontendInfo,
@@ -682,7 +931,10 @@ impl FarmingBehavior<'_> {
         self.after_enemy_kill_debug(frontend_info);
 
         self.stealed_target_count = 0;
-        self.last_kill_time = Instant::now();
+        self.last_kill_time = self.clock.now();
+
+        // Read experience gained from this kill
+        self.update_experience(frontend_info, image);
 
         // Pickup items
         self.pickup_items(config, image);
@@ -691,3 +943,205 @@ impl FarmingBehavior<'_> {
         State::SearchingForEnemy
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::{FarmingBehavior, State};
+    use crate::{
+        behavior::simulation::{
+            clicks, farming_config, pressed_keys, Scene, SceneMob, SimulationEnv,
+        },
+        data::{Bounds, GameEvent, MobType},
+    };
+
+    #[test]
+    fn test_kill_cycle() {
+        let env = SimulationEnv::new();
+        let mut sim = env.simulate::<FarmingBehavior>(farming_config(json!({})));
+        let mob = Scene::default().with_mob(MobType::Passive, 380, 250);
+
+        sim.step(&mob);
+        assert!(matches!(sim.behavior.state, State::EnemyFound(_)));
+
+        let inputs = sim.step(&mob);
+        assert!(matches!(sim.behavior.state, State::Attacking(_)));
+        assert_eq!(clicks(&inputs), vec![(399, 264).into()]);
+
+        for hp in [100, 60, 20] {
+            sim.step(&mob.clone().with_target(hp, 100));
+            assert!(matches!(sim.behavior.state, State::Attacking(_)));
+            assert!(sim.behavior.is_attacking);
+        }
+
+        sim.step(&Scene::default());
+        assert!(matches!(sim.behavior.state, State::AfterEnemyKill(_)));
+
+        sim.step(&Scene::default());
+        assert!(matches!(sim.behavior.state, State::SearchingForEnemy));
+        assert_eq!(sim.behavior.kill_count, 1);
+    }
+
+    #[test]
+    fn test_obstacle_avoidance_gives_up() {
+        let env = SimulationEnv::new();
+        let mut sim = env.simulate::<FarmingBehavior>(farming_config(json!({})));
+        let mob = Scene::default().with_mob(MobType::Passive, 380, 250);
+        let stuck = mob.clone().with_target(100, 100);
+
+        sim.step(&mob);
+        sim.step(&mob);
+        sim.step(&stuck);
+        assert!(matches!(sim.behavior.state, State::Attacking(_)));
+
+        // Target HP never moves, so the bot tries to get around an obstacle twice then gives up
+        let mut keys = vec![];
+        for _ in 0..30 {
+            sim.wait(Duration::from_secs(1));
+            let inputs = sim.step(&stuck);
+            keys.extend(pressed_keys(&inputs).into_iter().map(str::to_string));
+            if matches!(sim.behavior.state, State::SearchingForEnemy) {
+                break;
+            }
+        }
+        assert!(matches!(sim.behavior.state, State::SearchingForEnemy));
+        assert_eq!(keys, vec!["Z", "Z", "Escape"]);
+
+        // The unreachable mob is not clicked again right away
+        let inputs = sim.step(&mob);
+        assert!(clicks(&inputs).is_empty());
+        assert!(matches!(sim.behavior.state, State::SearchingForEnemy));
+    }
+
+    #[test]
+    fn test_already_attacked_abort() {
+        let env = SimulationEnv::new();
+        let mut sim = env.simulate::<FarmingBehavior>(farming_config(json!({})));
+        let mob = Scene::default().with_mob(MobType::Passive, 380, 250);
+
+        sim.step(&mob);
+        sim.step(&mob);
+
+        // Someone else is damaging the target while our HP has not changed for a while
+        sim.wait(Duration::from_secs(6));
+        let inputs = sim.step(&mob.clone().with_target(80, 100));
+        assert!(matches!(sim.behavior.state, State::SearchingForEnemy));
+        assert!(!sim.behavior.is_attacking);
+        assert_eq!(pressed_keys(&inputs), vec!["Escape"]);
+
+        let inputs = sim.step(&mob);
+        assert!(clicks(&inputs).is_empty());
+    }
+
+    #[test]
+    fn test_cannot_attack_event() {
+        let env = SimulationEnv::new();
+        let mut sim = env.simulate::<FarmingBehavior>(farming_config(json!({})));
+        let mob = Scene::default().with_mob(MobType::Passive, 380, 250);
+
+        sim.step(&mob);
+        sim.step(&mob);
+        assert!(matches!(sim.behavior.state, State::Attacking(_)));
+
+        let inputs = sim.send_event(GameEvent::CannotAttack);
+        assert!(matches!(sim.behavior.state, State::SearchingForEnemy));
+        assert_eq!(pressed_keys(&inputs), vec!["Escape"]);
+
+        // The skipped mob isn't clicked again
+        sim.step(&mob);
+        let inputs = sim.step(&mob);
+        assert!(clicks(&inputs).is_empty());
+
+        // Pickups pause while the inventory is full
+        sim.send_event(GameEvent::InventoryFull);
+        assert!(sim.behavior.inventory_full);
+        sim.send_event(GameEvent::ItemObtained("Penya".to_string()));
+        assert!(!sim.behavior.inventory_full);
+    }
+
+    #[test]
+    fn test_giant_policy() {
+        let mut giant = Scene::default();
+        giant.mobs.push(SceneMob {
+            mob_type: MobType::Passive,
+            bounds: Bounds::new(380, 250, 70, 5),
+        });
+        let clicks_giant = |policy: &str, hp: u32| {
+            let env = SimulationEnv::new();
+            let config = json!({ "giant_policy": policy, "giant_min_name_width": 60 });
+            let mut sim = env.simulate::<FarmingBehavior>(farming_config(config));
+            let scene = Scene {
+                hp,
+                ..giant.clone()
+            };
+            sim.step(&scene);
+            !clicks(&sim.step(&scene)).is_empty()
+        };
+
+        assert!(clicks_giant("Attack", 100));
+        assert!(!clicks_giant("Avoid", 100));
+        assert!(clicks_giant("AttackAtFullHp", 100));
+        assert!(!clicks_giant("AttackAtFullHp", 50));
+    }
+
+    #[test]
+    fn test_flee_from_giant() {
+        let env = SimulationEnv::new();
+        let config = json!({ "giant_policy": "FleeIfAggroed", "giant_min_name_width": 60 });
+        let giant_at = |x, y, hp| {
+            let mut scene = Scene {
+                hp,
+                ..Scene::default()
+            };
+            scene.mobs.push(SceneMob {
+                mob_type: MobType::Passive,
+                bounds: Bounds::new(x, y, 70, 5),
+            });
+            scene
+        };
+
+        // Hurt by a passive mob while a giant idles far away, keeps farming
+        let mut sim = env.simulate::<FarmingBehavior>(farming_config(config.clone()));
+        let inputs = sim.step(&giant_at(60, 480, 80).with_mob(MobType::Passive, 380, 250));
+        assert!(!pressed_keys(&inputs).contains(&"Escape"));
+        assert!(matches!(sim.behavior.state, State::EnemyFound(_)));
+
+        // Hurt while a giant walks up to us, runs away
+        let mut sim = env.simulate::<FarmingBehavior>(farming_config(config));
+        assert!(!pressed_keys(&sim.step(&giant_at(100, 450, 100))).contains(&"Escape"));
+        assert!(!pressed_keys(&sim.step(&giant_at(130, 435, 100))).contains(&"Escape"));
+        assert!(pressed_keys(&sim.step(&giant_at(160, 420, 90))).contains(&"Escape"));
+    }
+
+    #[test]
+    fn test_slot_cooldown() {
+        let unused = json!({ "slot_type": "Unused", "slot_enabled": true });
+        let mut slots = vec![unused.clone(); 10];
+        slots[0] = json!({
+            "slot_type": "Pill",
+            "slot_cooldown": 5000,
+            "slot_threshold": 50,
+            "slot_enabled": true,
+        });
+        let mut slot_bars = vec![json!({ "slots": vec![unused; 10] }); 9];
+        slot_bars[0] = json!({ "slots": slots });
+
+        let env = SimulationEnv::new();
+        let config = farming_config(json!({ "slot_bars": slot_bars }));
+        let mut sim = env.simulate::<FarmingBehavior>(config);
+        let hurt = Scene {
+            hp: 40,
+            ..Scene::default()
+        };
+
+        assert_eq!(pressed_keys(&sim.step(&hurt)), vec!["F1", "0"]);
+        assert!(pressed_keys(&sim.step(&hurt)).is_empty());
+
+        // Fast-forward past the pill cooldown
+        sim.wait(Duration::from_secs(5));
+        assert_eq!(pressed_keys(&sim.step(&hurt)), vec!["F1", "0"]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+
+use guard::guard;
+use slog::Logger;
+
+use super::Behavior;
+use crate::{
+    data::Target,
+    image_analyzer::ImageAnalyzer,
+    ipc::{BotConfig, FrontendInfo},
+    movement::MovementAccessor,
+    platform::{InputSink, KeyMode},
+    script::{Script, ScriptAction, ScriptRunner, ScriptValues},
+    utils::Clock,
+};
+
+pub struct ScriptedBehavior<'a> {
+    logger: &'a Logger,
+    movement: &'a MovementAccessor,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
+    /// Source of the running script
+    script: String,
+    runner: Option<ScriptRunner>,
+    /// Keys held by the script until it releases them
+    held_keys: Vec<String>,
+}
+
+impl<'a> Behavior<'a> for ScriptedBehavior<'a> {
+    fn new(
+        logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
+        Self {
+            logger,
+            movement,
+            input,
+            clock,
+            script: String::new(),
+            runner: None,
+            held_keys: vec![],
+        }
+    }
+
+    fn start(&mut self, config: &BotConfig) {
+        self.update(config);
+        if let Some(runner) = self.runner.as_mut() {
+            runner.restart();
+        }
+    }
+
+    fn update(&mut self, config: &BotConfig) {
+        let script = config.script_config().script();
+        if script == self.script {
+            return;
+        }
+
+        self.script = script.to_string();
+        self.runner = match Script::parse(script) {
+            Ok(script) => Some(ScriptRunner::new(script)),
+            Err(error) => {
+                slog::error!(self.logger, "Invalid script"; "error" => error.to_string());
+                None
+            }
+        };
+    }
+
+    fn stop(&mut self, _config: &BotConfig) {
+        for key in self.held_keys.drain(..) {
+            self.input.send_key(&key, KeyMode::Release);
+        }
+    }
+
+    fn run_iteration(
+        &mut self,
+        _frontend_info: &mut FrontendInfo,
+        config: &BotConfig,
+        image: &mut ImageAnalyzer,
+    ) {
+        guard!(let Some(runner) = self.runner.as_mut() else { return });
+
+        let mobs = image.identify_mobs(&config.farming_config().mob_name_settings());
+        let stats = &image.client_stats;
+        let values = ScriptValues {
+            hp: stats.hp.value,
+            mp: stats.mp.value,
+            fp: stats.fp.value,
+            target_hp: stats.target_hp.value,
+            target_mp: stats.target_mp.value,
+            mobs: mobs.len() as u32,
+            target: image.identify_target_marker(false).is_some(),
+        };
+
+        for action in runner.step(&values, self.clock.now()) {
+            self.play(config, image, &mobs, &action);
+        }
+    }
+
+    fn describe_state(&self) -> String {
+        match &self.runner {
+            Some(runner) if runner.is_stopped() => "Stopped".to_string(),
+            Some(runner) => format!("Line {}", runner.line().unwrap_or_default()),
+            None => "NoScript".to_string(),
+        }
+    }
+}
+
+impl ScriptedBehavior<'_> {
+    fn play(
+        &mut self,
+        config: &BotConfig,
+        image: &ImageAnalyzer,
+        mobs: &[Target],
+        action: &ScriptAction,
+    ) {
+        match action {
+            ScriptAction::HoldKey(key, None) if !self.held_keys.contains(key) => {
+                self.held_keys.push(key.clone());
+            }
+            ScriptAction::ReleaseKey(key) => self.held_keys.retain(|held| held != key),
+            _ => {}
+        }
+
+        if let Some(movement) = action.movement() {
+            self.movement.play([movement.clone()]);
+            return;
+        }
+
+        match action {
+            ScriptAction::Slot(slot_bar, slot) => self.input.send_slot(*slot_bar, *slot),
+            ScriptAction::UseSlot(slot_type) => {
+                match config.farming_config().slot_index(*slot_type) {
+                    Some((slot_bar, slot)) => self.input.send_slot(slot_bar, slot),
+                    None => {
+                        slog::warn!(self.logger, "No slot to use"; "slot_type" => slot_type.to_string())
+                    }
+                }
+            }
+            ScriptAction::Attack => {
+                let center = image.screen_center();
+                let closest = mobs.iter().map(Target::get_attack_coords).min_by(|a, b| {
+                    let (a, b) = (a.distance_to(&center), b.distance_to(&center));
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                });
+                if let Some(point) = closest {
+                    self.input.mouse_click(point);
+                }
+            }
+            ScriptAction::PlaySequence(name) => match self.movement.play_sequence(name) {
+                Some(movement) => movement.wait(),
+                None => slog::warn!(self.logger, "Unknown movement sequence"; "name" => name),
+            },
+            ScriptAction::Log(message) => slog::info!(self.logger, "Script"; "message" => message),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScriptedBehavior;
+    use crate::{
+        behavior::{
+            simulation::{clicks, pressed_keys, Scene, SimulationEnv},
+            Behavior,
+        },
+        data::MobType,
+        ipc::BotConfig,
+        platform::{InputAction, KeyMode},
+    };
+
+    #[test]
+    fn test_scripted_inputs() {
+        let config: BotConfig = serde_json::from_value(serde_json::json!({
+            "change_id": 0,
+            "is_running": true,
+            "mode": "Scripted",
+            "farming_config": {},
+            "support_config": {},
+            "shout_config": {},
+            "script_config": {
+                "script": "
+                    hold W
+                    loop
+                        if hp < 50
+                            slot 1 2
+                        end
+                        if mobs > 0 and not target
+                            attack
+                        end
+                    end"
+            },
+        }))
+        .unwrap();
+
+        let env = SimulationEnv::new();
+        let mut sim = env.simulate::<ScriptedBehavior>(config);
+        let inputs = sim.step(&Scene::default());
+        assert!(inputs.contains(&InputAction::Key("W".to_string(), KeyMode::Hold)));
+        assert!(pressed_keys(&inputs).is_empty());
+
+        let scene = Scene {
+            hp: 40,
+            ..Scene::default().with_mob(MobType::Passive, 200, 200)
+        };
+        let inputs = sim.step(&scene);
+        assert_eq!(pressed_keys(&inputs), vec!["F1", "2"]);
+        assert_eq!(clicks(&inputs).len(), 1);
+
+        // A selected mob isn't clicked again
+        let scene = Scene::default()
+            .with_mob(MobType::Passive, 200, 200)
+            .with_target(80, 100);
+        let inputs = sim.step(&scene);
+        assert!(clicks(&inputs).is_empty());
+
+        // Held keys are released when the bot stops
+        let config = sim.config.clone();
+        sim.behavior.stop(&config);
+        assert_eq!(
+            env.input.actions(),
+            vec![InputAction::Key("W".to_string(), KeyMode::Release)]
+        );
+    }
+}
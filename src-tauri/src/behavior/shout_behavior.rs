@@ -2,14 +2,15 @@ use std::time::Instant;
 
 use guard::guard;
 use slog::Logger;
-use tauri::Window;
 
 use super::Behavior;
 use crate::{
     image_analyzer::ImageAnalyzer,
     ipc::{BotConfig, FrontendInfo, ShoutConfig},
     movement::MovementAccessor,
+    platform::InputSink,
     play,
+    utils::Clock,
 };
 
 #[allow(dead_code)]
@@ -17,7 +18,8 @@ pub struct ShoutBehavior<'a> {
     rng: rand::rngs::ThreadRng,
     logger: &'a Logger,
     movement: &'a MovementAccessor,
-    window: &'a Window,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
     last_shout_time: Instant,
     shown_messages: Vec<String>,
     shout_interval: u64,
@@ -25,13 +27,19 @@ pub struct ShoutBehavior<'a> {
 }
 
 impl<'a> Behavior<'a> for ShoutBehavior<'a> {
-    fn new(logger: &'a Logger, movement: &'a MovementAccessor, window: &'a Window) -> Self {
+    fn new(
+        logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
         Self {
             logger,
             movement,
-            window,
+            input,
+            clock,
             rng: rand::thread_rng(),
-            last_shout_time: Instant::now(),
+            last_shout_time: clock.now(),
             shown_messages: Vec::new(),
             shout_interval: 30000,
             message_iter: None,
@@ -69,11 +77,7 @@ impl ShoutBehavior<'_> {
         use crate::movement::prelude::*;
 
         // Return early if time since last shout is less than shout interval
-        if Instant::now()
-            .duration_since(self.last_shout_time)
-            .as_millis()
-            < self.shout_interval as u128
-        {
+        if self.clock.elapsed(self.last_shout_time).as_millis() < self.shout_interval as u128 {
             return;
         }
 
@@ -109,6 +113,51 @@ impl ShoutBehavior<'_> {
         ]);
 
         // Update last shout time
-        self.last_shout_time = Instant::now();
+        self.last_shout_time = self.clock.now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ShoutBehavior;
+    use crate::{
+        behavior::simulation::{Scene, SimulationEnv},
+        ipc::BotConfig,
+        platform::{InputAction, KeyMode},
+    };
+
+    #[test]
+    fn test_shout_inputs() {
+        let config: BotConfig = serde_json::from_str(
+            r#"{
+                "change_id": 0,
+                "is_running": true,
+                "mode": "AutoShout",
+                "farming_config": {},
+                "support_config": {},
+                "shout_config": { "shout_interval": 30000, "shout_messages": ["WTS stuff"] }
+            }"#,
+        )
+        .unwrap();
+
+        let env = SimulationEnv::new();
+        let mut sim = env.simulate::<ShoutBehavior>(config);
+        assert_eq!(sim.step(&Scene::default()), vec![InputAction::ClearOverlay]);
+
+        // Fast-forward to the next shout
+        sim.wait(Duration::from_secs(30));
+        assert_eq!(
+            sim.step(&Scene::default()),
+            vec![
+                InputAction::ClearOverlay,
+                InputAction::Key("Enter".to_string(), KeyMode::Press),
+                InputAction::Message("WTS stuff".to_string()),
+                InputAction::Key("Enter".to_string(), KeyMode::Press),
+                InputAction::Key("Escape".to_string(), KeyMode::Press),
+            ]
+        );
+        assert_eq!(sim.step(&Scene::default()), vec![InputAction::ClearOverlay]);
     }
 }
@@ -0,0 +1,297 @@
+//! Headless harness running behaviors against scripted scenes.
+//!
+//! Every step renders a synthetic client frame from a `Scene`, feeds it through the
+//! `ImageAnalyzer` and runs one behavior iteration on a virtual clock, so tests can
+//! assert state transitions and emitted inputs without a client or real waiting.
+
+use std::{sync::Arc, time::Duration};
+
+use image::{Rgba, RgbaImage};
+use libscreenshot::ImageBuffer;
+use slog::Logger;
+
+use super::Behavior;
+use crate::{
+    data::{Bounds, GameEvent, MobType, Point},
+    image_analyzer::{ImageAnalyzer, QueuedFrameSource},
+    ipc::{BotConfig, FrontendInfo},
+    movement::MovementAccessor,
+    platform::{InputAction, InputSink, KeyMode, RecordedInput, RecordingInputSink},
+    utils::VirtualClock,
+};
+
+const FRAME_WIDTH: u32 = 800;
+const FRAME_HEIGHT: u32 = 600;
+
+const HP_COLOR: [u8; 3] = [174, 18, 55];
+const MP_COLOR: [u8; 3] = [20, 84, 196];
+const FP_COLOR: [u8; 3] = [45, 230, 29];
+const PASSIVE_NAME_COLOR: [u8; 3] = [234, 234, 149];
+const AGGRESSIVE_NAME_COLOR: [u8; 3] = [179, 23, 23];
+const TARGET_MARKER_COLOR: [u8; 3] = [246, 90, 106];
+
+/// A mob name rendered in the scene.
+#[derive(Debug, Clone, Copy)]
+pub struct SceneMob {
+    pub mob_type: MobType,
+    pub bounds: Bounds,
+}
+
+/// The currently selected target.
+#[derive(Debug, Clone, Copy)]
+pub struct SceneTarget {
+    pub hp: u32,
+    pub mp: u32,
+    pub marker: bool,
+}
+
+/// Snapshot of the game world rendered into a single frame.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub hp: u32,
+    pub mp: u32,
+    pub fp: u32,
+    pub target: Option<SceneTarget>,
+    pub mobs: Vec<SceneMob>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            hp: 100,
+            mp: 100,
+            fp: 100,
+            target: None,
+            mobs: vec![],
+        }
+    }
+}
+
+impl Scene {
+    /// Add a mob whose name is drawn at the given position.
+    pub fn with_mob(mut self, mob_type: MobType, x: u32, y: u32) -> Self {
+        self.mobs.push(SceneMob {
+            mob_type,
+            bounds: Bounds::new(x, y, 40, 5),
+        });
+        self
+    }
+
+    /// Select a target with the given HP and MP in percent and show its marker.
+    pub fn with_target(mut self, hp: u32, mp: u32) -> Self {
+        self.target = Some(SceneTarget {
+            hp,
+            mp,
+            marker: true,
+        });
+        self
+    }
+
+    /// Render the scene into a frame the analyzer can read.
+    pub fn render(&self) -> ImageBuffer {
+        let mut frame = RgbaImage::from_pixel(FRAME_WIDTH, FRAME_HEIGHT, Rgba([0, 0, 0, 255]));
+
+        // Character stat tray
+        Self::draw_bar(&mut frame, 110, 40, self.hp, HP_COLOR);
+        Self::draw_bar(&mut frame, 110, 50, self.mp, MP_COLOR);
+        Self::draw_bar(&mut frame, 110, 60, self.fp, FP_COLOR);
+
+        if let Some(target) = self.target {
+            Self::draw_bar(&mut frame, 320, 35, target.hp, HP_COLOR);
+            Self::draw_bar(&mut frame, 320, 52, target.mp, MP_COLOR);
+            if target.marker {
+                Self::fill(
+                    &mut frame,
+                    Bounds::new(394, 200, 12, 6),
+                    TARGET_MARKER_COLOR,
+                );
+            }
+        }
+
+        for mob in &self.mobs {
+            let color = match mob.mob_type {
+                MobType::Passive => PASSIVE_NAME_COLOR,
+                MobType::Aggressive => AGGRESSIVE_NAME_COLOR,
+            };
+            Self::fill(&mut frame, mob.bounds, color);
+        }
+
+        frame
+    }
+
+    /// Draw a bar whose detected width equals `percent` pixels.
+    fn draw_bar(frame: &mut RgbaImage, x: u32, y: u32, percent: u32, color: [u8; 3]) {
+        if percent > 0 {
+            Self::fill(frame, Bounds::new(x, y, percent.min(100) + 1, 3), color);
+        }
+    }
+
+    fn fill(frame: &mut RgbaImage, bounds: Bounds, color: [u8; 3]) {
+        for y in bounds.y..bounds.y + bounds.h {
+            for x in bounds.x..bounds.x + bounds.w {
+                frame.put_pixel(x, y, Rgba([color[0], color[1], color[2], 255]));
+            }
+        }
+    }
+}
+
+/// Everything behaviors borrow for their whole lifetime.
+pub struct SimulationEnv {
+    pub logger: Logger,
+    pub clock: Arc<VirtualClock>,
+    pub input: Arc<RecordingInputSink>,
+    pub movement: MovementAccessor,
+}
+
+impl SimulationEnv {
+    pub fn new() -> Self {
+        let clock = Arc::new(VirtualClock::new());
+        let input = Arc::new(RecordingInputSink::with_clock(clock.clone()));
+        let movement = MovementAccessor::new(input.clone(), clock.clone());
+
+        Self {
+            logger: Logger::root(slog::Discard, slog::o!()),
+            clock,
+            input,
+            movement,
+        }
+    }
+
+    /// Create a behavior wired to this environment and a simulation driving it.
+    pub fn simulate<'a, B>(&'a self, config: BotConfig) -> Simulation<'a, B>
+    where
+        B: Behavior<'a>,
+    {
+        let frames = QueuedFrameSource::default();
+        let analyzer = ImageAnalyzer::new(frames.clone(), self.clock.clone());
+        let mut behavior = B::new(&self.logger, &self.movement, &*self.input, &*self.clock);
+        behavior.start(&config);
+
+        let mut simulation = Simulation {
+            env: self,
+            behavior,
+            analyzer,
+            frontend_info: FrontendInfo::default(),
+            config,
+            frames,
+            tick: Duration::from_millis(100),
+        };
+        simulation.calibrate();
+        simulation
+    }
+}
+
+impl Default for SimulationEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drives a single behavior frame by frame.
+pub struct Simulation<'a, B> {
+    pub env: &'a SimulationEnv,
+    pub behavior: B,
+    pub analyzer: ImageAnalyzer,
+    pub frontend_info: FrontendInfo,
+    pub config: BotConfig,
+    frames: QueuedFrameSource,
+    tick: Duration,
+}
+
+impl<'a, B> Simulation<'a, B>
+where
+    B: Behavior<'a>,
+{
+    /// Run one iteration on the given scene and return the inputs it emitted.
+    pub fn step(&mut self, scene: &Scene) -> Vec<InputAction> {
+        self.step_timed(scene)
+            .into_iter()
+            .map(|input| input.action)
+            .collect()
+    }
+
+    /// Same as `step`, keeping the time every input was sent at.
+    pub fn step_timed(&mut self, scene: &Scene) -> Vec<RecordedInput> {
+        self.env.clock.advance(self.tick);
+        self.env.input.take();
+
+        // Same order as the main loop
+        self.capture(scene);
+        self.env.input.clear_overlay();
+        self.analyzer.update_client_stats(&self.env.logger);
+        self.analyzer.update_life_state(&*self.env.input);
+        for event in self.analyzer.read_game_events() {
+            self.behavior.on_game_event(&self.config, &event);
+        }
+        self.behavior
+            .run_iteration(&mut self.frontend_info, &self.config, &mut self.analyzer);
+
+        self.env.input.take()
+    }
+
+    /// Publish a system message to the behavior as if it was read from the toast area.
+    pub fn send_event(&mut self, event: GameEvent) -> Vec<InputAction> {
+        self.env.input.take();
+        self.behavior.on_game_event(&self.config, &event);
+        self.env
+            .input
+            .take()
+            .into_iter()
+            .map(|input| input.action)
+            .collect()
+    }
+
+    /// Let time pass without running the behavior.
+    pub fn wait(&self, duration: Duration) {
+        self.env.clock.advance(duration);
+    }
+
+    /// Show every bar at its full width once so the analyzer learns their sizes.
+    fn calibrate(&mut self) {
+        for scene in [Scene::default().with_target(100, 100), Scene::default()] {
+            self.capture(&scene);
+            self.analyzer.update_client_stats(&self.env.logger);
+        }
+    }
+
+    fn capture(&mut self, scene: &Scene) {
+        self.frames.push(scene.render());
+        self.analyzer
+            .capture_frame(&self.env.logger, self.config.farming_config());
+    }
+}
+
+/// Keys pressed in the given inputs.
+pub fn pressed_keys(inputs: &[InputAction]) -> Vec<&str> {
+    inputs
+        .iter()
+        .filter_map(|input| match input {
+            InputAction::Key(key, KeyMode::Press) => Some(key.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Mouse clicks in the given inputs.
+pub fn clicks(inputs: &[InputAction]) -> Vec<Point> {
+    inputs
+        .iter()
+        .filter_map(|input| match input {
+            InputAction::MouseClick(point) => Some(*point),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Config running the farming mode with the given farming config.
+pub fn farming_config(farming_config: serde_json::Value) -> BotConfig {
+    serde_json::from_value(serde_json::json!({
+        "change_id": 0,
+        "is_running": true,
+        "mode": "Farming",
+        "farming_config": farming_config,
+        "support_config": {},
+        "shout_config": {},
+    }))
+    .unwrap()
+}
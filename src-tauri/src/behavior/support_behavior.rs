@@ -1,21 +1,25 @@
 use std::time::{Duration, Instant};
 
 use slog::Logger;
-use tauri::Window;
 
 use super::Behavior;
 use crate::{
-    image_analyzer::ImageAnalyzer,
+    image_analyzer::{BuffAction, ImageAnalyzer, SlotObservations},
     ipc::{BotConfig, FrontendInfo, SlotType, SupportConfig},
     movement::MovementAccessor,
-    platform::send_slot_eval,
+    platform::{InputSink, KeyMode},
     play,
+    utils::Clock,
 };
 
 pub struct SupportBehavior<'a> {
     movement: &'a MovementAccessor,
-    window: &'a Window,
+    input: &'a dyn InputSink,
+    clock: &'a dyn Clock,
     slots_usage_last_time: [[Option<Instant>; 10]; 9],
+    /// Slot bar shown since the last slot key press
+    shown_slot_bar: Option<usize>,
+    slot_observations: SlotObservations,
     last_buff_usage: Instant,
     last_jump_time: Instant,
     avoid_obstacle_direction: String,
@@ -24,13 +28,21 @@ pub struct SupportBehavior<'a> {
 }
 
 impl<'a> Behavior<'a> for SupportBehavior<'a> {
-    fn new(_logger: &'a Logger, movement: &'a MovementAccessor, window: &'a Window) -> Self {
+    fn new(
+        _logger: &'a Logger,
+        movement: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self {
         Self {
             movement,
-            window,
+            input,
+            clock,
             slots_usage_last_time: [[None; 10]; 9],
-            last_buff_usage: Instant::now(),
-            last_jump_time: Instant::now(),
+            shown_slot_bar: None,
+            slot_observations: [[None; 10]; 9],
+            last_buff_usage: clock.now(),
+            last_jump_time: clock.now(),
             avoid_obstacle_direction: "D".to_owned(),
             last_far_from_target: None,
             //is_on_flight: false,
@@ -50,8 +62,10 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
         image: &mut ImageAnalyzer,
     ) {
         let config = config.support_config();
-        let target_marker = image.identify_target_marker(true);
         self.update_slots_usage(config);
+        self.slot_observations =
+            image.slot_observations(self.shown_slot_bar, &self.slots_usage_last_time);
+        let target_marker = image.identify_target_marker(true);
 
         if image.client_stats.target_hp.value == 0 && target_marker.is_some() {
             self.get_slot_for(config, None, SlotType::RezSkill, true);
@@ -60,19 +74,19 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
         }
 
         self.check_restorations(config, image);
-        std::thread::sleep(Duration::from_millis(100));
+        self.clock.sleep(Duration::from_millis(100));
 
         if image.client_stats.target_hp.value > 0 {
             if let Some(target_marker) = target_marker {
                 let marker_distance = image.get_target_marker_distance(target_marker);
                 if marker_distance > 200 {
                     if self.last_far_from_target.is_none() {
-                        self.last_far_from_target = Some(Instant::now());
+                        self.last_far_from_target = Some(self.clock.now());
                     }
                     self.avoid_obstacle(config);
                 } else {
                     self.last_far_from_target = None;
-                    self.check_buffs(config);
+                    self.check_buffs(config, image);
                 }
             } else {
                 self.avoid_obstacle(config);
@@ -84,8 +98,16 @@ impl<'a> Behavior<'a> for SupportBehavior<'a> {
 impl SupportBehavior<'_> {
     fn avoid_obstacle(&mut self, config: &SupportConfig) {
         if let Some(last_far_from_target) = self.last_far_from_target {
-            if last_far_from_target.elapsed().as_millis() > config.obstacle_avoidance_cooldown() {
-                self.move_circle_pattern();
+            if self.clock.elapsed(last_far_from_target).as_millis()
+                > config.obstacle_avoidance_cooldown()
+            {
+                match config
+                    .obstacle_avoidance_sequence()
+                    .and_then(|name| self.movement.play_sequence(name))
+                {
+                    Some(movement) => movement.wait(),
+                    None => self.move_circle_pattern(),
+                }
             }
         } else {
             use crate::movement::prelude::*;
@@ -128,7 +150,7 @@ impl SupportBehavior<'_> {
                     .try_into();
                 if let Some(last_time) = last_time {
                     if let Ok(cooldown) = cooldown {
-                        let slot_last_time = last_time.elapsed().as_millis();
+                        let slot_last_time = self.clock.elapsed(last_time).as_millis();
                         if slot_last_time > cooldown {
                             self.slots_usage_last_time[slotbar_index][slot_index] = None;
                         }
@@ -145,15 +167,18 @@ impl SupportBehavior<'_> {
         slot_type: SlotType,
         send: bool,
     ) -> Option<(usize, usize)> {
-        if let Some(slot_index) =
-            config.get_usable_slot_index(slot_type, threshold, self.slots_usage_last_time)
-        {
+        if let Some(slot_index) = config.get_usable_slot_index(
+            slot_type,
+            threshold,
+            self.slots_usage_last_time,
+            &self.slot_observations,
+        ) {
             if send {
                 //slog::debug!(self.logger, "Slot usage"; "slot_type" => slot_type.to_string(), "value" => threshold);
                 self.send_slot(slot_index);
                 if slot_type == SlotType::HealSkill {
                     // Set last buff usage to now so that a buff doesn't try and happen immediately after a heal
-                    self.last_buff_usage = Instant::now();
+                    self.last_buff_usage = self.clock.now();
                 }
             }
 
@@ -170,8 +195,12 @@ impl SupportBehavior<'_> {
         send: bool,
     ) -> Vec<(usize, usize)> {
         let mut indexes = Vec::new();
-        let slot_indexes =
-            config.get_usable_slot_indexes(slot_type, threshold, self.slots_usage_last_time);
+        let slot_indexes = config.get_usable_slot_indexes(
+            slot_type,
+            threshold,
+            self.slots_usage_last_time,
+            &self.slot_observations,
+        );
         if !slot_indexes.is_empty() {
             for slot_index in slot_indexes {
                 if send {
@@ -186,17 +215,35 @@ impl SupportBehavior<'_> {
 
     fn send_slot(&mut self, slot_index: (usize, usize)) {
         // Send keystroke for first slot mapped to pill
-        send_slot_eval(self.window, slot_index.0, slot_index.1);
+        self.input.send_slot(slot_index.0, slot_index.1);
+        self.shown_slot_bar = Some(slot_index.0);
         // Update usage last time
-        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(Instant::now());
+        self.slots_usage_last_time[slot_index.0][slot_index.1] = Some(self.clock.now());
     }
 
-    fn check_buffs(&mut self, config: &SupportConfig) {
-        if self.last_buff_usage.elapsed().as_millis() > config.interval_between_buffs() {
-            self.last_buff_usage = Instant::now();
-            self.get_slot_for(config, None, SlotType::BuffSkill, true);
-            std::thread::sleep(Duration::from_millis(100));
+    fn check_buffs(&mut self, config: &SupportConfig, image: &mut ImageAnalyzer) {
+        if self.clock.elapsed(self.last_buff_usage).as_millis() <= config.interval_between_buffs() {
+            return;
+        }
+
+        // Only recast buffs missing from the buff tray
+        let slot_indexes = config.get_usable_slot_indexes(
+            SlotType::BuffSkill,
+            None,
+            self.slots_usage_last_time,
+            &self.slot_observations,
+        );
+        match image.next_buff_action(slot_indexes) {
+            Some(BuffAction::ShowSlotBar(slot_bar)) => {
+                self.input
+                    .send_key(&format!("F{}", slot_bar + 1), KeyMode::Press);
+                self.shown_slot_bar = Some(slot_bar);
+            }
+            Some(BuffAction::Cast(slot_index)) => self.send_slot(slot_index),
+            None => return,
         }
+        self.last_buff_usage = self.clock.now();
+        self.clock.sleep(Duration::from_millis(100));
     }
 
     fn check_restorations(&mut self, config: &SupportConfig, image: &mut ImageAnalyzer) {
@@ -1,16 +1,24 @@
 use slog::Logger;
-use tauri::Window;
 
 use crate::{
+    data::GameEvent,
     image_analyzer::ImageAnalyzer,
     ipc::{BotConfig, FrontendInfo},
     movement::MovementAccessor,
+    platform::InputSink,
+    utils::Clock,
 };
 
 pub trait Behavior<'a> {
     /// Runs on initialization
-    fn new(logger: &'a Logger, movement_accessor: &'a MovementAccessor, window: &'a Window)
-        -> Self;
+    fn new(
+        logger: &'a Logger,
+        movement_accessor: &'a MovementAccessor,
+        input: &'a dyn InputSink,
+        clock: &'a dyn Clock,
+    ) -> Self
+    where
+        Self: Sized;
 
     /// Runs on activation
     fn start(&mut self, config: &BotConfig);
@@ -28,4 +36,12 @@ pub trait Behavior<'a> {
         config: &BotConfig,
         analyzer: &mut ImageAnalyzer,
     );
+
+    /// Runs when the client shows a system message
+    fn on_game_event(&mut self, _config: &BotConfig, _event: &GameEvent) {}
+
+    /// Describes the current state for session recordings
+    fn describe_state(&self) -> String {
+        String::new()
+    }
 }
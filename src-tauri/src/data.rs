@@ -1,17 +1,38 @@
 mod target;
-pub use self::target::{MobType, Target, TargetType};
+pub use self::target::{MobNameSettings, MobRank, MobType, Target, TargetInfo, TargetType};
 
 mod bounds;
 pub use self::bounds::Bounds;
 
+mod connection;
+pub use self::connection::{ConnectionRecovery, ConnectionState, RecoveryAction, RecoverySettings};
+
+mod experience;
+pub use self::experience::{ExperienceStats, ExperienceTracker};
+
+mod frame_layout;
+pub use self::frame_layout::{Anchor, FrameLayout};
+
+mod game_event;
+pub use self::game_event::GameEvent;
+
+mod mob_tracker;
+pub use self::mob_tracker::{MobTracker, TrackId};
+
 mod point;
 pub use self::point::Point;
 
 mod point_cloud;
-pub use self::point_cloud::{point_selector, PointCloud};
+pub use self::point_cloud::PointCloud;
 
 mod stats_info;
-pub use self::stats_info::{ClientStats, StatInfo, StatusBarConfig, StatusBarKind};
+pub use self::stats_info::{ClientStats, LifeState, StatInfo, StatusBarConfig, StatusBarKind};
+
+mod minimap_info;
+pub use self::minimap_info::MinimapInfo;
+
+mod world_point;
+pub use self::world_point::WorldPoint;
 
 mod pixel_detection;
 pub use self::pixel_detection::{PixelDetection, PixelDetectionConfig, PixelDetectionKind};
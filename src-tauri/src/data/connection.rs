@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Which screen the client is showing.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionState {
+    #[default]
+    InGame,
+    Disconnected,
+    LoginScreen,
+    CharacterSelect,
+    Loading,
+    ServerDown,
+}
+
+/// What to do to get back in game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// In game, nothing to recover
+    None,
+    /// Back in game after the connection was lost
+    Restored,
+    /// Waiting for the screen to change or for the back-off delay
+    Wait,
+    /// Close the disconnect dialog or log in
+    PressEnter,
+    SelectCharacter(u32),
+    /// The retry limit was just reached
+    GiveUp,
+}
+
+/// Settings of the recovery sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoverySettings {
+    pub enabled: bool,
+    pub character_slot: u32,
+    pub max_attempts: u32,
+    /// Delay after the first attempt, doubled after each failed one
+    pub delay: Duration,
+    pub max_delay: Duration,
+    /// Loading for longer than this counts as a failed attempt
+    pub load_timeout: Duration,
+}
+
+/// Drives the way back in game once the connection is lost.
+#[derive(Debug, Default, Clone)]
+pub struct ConnectionRecovery {
+    lost_since: Option<Instant>,
+    state: ConnectionState,
+    state_since: Option<Instant>,
+    attempts: u32,
+    next_attempt: Option<Instant>,
+    gave_up: bool,
+}
+
+impl ConnectionRecovery {
+    /// Pick the next recovery step for the detected screen.
+    pub fn update(
+        &mut self,
+        state: ConnectionState,
+        settings: &RecoverySettings,
+        now: Instant,
+    ) -> RecoveryAction {
+        if state == ConnectionState::InGame {
+            let was_lost = self.lost_since.is_some();
+            *self = Self::default();
+            return if was_lost {
+                RecoveryAction::Restored
+            } else {
+                RecoveryAction::None
+            };
+        }
+
+        self.lost_since.get_or_insert(now);
+        if self.state != state || self.state_since.is_none() {
+            self.state = state;
+            self.state_since = Some(now);
+        }
+        if self.gave_up || !settings.enabled {
+            return RecoveryAction::Wait;
+        }
+        if self.next_attempt.map_or(false, |next| now < next) {
+            return RecoveryAction::Wait;
+        }
+
+        let action = match state {
+            ConnectionState::InGame => unreachable!(),
+            ConnectionState::Loading => {
+                let since = self.state_since.unwrap_or(now);
+                if now.saturating_duration_since(since) < settings.load_timeout {
+                    return RecoveryAction::Wait;
+                }
+                self.state_since = Some(now);
+                RecoveryAction::Wait
+            }
+            // Nothing to press, only wait longer each time
+            ConnectionState::ServerDown => RecoveryAction::Wait,
+            ConnectionState::Disconnected | ConnectionState::LoginScreen => {
+                RecoveryAction::PressEnter
+            }
+            ConnectionState::CharacterSelect => {
+                RecoveryAction::SelectCharacter(settings.character_slot)
+            }
+        };
+
+        if self.attempts >= settings.max_attempts {
+            self.gave_up = true;
+            return RecoveryAction::GiveUp;
+        }
+        self.attempts += 1;
+        let backoff = settings
+            .delay
+            .saturating_mul(1 << (self.attempts - 1).min(16));
+        self.next_attempt = Some(now + backoff.min(settings.max_delay));
+        action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ConnectionRecovery, ConnectionState, RecoveryAction, RecoverySettings};
+
+    #[test]
+    fn test_connection_recovery() {
+        let settings = RecoverySettings {
+            enabled: true,
+            character_slot: 1,
+            max_attempts: 4,
+            delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(5),
+            load_timeout: Duration::from_secs(30),
+        };
+        let start = Instant::now();
+        let seconds = |n: u64| start + Duration::from_secs(n);
+        let mut recovery = ConnectionRecovery::default();
+        let mut update = |state, now| recovery.update(state, &settings, now);
+
+        assert_eq!(update(ConnectionState::InGame, start), RecoveryAction::None);
+        assert_eq!(
+            update(ConnectionState::Disconnected, start),
+            RecoveryAction::PressEnter
+        );
+        // Back-off before the next attempt
+        assert_eq!(
+            update(ConnectionState::LoginScreen, seconds(1)),
+            RecoveryAction::Wait
+        );
+        assert_eq!(
+            update(ConnectionState::LoginScreen, seconds(2)),
+            RecoveryAction::PressEnter
+        );
+        assert_eq!(
+            update(ConnectionState::CharacterSelect, seconds(5)),
+            RecoveryAction::Wait
+        );
+        assert_eq!(
+            update(ConnectionState::CharacterSelect, seconds(6)),
+            RecoveryAction::SelectCharacter(1)
+        );
+        assert_eq!(
+            update(ConnectionState::Loading, seconds(20)),
+            RecoveryAction::Wait
+        );
+        assert_eq!(
+            update(ConnectionState::InGame, seconds(25)),
+            RecoveryAction::Restored
+        );
+
+        // Retry limit
+        for n in 0..4 {
+            assert_eq!(
+                update(ConnectionState::ServerDown, seconds(100 + n * 10)),
+                RecoveryAction::Wait
+            );
+        }
+        assert_eq!(
+            update(ConnectionState::ServerDown, seconds(150)),
+            RecoveryAction::GiveUp
+        );
+        assert_eq!(
+            update(ConnectionState::LoginScreen, seconds(200)),
+            RecoveryAction::Wait
+        );
+        assert_eq!(
+            update(ConnectionState::InGame, seconds(300)),
+            RecoveryAction::Restored
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use guard::guard;
+use serde::{Deserialize, Serialize};
+
+/// Experience drops by more than this when leveling up, smaller drops are death penalties.
+const LEVEL_UP_DROP: f32 = 50.0;
+
+/// Experience figures sent to the frontend, all values are percents of a level.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ExperienceStats {
+    pub current: f32,
+    pub gained: f32,
+    pub per_hour: f32,
+    /// Estimated milliseconds to the next level, 0 if unknown
+    pub time_to_level: u64,
+    pub level_ups: u32,
+}
+
+/// Tracks the experience percentage read after each kill.
+#[derive(Debug, Default, Clone)]
+pub struct ExperienceTracker {
+    start_time: Option<Instant>,
+    last: Option<f32>,
+    gained: f32,
+    level_ups: u32,
+}
+
+impl ExperienceTracker {
+    /// Record a new reading, returns whether the character leveled up since the last one.
+    pub fn update(&mut self, exp: f32, now: Instant) -> bool {
+        guard!(let Some(last) = self.last else {
+            self.start_time = Some(now);
+            self.last = Some(exp);
+            return false;
+        });
+        self.last = Some(exp);
+
+        if last - exp > LEVEL_UP_DROP {
+            self.gained += 100.0 - last + exp;
+            self.level_ups += 1;
+            true
+        } else {
+            self.gained += exp - last;
+            false
+        }
+    }
+
+    pub fn stats(&self, now: Instant) -> ExperienceStats {
+        let elapsed = self
+            .start_time
+            .map(|start| now.saturating_duration_since(start))
+            .unwrap_or_default();
+        let current = self.last.unwrap_or_default();
+
+        let per_hour = if elapsed.is_zero() {
+            0.0
+        } else {
+            self.gained / elapsed.as_secs_f32() * 3600.0
+        };
+        let time_to_level = if per_hour > 0.0 {
+            Duration::from_secs_f32((100.0 - current) / per_hour * 3600.0).as_millis() as u64
+        } else {
+            0
+        };
+
+        ExperienceStats {
+            current,
+            gained: self.gained,
+            per_hour,
+            time_to_level,
+            level_ups: self.level_ups,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ExperienceTracker;
+
+    #[test]
+    fn test_experience_tracker() {
+        let start = Instant::now();
+        let minutes = |n: u64| start + Duration::from_secs(n * 60);
+        let mut tracker = ExperienceTracker::default();
+
+        assert!(!tracker.update(90.0, start));
+        assert_eq!(tracker.stats(start).per_hour, 0.0);
+        assert_eq!(tracker.stats(start).time_to_level, 0);
+
+        assert!(!tracker.update(95.0, minutes(15)));
+        // Death penalty
+        assert!(!tracker.update(94.0, minutes(20)));
+        assert!(tracker.update(4.0, minutes(30)));
+
+        let stats = tracker.stats(minutes(30));
+        assert_eq!(stats.current, 4.0);
+        assert_eq!(stats.gained, 14.0);
+        assert_eq!(stats.per_hour, 28.0);
+        assert_eq!(stats.level_ups, 1);
+        // 96% left at 28%/hour
+        assert_eq!(stats.time_to_level / 60_000, 205);
+    }
+}
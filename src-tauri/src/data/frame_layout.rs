@@ -0,0 +1,120 @@
+use super::{Bounds, Point};
+
+/// Client size the default coordinates were measured in.
+pub const REFERENCE_FRAME_SIZE: (u32, u32) = (800, 600);
+
+/// Part of the screen an UI element sticks to when the client is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    Center,
+    BottomCenter,
+}
+
+/// Size of a frame, maps coordinates measured in another frame to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLayout {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for FrameLayout {
+    fn default() -> Self {
+        let (width, height) = REFERENCE_FRAME_SIZE;
+        Self { width, height }
+    }
+}
+
+impl FrameLayout {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Where the anchor is in this frame.
+    fn anchor_point(&self, anchor: Anchor) -> (i64, i64) {
+        let (width, height) = (self.width as i64, self.height as i64);
+        match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::TopCenter => (width / 2, 0),
+            Anchor::TopRight => (width, 0),
+            Anchor::Center => (width / 2, height / 2),
+            Anchor::BottomCenter => (width / 2, height),
+        }
+    }
+
+    /// Move a point of `from` so it keeps its distance to the anchor in this frame.
+    pub fn move_point(&self, point: Point, anchor: Anchor, from: &FrameLayout) -> Point {
+        let (to_x, to_y) = self.anchor_point(anchor);
+        let (from_x, from_y) = from.anchor_point(anchor);
+        Point::new(
+            (point.x as i64 + to_x - from_x).max(0) as u32,
+            (point.y as i64 + to_y - from_y).max(0) as u32,
+        )
+    }
+
+    /// Move bounds of `from` so they keep their distance to the anchor in this frame.
+    pub fn move_bounds(&self, bounds: Bounds, anchor: Anchor, from: &FrameLayout) -> Bounds {
+        let origin = self.move_point(Point::new(bounds.x, bounds.y), anchor, from);
+        Bounds::new(origin.x, origin.y, bounds.w, bounds.h)
+    }
+
+    /// Move bounds measured in an 800x600 client.
+    pub fn place(&self, bounds: Bounds, anchor: Anchor) -> Bounds {
+        self.move_bounds(bounds, anchor, &Self::default())
+    }
+
+    /// Scale a distance in the world measured in an 800x600 client.
+    /// The camera keeps its vertical field of view, so the world grows with the height.
+    pub fn scale_distance(&self, distance: i32) -> i32 {
+        let (_, reference_height) = REFERENCE_FRAME_SIZE;
+        (distance as i64 * self.height as i64 / reference_height as i64) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Anchor, FrameLayout};
+    use crate::data::Bounds;
+
+    #[test]
+    fn test_frame_layout() {
+        let reference = FrameLayout::default();
+        let bounds = Bounds::new(240, 460, 350, 100);
+        assert_eq!(reference.place(bounds, Anchor::BottomCenter), bounds);
+
+        let fullscreen = FrameLayout::new(1920, 1080);
+        assert_eq!(fullscreen.place(bounds, Anchor::TopLeft), bounds);
+        assert_eq!(
+            fullscreen.place(bounds, Anchor::TopCenter),
+            Bounds::new(800, 460, 350, 100)
+        );
+        assert_eq!(
+            fullscreen.place(bounds, Anchor::TopRight),
+            Bounds::new(1360, 460, 350, 100)
+        );
+        assert_eq!(
+            fullscreen.place(bounds, Anchor::Center),
+            Bounds::new(800, 700, 350, 100)
+        );
+        assert_eq!(
+            fullscreen.place(bounds, Anchor::BottomCenter),
+            Bounds::new(800, 940, 350, 100)
+        );
+
+        // Smaller frames never move bounds before the frame edge
+        let small = FrameLayout::new(400, 300);
+        assert_eq!(
+            small.place(bounds, Anchor::Center),
+            Bounds::new(40, 310, 350, 100)
+        );
+        assert_eq!(
+            small.move_bounds(Bounds::new(10, 10, 5, 5), Anchor::BottomCenter, &fullscreen),
+            Bounds::new(0, 0, 5, 5)
+        );
+
+        assert_eq!(reference.scale_distance(325), 325);
+        assert_eq!(fullscreen.scale_distance(325), 585);
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// Something the client reported through a system message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    InventoryFull,
+    TargetTooFar,
+    NotEnoughMp,
+    ItemObtained(String),
+    CannotAttack,
+}
+
+impl GameEvent {
+    /// Classify a single line of the toast area.
+    pub fn classify(line: &str) -> Option<Self> {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        let lower = line.to_lowercase();
+        let contains_any = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        if contains_any(&[
+            "inventory is full",
+            "inventory full",
+            "not enough inventory",
+        ]) {
+            Some(Self::InventoryFull)
+        } else if contains_any(&["too far"]) {
+            Some(Self::TargetTooFar)
+        } else if contains_any(&["not enough mp", "insufficient mp", "mp is insufficient"]) {
+            Some(Self::NotEnoughMp)
+        } else if contains_any(&["cannot attack", "can't attack", "unable to attack"]) {
+            Some(Self::CannotAttack)
+        } else {
+            ["obtained", "acquired"].iter().find_map(|keyword| {
+                let index = lower.find(keyword)? + keyword.len();
+                // Lowercasing may change byte offsets, so only slice ASCII lines
+                let item = if line.is_ascii() { &line[index..] } else { "" };
+                let item = item.trim_matches(|c: char| !c.is_alphanumeric());
+                Some(Self::ItemObtained(item.to_string()))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameEvent;
+
+    #[test]
+    fn test_classify() {
+        let classify = |line| GameEvent::classify(line);
+        assert_eq!(
+            classify("Your  inventory is full."),
+            Some(GameEvent::InventoryFull)
+        );
+        assert_eq!(
+            classify("The target is too far away"),
+            Some(GameEvent::TargetTooFar)
+        );
+        assert_eq!(classify("Not enough MP."), Some(GameEvent::NotEnoughMp));
+        assert_eq!(
+            classify("You cannot attack this target"),
+            Some(GameEvent::CannotAttack)
+        );
+        assert_eq!(
+            classify("You have obtained [Aibatt Wing]."),
+            Some(GameEvent::ItemObtained("Aibatt Wing".to_string()))
+        );
+        assert_eq!(
+            classify("Acquired 15 Penya"),
+            Some(GameEvent::ItemObtained("15 Penya".to_string()))
+        );
+        assert_eq!(classify("Welcome to Madrigal"), None);
+        assert_eq!(classify(""), None);
+    }
+}
@@ -0,0 +1,31 @@
+use super::{Point, WorldPoint};
+
+/// What the minimap shows around the character, it is drawn with north up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinimapInfo {
+    /// Center of the player arrow in the frame
+    pub player: Point,
+    /// Compass heading the arrow points to in degrees, 0 is north and 90 east
+    pub heading: Option<f32>,
+    /// Mob dots in the frame
+    pub mobs: Vec<Point>,
+}
+
+impl MinimapInfo {
+    /// World position of a minimap point, the character standing at `position`.
+    /// `units_per_pixel` depends on how far the minimap is zoomed.
+    pub fn to_world(&self, point: Point, position: WorldPoint, units_per_pixel: f32) -> WorldPoint {
+        WorldPoint::new(
+            position.x + (point.x as f32 - self.player.x as f32) * units_per_pixel,
+            position.z + (point.y as f32 - self.player.y as f32) * units_per_pixel,
+        )
+    }
+
+    /// World positions of the mobs, the character standing at `position`.
+    pub fn mob_positions(&self, position: WorldPoint, units_per_pixel: f32) -> Vec<WorldPoint> {
+        self.mobs
+            .iter()
+            .map(|mob| self.to_world(*mob, position, units_per_pixel))
+            .collect()
+    }
+}
@@ -0,0 +1,204 @@
+use std::{cmp::Ordering, time::Instant};
+
+use super::{Point, Target};
+
+/// Mobs further than this from a track's predicted position start a new track.
+const MAX_MATCH_DISTANCE: f32 = 60.0;
+/// Tracks are dropped after this many updates without a matching mob.
+const MAX_MISSED_UPDATES: u32 = 5;
+/// Pixels per second a mob has to walk towards a point to be closing in.
+const MIN_APPROACH_SPEED: f32 = 20.0;
+
+pub type TrackId = u32;
+
+/// A mob followed across frames.
+#[derive(Debug, Clone, Copy)]
+pub struct Track {
+    pub id: TrackId,
+    pub target: Target,
+    /// Pixels per second
+    pub velocity: (f32, f32),
+    pub last_seen: Instant,
+    missed: u32,
+}
+
+impl Track {
+    fn new(id: TrackId, target: Target, now: Instant) -> Self {
+        Self {
+            id,
+            target,
+            velocity: (0.0, 0.0),
+            last_seen: now,
+            missed: 0,
+        }
+    }
+
+    /// Whether the mob is within `reach` of the point or walking towards it.
+    pub fn is_closing_in(&self, point: Point, reach: f32) -> bool {
+        let coords = self.target.get_attack_coords();
+        let distance = coords.distance_to(&point);
+        if distance <= reach {
+            return true;
+        }
+        let (dx, dy) = (
+            point.x as f32 - coords.x as f32,
+            point.y as f32 - coords.y as f32,
+        );
+        (self.velocity.0 * dx + self.velocity.1 * dy) / distance >= MIN_APPROACH_SPEED
+    }
+
+    /// Where the mob should be at `now` if it kept its velocity.
+    fn predicted_coords(&self, now: Instant) -> (f32, f32) {
+        let elapsed = now.saturating_duration_since(self.last_seen).as_secs_f32();
+        let coords = self.target.get_attack_coords();
+        (
+            coords.x as f32 + self.velocity.0 * elapsed,
+            coords.y as f32 + self.velocity.1 * elapsed,
+        )
+    }
+
+    fn follow(&mut self, target: Target, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_seen).as_secs_f32();
+        let (from, to) = (self.target.get_attack_coords(), target.get_attack_coords());
+        if elapsed > 0.0 {
+            // Smooth the velocity since detected bounds jitter a bit
+            let dx = (to.x as f32 - from.x as f32) / elapsed;
+            let dy = (to.y as f32 - from.y as f32) / elapsed;
+            self.velocity = ((self.velocity.0 + dx) / 2.0, (self.velocity.1 + dy) / 2.0);
+        }
+        self.target = target;
+        self.last_seen = now;
+        self.missed = 0;
+    }
+}
+
+/// Gives mobs an identity across frames by matching them to the closest known track.
+#[derive(Debug, Default, Clone)]
+pub struct MobTracker {
+    tracks: Vec<Track>,
+    next_id: TrackId,
+}
+
+impl MobTracker {
+    /// Match the mobs of a new frame with the known tracks and tag them with their track id.
+    pub fn update(&mut self, mobs: &mut [Target], now: Instant) {
+        // Every close enough track and mob pair, closest first
+        let mut pairs = vec![];
+        for (track_index, track) in self.tracks.iter().enumerate() {
+            let (x, y) = track.predicted_coords(now);
+            for (mob_index, mob) in mobs.iter().enumerate() {
+                if mob.target_type != track.target.target_type {
+                    continue;
+                }
+                let coords = mob.get_attack_coords();
+                let distance = (x - coords.x as f32).hypot(y - coords.y as f32);
+                if distance <= MAX_MATCH_DISTANCE {
+                    pairs.push((distance, track_index, mob_index));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut matched_tracks = vec![false; self.tracks.len()];
+        let mut matches = vec![None; mobs.len()];
+        for (_, track_index, mob_index) in pairs {
+            if !matched_tracks[track_index] && matches[mob_index].is_none() {
+                matched_tracks[track_index] = true;
+                matches[mob_index] = Some(track_index);
+            }
+        }
+
+        for (track, matched) in self.tracks.iter_mut().zip(matched_tracks) {
+            if !matched {
+                track.missed += 1;
+            }
+        }
+        for (mob, track_index) in mobs.iter_mut().zip(matches) {
+            let id = match track_index {
+                Some(track_index) => {
+                    let track = &mut self.tracks[track_index];
+                    track.follow(*mob, now);
+                    track.id
+                }
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    self.tracks.push(Track::new(id, *mob, now));
+                    id
+                }
+            };
+            mob.track_id = Some(id);
+        }
+
+        self.tracks
+            .retain(|track| track.missed <= MAX_MISSED_UPDATES);
+    }
+
+    pub fn track(&self, id: TrackId) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::MobTracker;
+    use crate::data::{Bounds, MobType, Point, Target, TargetType};
+
+    fn mob(mob_type: MobType, x: u32, y: u32) -> Target {
+        Target {
+            target_type: TargetType::Mob(mob_type),
+            bounds: Bounds::new(x, y, 40, 5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mob_tracker() {
+        let start = Instant::now();
+        let seconds = |n: u64| start + Duration::from_secs(n);
+        let mut tracker = MobTracker::default();
+
+        let mut mobs = vec![
+            mob(MobType::Passive, 100, 100),
+            mob(MobType::Passive, 300, 100),
+        ];
+        tracker.update(&mut mobs, start);
+        assert_eq!(mobs[0].track_id, Some(0));
+        assert_eq!(mobs[1].track_id, Some(1));
+
+        // Detection order doesn't matter, the first mob walks right
+        let mut mobs = vec![
+            mob(MobType::Passive, 301, 100),
+            mob(MobType::Passive, 120, 100),
+            mob(MobType::Aggressive, 500, 300),
+        ];
+        tracker.update(&mut mobs, seconds(1));
+        assert_eq!(mobs[0].track_id, Some(1));
+        assert_eq!(mobs[1].track_id, Some(0));
+        assert_eq!(mobs[2].track_id, Some(2));
+
+        let walking = tracker.track(0).unwrap();
+        assert_eq!(walking.velocity, (10.0, 0.0));
+        let idle = tracker.track(1).unwrap();
+        assert_eq!(idle.velocity, (0.5, 0.0));
+        // Idle mobs only close in once within reach
+        assert!(!idle.is_closing_in(Point::new(600, 100), 100.0));
+        assert!(idle.is_closing_in(Point::new(400, 100), 100.0));
+
+        // Velocity helps following a mob that keeps walking
+        let mut mobs = vec![mob(MobType::Passive, 185, 100)];
+        tracker.update(&mut mobs, seconds(3));
+        assert_eq!(mobs[0].track_id, Some(0));
+
+        // Mobs out of sight for too long are forgotten
+        for n in 4..10 {
+            tracker.update(&mut [], seconds(n));
+        }
+        assert!((0..3).all(|id| tracker.track(id).is_none()));
+        let mut mobs = vec![mob(MobType::Passive, 301, 100)];
+        tracker.update(&mut mobs, seconds(10));
+        assert_eq!(mobs[0].track_id, Some(3));
+    }
+}
@@ -1,7 +1,9 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 /// A point in 2D space.
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Serialize, Deserialize)]
 pub struct Point {
     pub x: u32,
     pub y: u32,
@@ -12,6 +14,13 @@ impl Point {
     pub fn new(x: u32, y: u32) -> Self {
         Self { x, y }
     }
+
+    /// Euclidean distance to another point.
+    pub fn distance_to(&self, other: &Point) -> f32 {
+        let dx = self.x as f32 - other.x as f32;
+        let dy = self.y as f32 - other.y as f32;
+        (dx * dx + dy * dy).sqrt()
+    }
 }
 
 impl From<(u32, u32)> for Point {
@@ -1,18 +1,6 @@
-use super::{Bounds, Point};
-
-pub mod point_selector {
-    use crate::data::Point;
-
-    /// Select points on x axis
-    pub fn x_axis(point: &Point) -> u32 {
-        point.x
-    }
+use std::collections::HashMap;
 
-    /// Select points on y axis
-    pub fn y_axis(point: &Point) -> u32 {
-        point.y
-    }
-}
+use super::{Bounds, Point};
 
 /// A point cloud in 2D space.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -21,39 +9,10 @@ pub struct PointCloud {
 }
 
 impl PointCloud {
-    pub fn new<P>(points: P) -> Self
-    where
-        P: AsRef<[Point]>,
-    {
-        Self {
-            points: points.as_ref().to_vec(),
-        }
-    }
-
     pub fn push(&mut self, point: Point) {
         self.points.push(point);
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.points.is_empty()
-    }
-
-    fn sort_by<Selector>(&mut self, selector: Selector)
-    where
-        Selector: Fn(&Point) -> u32,
-    {
-        self.points.sort_by_key(|point| selector(point));
-    }
-
-    fn sorted_by<Selector>(&self, selector: Selector) -> Self
-    where
-        Selector: Fn(&Point) -> u32,
-    {
-        let mut copy = self.clone();
-        copy.sort_by(selector);
-        copy
-    }
-
     pub fn to_bounds(&self) -> Bounds {
         // Calculate min/max for x/y coords
         let min_x = self.points.iter().map(|point| point.x).min().unwrap_or(0);
@@ -70,41 +29,59 @@ impl PointCloud {
         }
     }
 
-    pub fn cluster_by_distance<F>(&self, distance: u32, selector: F) -> Vec<PointCloud>
-    where
-        F: Fn(&Point) -> u32,
-    {
-        // Sort points by axis selector
-        let points = self.sorted_by(&selector);
-        let mut clusters = vec![PointCloud::default()];
-
-        // Iterate over points and build clusters
-        for point in points {
-            // Fill default cluster first
-            let last_cluster = clusters.last_mut().unwrap();
-            if last_cluster.is_empty() {
-                last_cluster.push(point);
-                continue;
-            }
+    /// Split the cloud into connected components. Two points are connected when they are at most
+    /// `max_gap_x` apart horizontally and `max_gap_y` apart vertically.
+    pub fn cluster_connected(&self, max_gap_x: u32, max_gap_y: u32) -> Vec<PointCloud> {
+        // Bucket points into cells as large as the gaps so neighbours are in adjacent cells
+        let cell_size = (max_gap_x + 1, max_gap_y + 1);
+        let cell_of = |point: &Point| (point.x / cell_size.0, point.y / cell_size.1);
+        let mut cells: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+        for (index, point) in self.points.iter().enumerate() {
+            cells.entry(cell_of(point)).or_default().push(index);
+        }
 
-            // Select coordinates of last pointer in active cluster and current point
-            let last_coord = selector(last_cluster.points.last().unwrap());
-            let curr_coord = selector(&point);
+        // Union every pair of connected points
+        let mut parents = (0..self.points.len()).collect::<Vec<_>>();
+        for (index, point) in self.points.iter().enumerate() {
+            let (cell_x, cell_y) = cell_of(point);
+            for neighbour_x in cell_x.saturating_sub(1)..=cell_x + 1 {
+                for neighbour_y in cell_y.saturating_sub(1)..=cell_y + 1 {
+                    let neighbours = cells.get(&(neighbour_x, neighbour_y));
+                    for &neighbour in neighbours.into_iter().flatten() {
+                        let other = &self.points[neighbour];
+                        if neighbour < index
+                            && point.x.max(other.x) - point.x.min(other.x) <= max_gap_x
+                            && point.y.max(other.y) - point.y.min(other.y) <= max_gap_y
+                        {
+                            let root = Self::find_root(&mut parents, index);
+                            let other_root = Self::find_root(&mut parents, neighbour);
+                            parents[root.max(other_root)] = root.min(other_root);
+                        }
+                    }
+                }
+            }
+        }
 
-            // Decide whether to include the point in the current cluster or create a new one
-            if curr_coord.abs_diff(last_coord) <= distance {
-                last_cluster.push(point);
-            } else {
+        // Group points by component, ordered by their first point
+        let mut clusters: Vec<PointCloud> = Vec::default();
+        let mut cluster_of_root = HashMap::new();
+        for (index, point) in self.points.iter().enumerate() {
+            let root = Self::find_root(&mut parents, index);
+            let cluster = *cluster_of_root.entry(root).or_insert_with(|| {
                 clusters.push(PointCloud::default());
-                clusters.last_mut().unwrap().push(point);
-            }
+                clusters.len() - 1
+            });
+            clusters[cluster].push(*point);
         }
+        clusters
+    }
 
-        // Return clusters or empty vector if no clusters were created
-        match &clusters[..] {
-            [n] if n.points.is_empty() => Vec::default(),
-            _ => clusters,
+    fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
         }
+        index
     }
 }
 
@@ -139,20 +116,34 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::data::{point_selector, Bounds, Point, PointCloud};
+    use crate::data::{Bounds, Point, PointCloud};
 
     #[test]
-    fn test_cluster_by_distance() {
-        let distance = 5;
-        let points: Vec<Point> = [(0, 0), (9, 1), (5, 1), (15, 5), (17, 3)]
-            .into_iter()
-            .map(|x| x.into())
-            .collect();
-        let cloud = PointCloud::new(points);
-        let clusters = cloud.cluster_by_distance(distance, point_selector::x_axis);
+    fn test_cluster_connected() {
+        // Letters of a single name with small gaps merge
+        let cloud = PointCloud::from([(0, 0), (2, 1), (6, 0), (9, 2), (12, 1)]);
+        let clusters = cloud.cluster_connected(4, 3);
+        assert_eq!(clusters, vec![cloud.clone()]);
+
+        // Names at similar x but different heights stay apart
+        let cloud = PointCloud::from([(10, 10), (20, 10), (12, 30), (22, 30)]);
+        let clusters = cloud.cluster_connected(12, 3);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0], PointCloud::from([(10, 10), (20, 10)]));
+        assert_eq!(clusters[1], PointCloud::from([(12, 30), (22, 30)]));
+
+        // Neighbouring names at the same height stay apart
+        let cloud = PointCloud::from([(0, 5), (8, 5), (16, 5), (40, 5), (48, 5)]);
+        let clusters = cloud.cluster_connected(10, 3);
         assert_eq!(clusters.len(), 2);
-        assert_eq!(clusters[0], PointCloud::from([(0, 0), (5, 1), (9, 1)]));
-        assert_eq!(clusters[1], PointCloud::from([(15, 5), (17, 3)]));
+        assert_eq!(clusters[0].to_bounds(), Bounds::new(0, 5, 16, 0));
+        assert_eq!(clusters[1].to_bounds(), Bounds::new(40, 5, 8, 0));
+
+        // Components chained through intermediate points merge
+        let cloud = PointCloud::from([(30, 0), (0, 0), (20, 2), (10, 1)]);
+        assert_eq!(cloud.cluster_connected(10, 3).len(), 1);
+
+        assert!(PointCloud::default().cluster_connected(10, 3).is_empty());
     }
 
     #[test]
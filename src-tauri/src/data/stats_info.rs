@@ -1,15 +1,16 @@
 use std::{fmt, time::Instant};
 
+use serde::{Deserialize, Serialize};
 use slog::Logger;
-use tauri::Window;
 
-use super::PointCloud;
+use super::{Anchor, Bounds, FrameLayout, Point};
 use crate::{
-    image_analyzer::{Color, ImageAnalyzer},
-    platform::{eval_send_key, KeyMode, draw_bounds_rect, remove_all_markers},
+    image_analyzer::{Color, DeathSignals, ImageAnalyzer},
+    platform::{InputSink, KeyMode},
+    utils::{Clock, SystemClock},
 };
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum StatusBarKind {
     #[default]
     Hp,
@@ -30,6 +31,27 @@ impl fmt::Display for StatusBarKind {
     }
 }
 
+impl StatusBarKind {
+    /// Part of the screen the bar sticks to.
+    pub fn anchor(&self) -> Anchor {
+        match self {
+            StatusBarKind::Hp | StatusBarKind::Mp | StatusBarKind::Fp => Anchor::TopLeft,
+            StatusBarKind::TargetHP | StatusBarKind::TargetMP => Anchor::TopCenter,
+        }
+    }
+}
+
+/// Whether the character is alive, as far as the frame tells.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LifeState {
+    #[default]
+    Unknown,
+    Alive,
+    Dead,
+    /// Died and waiting for the stat tray to show up again
+    Reviving,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClientStats {
     pub hp: StatInfo,
@@ -37,33 +59,35 @@ pub struct ClientStats {
     pub fp: StatInfo,
     pub target_hp: StatInfo,
     pub target_mp: StatInfo,
-    is_alive: bool,
+    life_state: LifeState,
     pub stat_try_not_detected_count: i32,
-    window: Window,
 }
 impl ClientStats {
-    pub fn new(window: Window) -> Self {
+    pub fn new(clock: &dyn Clock) -> Self {
         Self {
-            hp: StatInfo::new(0, 100, StatusBarKind::Hp, None, window.clone()),
-            mp: StatInfo::new(0, 100, StatusBarKind::Mp, None, window.clone()),
-            fp: StatInfo::new(0, 100, StatusBarKind::Fp, None, window.clone()),
-            target_hp: StatInfo::new(0, 0, StatusBarKind::TargetHP, None, window.clone()),
-            target_mp: StatInfo::new(0, 0, StatusBarKind::TargetMP, None, window.clone()),
-            is_alive: true,
+            hp: StatInfo::new(0, 100, StatusBarKind::Hp, clock, None),
+            mp: StatInfo::new(0, 100, StatusBarKind::Mp, clock, None),
+            fp: StatInfo::new(0, 100, StatusBarKind::Fp, clock, None),
+            target_hp: StatInfo::new(0, 0, StatusBarKind::TargetHP, clock, None),
+            target_mp: StatInfo::new(0, 0, StatusBarKind::TargetMP, clock, None),
+            life_state: LifeState::Unknown,
             stat_try_not_detected_count: 0,
-            window,
         }
     }
 
-    // update all bars values at once
-    pub fn update(&mut self, image: &ImageAnalyzer, _logger: &Logger) {
+    // update all bars values at once, from the bounds of each bar in the frame
+    pub fn update<F>(&mut self, bar_bounds: F, now: Instant, _logger: &Logger)
+    where
+        F: Fn(StatusBarKind) -> Bounds,
+    {
         let _should_debug = [
-            self.hp.update_value(image),
-            self.mp.update_value(image),
-            self.fp.update_value(image),
-            self.target_hp.update_value(image),
-            self.target_mp.update_value(image),
-        ];
+            &mut self.hp,
+            &mut self.mp,
+            &mut self.fp,
+            &mut self.target_hp,
+            &mut self.target_mp,
+        ]
+        .map(|stat| stat.update_value(bar_bounds(stat.stat_kind), now));
         // Debug is deactivated
         /*if should_debug.contains(&true) {
             self.debug_print(_logger);
@@ -71,7 +95,7 @@ impl ClientStats {
     }
 
     // Detect whether we can read or not stat_tray and open it if needed
-    pub fn detect_stat_tray(&mut self) -> bool {
+    pub fn detect_stat_tray(&mut self, input: &dyn InputSink) -> bool {
         // Since HP/MP/FP are 0 we know bar should be hidden
         if self.hp.value == 0 && self.mp.value == 0 && self.fp.value == 0 {
             self.stat_try_not_detected_count += 1;
@@ -79,7 +103,7 @@ impl ClientStats {
                 self.stat_try_not_detected_count = 0;
 
                 // Try to open char stat tray
-                eval_send_key(&self.window, "T", KeyMode::Press);
+                input.send_key("T", KeyMode::Press);
             }
             false
         } else {
@@ -88,26 +112,39 @@ impl ClientStats {
         }
     }
 
+    /// Update the life state from the death cues of the frame and the HP bar.
+    pub fn update_life_state(&mut self, signals: DeathSignals, input: &dyn InputSink) -> LifeState {
+        // The HP bar can only be trusted while the stat tray is open
+        let tray_visible = self.detect_stat_tray(input);
+
+        self.life_state = if signals.revive_dialog
+            || (tray_visible && self.hp.value == 0)
+            || (!tray_visible && signals.grey_screen)
+        {
+            LifeState::Dead
+        } else if tray_visible {
+            LifeState::Alive
+        } else if matches!(self.life_state, LifeState::Dead | LifeState::Reviving) {
+            LifeState::Reviving
+        } else {
+            LifeState::Unknown
+        };
+        self.life_state
+    }
+
     // bot died
-    pub fn is_alive(&mut self) -> bool {
-        // We need to be sure that char tray is open before
-        if self.detect_stat_tray() {
-            // Obfviously
-            self.is_alive = self.hp.value != 0;
-        }
-        self.is_alive
+    pub fn is_alive(&self) -> bool {
+        self.life_state != LifeState::Dead
     }
 
-    pub fn _debug_print(&mut self, logger: &Logger) {
-        // Stringify is_alive
-        let alive_str = {
-            if self.is_alive() {
-                "alive"
-            } else {
-                "dead"
-            }
-        };
-        slog::debug!(logger, "Stats detection"; "HP" => self.hp.value, "MP" => self.mp.value, "FP" => self.fp.value, "Enemy HP" => self.target_hp.value, "Character is" => alive_str);
+    pub fn _debug_print(&self, logger: &Logger) {
+        slog::debug!(logger, "Stats detection"; "HP" => self.hp.value, "MP" => self.mp.value, "FP" => self.fp.value, "Enemy HP" => self.target_hp.value, "Character is" => format!("{:?}", self.life_state));
+    }
+}
+
+impl Default for ClientStats {
+    fn default() -> Self {
+        Self::new(&SystemClock)
     }
 }
 
@@ -118,7 +155,6 @@ pub struct StatInfo {
     pub stat_kind: StatusBarKind,
     pub last_value: u32,
     pub last_update_time: Option<Instant>,
-    window: Window,
 }
 
 impl PartialEq for StatInfo {
@@ -138,52 +174,29 @@ impl StatInfo {
         max_w: u32,
         value: u32,
         stat_kind: StatusBarKind,
+        clock: &dyn Clock,
         image: Option<&ImageAnalyzer>,
-        window: Window,
     ) -> Self {
         let mut res = Self {
             max_w,
             value,
             stat_kind,
-            last_update_time: Some(Instant::now()),
+            last_update_time: Some(clock.now()),
             last_value: 100,
-            window,
         };
         if let Some(image) = image {
-            res.update_value(image);
+            res.update_value(image.status_bar_bounds(stat_kind), clock.now());
         }
 
         res
     }
 
-    pub fn reset_last_update_time(&mut self) {
-        self.last_update_time = Some(Instant::now());
+    pub fn reset_last_update_time(&mut self, clock: &dyn Clock) {
+        self.last_update_time = Some(clock.now());
     }
 
-    pub fn update_value(&mut self, image: &ImageAnalyzer) -> bool {
-        remove_all_markers(&self.window);
-        let status_bar_config: StatusBarConfig = self.stat_kind.into();
-        let recv = image.pixel_detection(
-            status_bar_config.refs,
-            status_bar_config.min_x,
-            status_bar_config.min_y,
-            status_bar_config.max_x,
-            status_bar_config.max_y,
-            Some(2),
-        );
-
-        // Receive points from channel
-        let cloud = {
-            let mut cloud = PointCloud::default();
-            while let Ok(point) = recv.recv() {
-                cloud.push(point);
-            }
-            cloud
-        };
-
-        // Calculate bounds
-        let bounds = cloud.to_bounds();
-
+    /// Update the value from the bounds of the bar found by the frame scan.
+    pub fn update_value(&mut self, bounds: Bounds, now: Instant) -> bool {
         // let status_bar_config: StatusBarConfig = self.stat_kind.into();
         
         // draw_bounds_rect(
@@ -208,7 +221,7 @@ impl StatInfo {
         self.last_value = old_value;
         if updated_value != old_value {
             self.value = updated_value;
-            self.last_update_time = Some(Instant::now());
+            self.last_update_time = Some(now);
             true
         } else {
             false
@@ -235,6 +248,14 @@ impl StatusBarConfig {
             ..Default::default()
         }
     }
+
+    /// Move the search region of a bar found in `from` to the same place in `to`.
+    pub fn moved(mut self, anchor: Anchor, from: &FrameLayout, to: &FrameLayout) -> Self {
+        let min = to.move_point(Point::new(self.min_x, self.min_y), anchor, from);
+        let max = to.move_point(Point::new(self.max_x, self.max_y), anchor, from);
+        (self.min_x, self.min_y, self.max_x, self.max_y) = (min.x, min.y, max.x, max.y);
+        self
+    }
 }
 
 impl From<StatusBarKind> for StatusBarConfig {
@@ -309,3 +330,46 @@ impl PartialEq for StatusBarConfig {
         self.max_x == other.max_x
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientStats, LifeState};
+    use crate::{image_analyzer::DeathSignals, platform::RecordingInputSink, utils::VirtualClock};
+
+    #[test]
+    fn test_life_state() {
+        let input = RecordingInputSink::new();
+        let mut stats = ClientStats::new(&VirtualClock::new());
+        let nothing = DeathSignals::default();
+        let grey = DeathSignals {
+            grey_screen: true,
+            ..Default::default()
+        };
+        let dialog = DeathSignals {
+            revive_dialog: true,
+            ..Default::default()
+        };
+        let update = |stats: &mut ClientStats, hp, mp, signals| {
+            stats.hp.value = hp;
+            stats.mp.value = mp;
+            stats.fp.value = mp;
+            stats.update_life_state(signals, &input)
+        };
+
+        assert_eq!(update(&mut stats, 0, 0, nothing), LifeState::Unknown);
+        assert_eq!(update(&mut stats, 80, 50, nothing), LifeState::Alive);
+        // A grey tint alone isn't trusted while the HP bar is readable
+        assert_eq!(update(&mut stats, 80, 50, grey), LifeState::Alive);
+        assert_eq!(update(&mut stats, 0, 50, nothing), LifeState::Dead);
+
+        // Covered stat tray
+        assert_eq!(update(&mut stats, 0, 0, grey), LifeState::Dead);
+        assert_eq!(update(&mut stats, 0, 0, dialog), LifeState::Dead);
+        assert!(!stats.is_alive());
+
+        // Loading after the revive
+        assert_eq!(update(&mut stats, 0, 0, nothing), LifeState::Reviving);
+        assert!(stats.is_alive());
+        assert_eq!(update(&mut stats, 30, 20, nothing), LifeState::Alive);
+    }
+}
@@ -1,12 +1,22 @@
-use super::{Bounds, Point};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+use super::{Bounds, Point, TrackId};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MobType {
     Passive,
     Aggressive,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+/// Giants and bosses are stronger than regular mobs of the same level.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MobRank {
+    #[default]
+    Normal,
+    Giant,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum TargetType {
     Mob(MobType),
     #[default]
@@ -14,10 +24,52 @@ pub enum TargetType {
 }
 
 /// A target in 2D space.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Target {
     pub target_type: TargetType,
     pub bounds: Bounds,
+    #[serde(default)]
+    pub rank: MobRank,
+    /// Set once the mob tracker matched it across frames
+    #[serde(default)]
+    pub track_id: Option<TrackId>,
+}
+
+/// How the name pixels of a frame are grouped into mob names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MobNameSettings {
+    /// Pixels of the same name are at most this far apart
+    pub gap_x: u32,
+    pub gap_y: u32,
+    /// Narrower names are left out, they likely cause misclicks
+    pub min_width: u32,
+    /// Wider names are left out, they are likely not mobs
+    pub max_width: u32,
+    /// Names at least this wide are giants, 0 disables it
+    pub giant_width: u32,
+}
+
+/// Name and level read from the selected target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetInfo {
+    pub name: String,
+    pub level: Option<u32>,
+}
+
+impl TargetInfo {
+    /// Parse the text read from the target name and level areas.
+    pub fn parse(name: &str, level: &str) -> Option<Self> {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return None;
+        }
+
+        let level = level
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|digits| !digits.is_empty())
+            .and_then(|digits| digits.parse().ok());
+        Some(Self { name, level })
+    }
 }
 
 impl Target {
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// A position in the game world, `z` grows southward like the game's coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WorldPoint {
+    pub x: f32,
+    pub z: f32,
+}
+
+impl WorldPoint {
+    pub fn new(x: f32, z: f32) -> Self {
+        Self { x, z }
+    }
+
+    /// Parse coordinates as the game displays them, `x, y, z` or `x, z`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let numbers = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        match numbers[..] {
+            [x, z] | [x, _, z] => Some(Self::new(x, z)),
+            _ => None,
+        }
+    }
+
+    pub fn distance_to(&self, other: &WorldPoint) -> f32 {
+        (other.x - self.x).hypot(other.z - self.z)
+    }
+
+    /// Compass heading towards another point in degrees, 0 is north and 90 east.
+    pub fn heading_to(&self, other: &WorldPoint) -> f32 {
+        (other.x - self.x)
+            .atan2(self.z - other.z)
+            .to_degrees()
+            .rem_euclid(360.0)
+    }
+
+    /// Smallest turn from one heading to another in degrees, positive turns right.
+    pub fn turn_between(from: f32, to: f32) -> f32 {
+        (to - from + 540.0).rem_euclid(360.0) - 180.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WorldPoint;
+
+    #[test]
+    fn test_world_point() {
+        assert_eq!(
+            WorldPoint::parse("6968, 100, 3343"),
+            Some(WorldPoint::new(6968.0, 3343.0))
+        );
+        assert_eq!(
+            WorldPoint::parse(" 6968.5 3343 "),
+            Some(WorldPoint::new(6968.5, 3343.0))
+        );
+        assert_eq!(WorldPoint::parse("6968"), None);
+        assert_eq!(WorldPoint::parse("Flaris"), None);
+
+        let origin = WorldPoint::new(100.0, 100.0);
+        assert_eq!(origin.distance_to(&WorldPoint::new(103.0, 104.0)), 5.0);
+        assert_eq!(origin.heading_to(&WorldPoint::new(100.0, 50.0)), 0.0);
+        assert_eq!(origin.heading_to(&WorldPoint::new(150.0, 100.0)), 90.0);
+        assert_eq!(origin.heading_to(&WorldPoint::new(100.0, 150.0)), 180.0);
+        assert_eq!(origin.heading_to(&WorldPoint::new(50.0, 100.0)), 270.0);
+
+        assert_eq!(WorldPoint::turn_between(350.0, 10.0), 20.0);
+        assert_eq!(WorldPoint::turn_between(10.0, 350.0), -20.0);
+        assert_eq!(WorldPoint::turn_between(90.0, 270.0), -180.0);
+    }
+}
@@ -1,27 +1,64 @@
 use std::{
-    sync::mpsc::{sync_channel, Receiver},
-    time::Instant,
+    cmp::Ordering,
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 //use libscreenshot::shared::Area;
-use libscreenshot::{ImageBuffer, WindowCaptureProvider};
-use rayon::iter::{ParallelBridge, ParallelIterator};
+use guard::guard;
+use libscreenshot::ImageBuffer;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
 use slog::Logger;
-use tauri::Window;
-
-use image::{DynamicImage, Rgb, Rgba};
-use imageproc::contrast::adaptive_threshold;
-use std::io::Cursor;
-use tesseract::Tesseract;
 
 use crate::{
-    data::{point_selector, Bounds, ClientStats, MobType, Point, PointCloud, Target, TargetType},
+    data::{
+        Anchor, Bounds, ClientStats, ConnectionState, FrameLayout, GameEvent, LifeState,
+        MinimapInfo, MobNameSettings, MobRank, MobType, Point, PointCloud, StatusBarConfig,
+        StatusBarKind, Target, TargetInfo, TargetType, WorldPoint,
+    },
     ipc::FarmingConfig,
-    platform::{IGNORE_AREA_BOTTOM, IGNORE_AREA_TOP},
-    utils::Timer,
+    platform::InputSink,
+    utils::{Clock, Timer},
 };
 
-#[derive(Debug, Clone, Copy, Default)]
+mod buff_tray;
+pub use self::buff_tray::BuffAction;
+use self::buff_tray::BuffTray;
+
+mod calibration;
+pub use self::calibration::CalibrationConfig;
+
+mod connection_detector;
+
+mod death_detector;
+pub use self::death_detector::DeathSignals;
+
+mod frame_source;
+pub use self::frame_source::{
+    FrameSource, PngReplaySource, QueuedFrameSource, WindowCaptureSource,
+};
+
+mod minimap;
+
+mod ocr;
+use self::ocr::ThrottledText;
+pub use self::ocr::{OcrEngine, OcrOptions};
+
+mod pixel_scan;
+pub use self::pixel_scan::{PixelQuery, PixelScan};
+
+mod slot_bar;
+pub use self::slot_bar::{SlotBarReader, SlotObservation, SlotObservations};
+
+mod target_scoring;
+pub use self::target_scoring::{ScoringContext, TargetScorer, WeightedScorer};
+
+mod toast_reader;
+pub use self::toast_reader::ToastReader;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Color {
     pub refs: [u8; 3],
 }
@@ -32,17 +69,39 @@ impl Color {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum BoundsArea {
+/// Client areas text can be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundsArea {
     Ping,
     Toast,
     SelectName,
     MobElement,
     MobLevel,
     Experience,
+    ReviveDialog,
+    /// Middle of the screen, where out of game screens show their text
+    Screen,
+    /// Coordinates below the minimap
+    Position,
 }
 
+/// Colors looked for by the per-frame pixel scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Detection {
+    StatusBar(StatusBarKind),
+    TargetMarker,
+    BlankTargetMarker,
+    Mob(MobType),
+}
+
+/// Out of game screens and the revive dialog stay up for seconds, they aren't read every frame.
+const SCREEN_READ_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Mob names right of the stat tray are still part of it.
+const STATUS_TRAY_MARGIN: u32 = 25;
+
 impl BoundsArea {
+    /// Bounds in an 800x600 client.
     fn to_rect(&self) -> Bounds {
         match self {
             BoundsArea::Ping => Bounds::new(2, 110, 120, 20),
@@ -51,6 +110,56 @@ impl BoundsArea {
             BoundsArea::MobElement => Bounds::new(267, 0, 68, 70),
             BoundsArea::MobLevel => Bounds::new(284, 24, 40, 40),
             BoundsArea::Experience => Bounds::new(148, 86, 74, 14),
+            BoundsArea::ReviveDialog => Bounds::new(250, 200, 300, 200),
+            BoundsArea::Screen => Bounds::new(100, 100, 600, 400),
+            BoundsArea::Position => Bounds::new(650, 160, 145, 16),
+        }
+    }
+
+    /// Part of the screen the area sticks to.
+    fn anchor(&self) -> Anchor {
+        match self {
+            BoundsArea::Ping | BoundsArea::Experience => Anchor::TopLeft,
+            BoundsArea::SelectName | BoundsArea::MobElement | BoundsArea::MobLevel => {
+                Anchor::TopCenter
+            }
+            BoundsArea::ReviveDialog | BoundsArea::Screen => Anchor::Center,
+            BoundsArea::Toast => Anchor::BottomCenter,
+            BoundsArea::Position => Anchor::TopRight,
+        }
+    }
+
+    /// Preprocessing used to read the area.
+    fn ocr_options(&self) -> OcrOptions {
+        match self {
+            BoundsArea::Ping | BoundsArea::Screen => OcrOptions::default(),
+            BoundsArea::Toast | BoundsArea::ReviveDialog => OcrOptions {
+                scale: 2,
+                ..Default::default()
+            },
+            BoundsArea::SelectName | BoundsArea::MobElement => OcrOptions {
+                scale: 2,
+                single_line: true,
+                ..Default::default()
+            },
+            BoundsArea::MobLevel => OcrOptions {
+                scale: 3,
+                single_line: true,
+                whitelist: Some("0123456789"),
+                ..Default::default()
+            },
+            BoundsArea::Experience => OcrOptions {
+                scale: 3,
+                single_line: true,
+                whitelist: Some("0123456789.%"),
+                ..Default::default()
+            },
+            BoundsArea::Position => OcrOptions {
+                scale: 3,
+                single_line: true,
+                whitelist: Some("0123456789., "),
+                ..Default::default()
+            },
         }
     }
 }
@@ -58,72 +167,93 @@ impl BoundsArea {
 #[derive(Debug, Clone)]
 pub struct ImageAnalyzer {
     image: Option<ImageBuffer>,
-    pub window_id: u64,
+    captured_at: Option<Instant>,
+    detections: HashMap<Detection, PointCloud>,
+    frame_source: Arc<Mutex<dyn FrameSource>>,
+    ocr: Arc<Mutex<OcrEngine>>,
+    toasts: ToastReader,
+    buffs: BuffTray,
+    slot_bar: SlotBarReader,
+    calibration: Option<CalibrationConfig>,
+    /// Out of game screen text, read while no status bar is shown
+    screen_text: ThrottledText,
+    /// Revive dialog text, read while the character looks dead
+    revive_text: ThrottledText,
+    clock: Arc<dyn Clock>,
     pub client_stats: ClientStats,
     pub disconnect_count: i8,
     pub is_disconnect: bool,
 }
 
 impl ImageAnalyzer {
-    pub fn new(window: &Window) -> Self {
+    pub fn new<S>(frame_source: S, clock: Arc<dyn Clock>) -> Self
+    where
+        S: FrameSource + 'static,
+    {
         Self {
-            window_id: 0,
             image: None,
-            client_stats: ClientStats::new(window.to_owned()),
+            captured_at: None,
+            detections: HashMap::new(),
+            frame_source: Arc::new(Mutex::new(frame_source)),
+            ocr: Arc::new(Mutex::new(OcrEngine::new("eng"))),
+            toasts: ToastReader::default(),
+            buffs: BuffTray::default(),
+            slot_bar: SlotBarReader::default(),
+            calibration: None,
+            screen_text: ThrottledText::new(SCREEN_READ_INTERVAL),
+            revive_text: ThrottledText::new(SCREEN_READ_INTERVAL),
+            client_stats: ClientStats::new(&*clock),
+            clock,
             disconnect_count: 0,
             is_disconnect: false,
         }
     }
 
-    pub fn image_is_some(&self) -> bool {
-        self.image.is_some()
+    /// Get the last captured frame.
+    pub fn image(&self) -> Option<&ImageBuffer> {
+        self.image.as_ref()
     }
 
-    pub fn capture_window(&mut self, logger: &Logger, _config: &FarmingConfig) {
-        let _timer = Timer::start_new("capture_window");
-        if self.window_id == 0 {
-            return;
-        }
+    /// Get the time the last frame was captured at.
+    pub fn captured_at(&self) -> Option<Instant> {
+        self.captured_at
+    }
 
-        if let Some(provider) = libscreenshot::get_window_capture_provider() {
-            if let Ok(image) = provider.capture_window(self.window_id) {
-                self.image = Some(image);
-            } else {
-                slog::warn!(logger, "Failed to capture window"; "window_id" => self.window_id);
-            }
-        }
+    pub fn image_is_some(&self) -> bool {
+        self.image.is_some()
     }
 
-    fn rgba8_to_rgb8(
-        input: image::ImageBuffer<Rgba<u8>, Vec<u8>>,
-    ) -> image::ImageBuffer<Rgb<u8>, Vec<u8>> {
-        let width = input.width() as usize;
-        let height = input.height() as usize;
+    /// Replace the source frames are captured from.
+    pub fn set_frame_source<S>(&mut self, frame_source: S)
+    where
+        S: FrameSource + 'static,
+    {
+        self.frame_source = Arc::new(Mutex::new(frame_source));
+    }
 
-        // Get the raw image data as a vector
-        let input: &Vec<u8> = input.as_raw();
+    /// Use the located bars and areas instead of the default ones.
+    pub fn set_calibration(&mut self, calibration: Option<CalibrationConfig>) {
+        self.calibration = calibration;
+    }
 
-        // Allocate a new buffer for the RGB image, 3 bytes per pixel
-        let mut output_data = vec![0u8; width * height * 3];
+    pub fn capture_frame(&mut self, logger: &Logger, config: &FarmingConfig) {
+        let _timer = Timer::start_new("capture_frame");
 
-        let mut i = 0;
-        // Iterate through 4-byte chunks of the image data (RGBA bytes)
-        for chunk in input.chunks(4) {
-            // ... and copy each of them to output, leaving out the A byte
-            output_data[i..i + 3].copy_from_slice(&chunk[0..3]);
-            i += 3;
+        if let Some(image) = self.frame_source.lock().capture(logger) {
+            self.detections = Self::detect_pixels(&image, config, self.calibration.as_ref());
+            self.buffs.update(&image);
+            self.slot_bar.update(&image, self.clock.now());
+            self.image = Some(image);
+            self.captured_at = Some(self.clock.now());
         }
 
-        // Construct a new image
-        image::ImageBuffer::from_raw(width as u32, height as u32, output_data).unwrap()
+        if let Some(error) = self.ocr.lock().take_init_error() {
+            slog::error!(logger, "Failed to initialize OCR, text can't be read"; "error" => error);
+        }
     }
 
     pub fn detect_disconnect(&mut self, logger: &Logger) {
-        let image = self.image.as_ref().unwrap();
-        // let un_image = self.image.unwrap();
-        let text = self
-            .perform_ocr(image, BoundsArea::Ping, Some("eng"))
-            .unwrap();
+        let text = self.read_text(BoundsArea::Ping).unwrap_or_default();
 
         if self.disconnect_count > 10 {
             self.is_disconnect = true;
@@ -165,117 +295,288 @@ impl ImageAnalyzer {
         }
     }
 
-    fn perform_ocr(
-        &self,
-        image_buffer: &ImageBuffer,
-        area: BoundsArea,
-        lang: Option<&str>,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        // Initialize the Tesseract API
-        let mut tesseract: Tesseract = Tesseract::new(None, Some("eng")).unwrap();
-        if let Some(real_lang) = lang {
-            tesseract = Tesseract::new(None, lang).unwrap();
+    /// Figure out which screen the client shows.
+    pub fn detect_connection_state(&mut self, logger: &Logger) -> ConnectionState {
+        self.detect_disconnect(logger);
+        if self.is_disconnect {
+            return ConnectionState::Disconnected;
         }
 
-        let rgb_image = Self::rgba8_to_rgb8(image_buffer.clone());
-        // rgb_image.save("screen.png").expect("save error");
-        // Convert the ImageBuffer to a DynamicImage
-        let mut raw = DynamicImage::ImageRgb8(rgb_image);
+        // Status bars are only shown in game
+        let stats = &self.client_stats;
+        if stats.hp.value > 0 || stats.mp.value > 0 || stats.fp.value > 0 {
+            self.screen_text.reset();
+            return ConnectionState::InGame;
+        }
+        guard!(let Some(image) = self.image.as_ref() else {
+            return ConnectionState::InGame;
+        });
+
+        let now = self.clock.now();
+        if self.screen_text.is_due(now) {
+            let text = self.read_text(BoundsArea::Screen);
+            self.screen_text.update(text, now);
+        }
+        let text = self.screen_text.text().unwrap_or_default();
+        if let Some(state) = connection_detector::classify_screen_text(text) {
+            slog::debug!(logger, "Out of game screen"; "state" => format!("{:?}", state));
+            state
+        } else if connection_detector::is_dark_screen(image) {
+            ConnectionState::Loading
+        } else {
+            ConnectionState::InGame
+        }
+    }
+
+    /// Read the text of an area with its default preprocessing.
+    pub fn read_text(&self, area: BoundsArea) -> Option<String> {
+        self.read_text_with(area, &area.ocr_options())
+    }
+
+    /// Read the text of an area with the given preprocessing.
+    pub fn read_text_with(&self, area: BoundsArea, options: &OcrOptions) -> Option<String> {
+        let _timer = Timer::start_new("read_text");
+        let image = self.image.as_ref()?;
+        self.ocr
+            .lock()
+            .read(image, self.area_bounds(area), options)
+            .ok()
+    }
+
+    /// Bounds of an area in the last frame, as calibrated if it was located.
+    pub fn area_bounds(&self, area: BoundsArea) -> Bounds {
+        Self::locate_area(area, &self.frame_layout(), self.calibration.as_ref())
+    }
 
-        let _width: u32 = raw.width();
-        let _height: u32 = raw.height();
+    /// Size of the last frame.
+    pub fn frame_layout(&self) -> FrameLayout {
+        self.image
+            .as_ref()
+            .map(|image| FrameLayout::new(image.width(), image.height()))
+            .unwrap_or_default()
+    }
 
-        let bounds = area.to_rect();
-        let corp_img =
-            image::imageops::crop(&mut raw, bounds.x, bounds.y, bounds.w, bounds.h).to_image();
-        // let corp_img = image::imageops::crop(&mut raw,0,0,_width,_height).to_image();
+    fn locate_area(
+        area: BoundsArea,
+        layout: &FrameLayout,
+        calibration: Option<&CalibrationConfig>,
+    ) -> Bounds {
+        let (bounds, from) = calibration
+            .and_then(|calibration| Some((calibration.area(area)?, calibration.frame_layout())))
+            .unwrap_or_else(|| (area.to_rect(), FrameLayout::default()));
+        layout.move_bounds(bounds, area.anchor(), &from)
+    }
 
-        // let luma_corp = DynamicImage::ImageRgba8(corp_img).into_luma8();
-        // let adapt_corp = adaptive_threshold(&luma_corp, 123);
+    /// Search region and colors of a bar, as calibrated if it was located.
+    fn locate_status_bar(
+        kind: StatusBarKind,
+        layout: &FrameLayout,
+        calibration: Option<&CalibrationConfig>,
+    ) -> StatusBarConfig {
+        let (bar, from) = calibration
+            .and_then(|calibration| {
+                Some((calibration.status_bar(kind)?, calibration.frame_layout()))
+            })
+            .unwrap_or_else(|| (kind.into(), FrameLayout::default()));
+        bar.moved(kind.anchor(), &from, layout)
+    }
 
-        // corp_img.save("crop.png").unwrap();
-        // Convert the DynamicImage to a leptonica Pix object
-        // let pix = leptonica::Pix::from_dynamic_image(&dynamic_image);
-        let mut bytes: Vec<u8> = Vec::new();
-        let _ = corp_img.write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png);
+    /// The stat tray with the bars and the experience.
+    fn status_tray_bounds(layout: &FrameLayout, calibration: Option<&CalibrationConfig>) -> Bounds {
+        let bars = [StatusBarKind::Hp, StatusBarKind::Mp, StatusBarKind::Fp]
+            .map(|kind| Self::locate_status_bar(kind, layout, calibration));
+        let experience = Self::locate_area(BoundsArea::Experience, layout, calibration);
+        let right = bars.iter().map(|bar| bar.max_x).max().unwrap_or_default();
+        let bottom = bars.iter().map(|bar| bar.max_y).max().unwrap_or_default();
+        Bounds::new(
+            0,
+            0,
+            right + STATUS_TRAY_MARGIN,
+            bottom.max(experience.y + experience.h),
+        )
+    }
 
-        // Set the image for OCR
-        let text: String = tesseract
-            .set_image_from_mem(&bytes)
-            .unwrap()
-            .recognize()
-            .unwrap()
-            .get_text()
-            .unwrap();
-        Ok(text)
+    /// Read the name and level of the selected target.
+    pub fn read_target_info(&self) -> Option<TargetInfo> {
+        let name = self.read_text(BoundsArea::SelectName)?;
+        let level = self.read_text(BoundsArea::MobLevel).unwrap_or_default();
+        TargetInfo::parse(&name, &level)
     }
 
-    pub fn pixel_detection(
-        &self,
-        colors: Vec<Color>,
-        min_x: u32,
-        min_y: u32,
-        mut max_x: u32,
-        mut max_y: u32,
-        tolerence: Option<u8>,
-    ) -> Receiver<Point> {
-        let (snd, recv) = sync_channel::<Point>(4096);
-        let image = self.image.as_ref().unwrap();
+    /// Read the toast area and return the messages that appeared since the last read.
+    pub fn read_game_events(&mut self) -> Vec<GameEvent> {
+        let now = self.clock.now();
+        if !self.toasts.should_read(now) {
+            return vec![];
+        }
 
-        let dimension = image.dimensions();
-        let content = image.as_raw();
+        let text = self.read_text(BoundsArea::Toast).unwrap_or_default();
+        self.toasts.read(&text, now)
+    }
+
+    /// Read the experience percentage of the current level.
+    pub fn read_experience(&self) -> Option<f32> {
+        let text = self.read_text(BoundsArea::Experience)?;
+        let value = text
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == '.')
+            .collect::<String>()
+            .parse::<f32>()
+            .ok()?;
+        (0.0..=100.0).contains(&value).then(|| value)
+    }
+
+    /// Read the coordinates of the character.
+    pub fn read_position(&self) -> Option<WorldPoint> {
+        WorldPoint::parse(&self.read_text(BoundsArea::Position)?)
+    }
+
+    /// Find the player arrow and the mobs on the minimap of the last frame.
+    pub fn read_minimap(&self) -> Option<MinimapInfo> {
+        let image = self.image.as_ref()?;
+        minimap::read_minimap(image, minimap::minimap_bounds(&self.frame_layout()))
+    }
+
+    /// Cooldowns read from the last frame, which showed `slot_bar`.
+    pub fn slot_observations(
+        &mut self,
+        slot_bar: Option<usize>,
+        last_usage: &[[Option<Instant>; 10]; 9],
+    ) -> SlotObservations {
+        self.slot_bar.observations(slot_bar, last_usage)
+    }
+
+    /// What to do about the first buff slot whose buff isn't in the buff tray.
+    pub fn next_buff_action(&mut self, slot_indexes: Vec<(usize, usize)>) -> Option<BuffAction> {
+        self.buffs.next_action(slot_indexes, self.clock.now())
+    }
 
-        if max_x == 0 {
-            max_x = image.width();
+    /// Look for the grey death tint and the revive dialog.
+    pub fn detect_death(&mut self) -> DeathSignals {
+        guard!(let Some(image) = self.image.as_ref() else {
+            return DeathSignals::default();
+        });
+        let grey_screen = death_detector::is_grey_screen(image);
+
+        // Only read the dialog once something already looks wrong
+        if !grey_screen && self.client_stats.hp.value > 0 {
+            self.revive_text.reset();
+            return DeathSignals {
+                grey_screen,
+                revive_dialog: false,
+            };
+        }
+        let now = self.clock.now();
+        if self.revive_text.is_due(now) {
+            let text = self.read_text(BoundsArea::ReviveDialog);
+            self.revive_text.update(text, now);
+        }
+        let revive_dialog = self
+            .revive_text
+            .text()
+            .map_or(false, death_detector::is_revive_dialog);
+
+        DeathSignals {
+            grey_screen,
+            revive_dialog,
         }
+    }
+
+    /// Update the life state of the character from the current frame.
+    pub fn update_life_state(&mut self, input: &dyn InputSink) -> LifeState {
+        let signals = self.detect_death();
+        self.client_stats.update_life_state(signals, input)
+    }
 
-        if max_y == 0 {
-            max_y = image.height();
+    /// Scan the frame once for every detected color: status bars, target markers and mob names.
+    fn detect_pixels(
+        image: &ImageBuffer,
+        config: &FarmingConfig,
+        calibration: Option<&CalibrationConfig>,
+    ) -> HashMap<Detection, PointCloud> {
+        let _timer = Timer::start_new("detect_pixels");
+        let mut scan = PixelScan::default();
+        let layout = FrameLayout::new(image.width(), image.height());
+
+        for kind in [
+            StatusBarKind::Hp,
+            StatusBarKind::Mp,
+            StatusBarKind::Fp,
+            StatusBarKind::TargetHP,
+            StatusBarKind::TargetMP,
+        ] {
+            let bar = Self::locate_status_bar(kind, &layout, calibration);
+            scan.add(
+                Detection::StatusBar(kind),
+                PixelQuery::new(bar.refs, 2).within(bar.min_x, bar.min_y, bar.max_x, bar.max_y),
+            );
         }
 
-        image
-            .enumerate_rows()
-            .par_bridge()
-            .for_each(move |(y, row)| {
-                // Skip this row if it's in an ignored area
-                let image_height = image.height();
-                #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
-                if y <= IGNORE_AREA_TOP
-                    || y > image_height
-                        .checked_sub(IGNORE_AREA_BOTTOM)
-                        .unwrap_or(image_height)
-                    || y > IGNORE_AREA_TOP + max_y
-                    || y > max_y
-                    || y < min_y
-                {
-                    return;
-                }
+        scan.add(
+            Detection::TargetMarker,
+            PixelQuery::new(vec![Color::new(246, 90, 106)], 5),
+        );
+        scan.add(
+            Detection::BlankTargetMarker,
+            PixelQuery::new(vec![Color::new(164, 180, 226)], 5),
+        );
 
-                // Loop over columns
-                'outer: for (x, _, px) in row {
-                    if px.0[3] != 255 || x >= max_x {
-                        return;
-                    } else if x < min_x {
-                        continue;
-                    }
-
-                    for ref_color in colors.iter() {
-                        // Check if the pixel matches any of the reference colors
-                        if Self::pixel_matches(&px.0, &ref_color.refs, tolerence.unwrap_or(5)) {
-                            #[allow(dropping_copy_types)]
-                            drop(snd.send(Point::new(x, y)));
-
-                            // Continue to next column
-                            continue 'outer;
-                        }
-                    }
-                }
-            });
-        recv
+        // Reference colors
+        let ref_color_pas_wrapped: [Option<u8>; 3] = config.passive_mobs_colors(); // Passive mobs 234, 234, 149
+        let ref_color_agg_wrapped: [Option<u8>; 3] = config.aggressive_mobs_colors(); // Aggro mobs 179, 23, 23
+        let ref_color_pas = Color::new(
+            ref_color_pas_wrapped[0].unwrap_or(234),
+            ref_color_pas_wrapped[1].unwrap_or(234),
+            ref_color_pas_wrapped[2].unwrap_or(149),
+        );
+        let ref_color_agg = Color::new(
+            ref_color_agg_wrapped[0].unwrap_or(179),
+            ref_color_agg_wrapped[1].unwrap_or(23),
+            ref_color_agg_wrapped[2].unwrap_or(23),
+        );
+
+        // avoid detect the health bar as a monster
+        let status_tray = Self::status_tray_bounds(&layout, calibration);
+        scan.add(
+            Detection::Mob(MobType::Passive),
+            PixelQuery::new(vec![ref_color_pas], config.passive_tolerence()).excluding(status_tray),
+        );
+        scan.add(
+            Detection::Mob(MobType::Aggressive),
+            PixelQuery::new(vec![ref_color_agg], config.aggressive_tolerence())
+                .excluding(status_tray),
+        );
+
+        scan.run(image)
+    }
+
+    /// Points of the last frame matching the given detection.
+    fn detected_points(&self, detection: Detection) -> PointCloud {
+        self.detections.get(&detection).cloned().unwrap_or_default()
+    }
+
+    /// Bounds of the given status bar in the last frame.
+    pub fn status_bar_bounds(&self, kind: StatusBarKind) -> Bounds {
+        Self::bar_bounds(&self.detections, kind)
+    }
+
+    fn bar_bounds(detections: &HashMap<Detection, PointCloud>, kind: StatusBarKind) -> Bounds {
+        detections
+            .get(&Detection::StatusBar(kind))
+            .map(PointCloud::to_bounds)
+            .unwrap_or_default()
+    }
+
+    /// Update the status bar values from the pixels the frame scan found.
+    pub fn update_client_stats(&mut self, logger: &Logger) {
+        let detections = &self.detections;
+        let now = self.clock.now();
+        self.client_stats
+            .update(|kind| Self::bar_bounds(detections, kind), now, logger);
     }
 
     fn merge_cloud_into_mobs(
-        config: Option<&FarmingConfig>,
+        settings: Option<&MobNameSettings>,
         cloud: &PointCloud,
         mob_type: TargetType,
         //ignore_size: bool,
@@ -283,34 +584,37 @@ impl ImageAnalyzer {
         let _timer = Timer::start_new("merge_cloud_into_mobs");
 
         // Max merge distance
-        let max_distance_x: u32 = 50;
-        let max_distance_y: u32 = 3;
-
-        // Cluster coordinates in x-direction
-        let x_clusters = cloud.cluster_by_distance(max_distance_x, point_selector::x_axis);
-
-        let mut xy_clusters = Vec::default();
-        for x_cluster in x_clusters {
-            // Cluster current x-cluster coordinates in y-direction
-            let local_y_clusters =
-                x_cluster.cluster_by_distance(max_distance_y, point_selector::y_axis);
-            // Extend final xy-clusters with local y-clusters
-            xy_clusters.extend(local_y_clusters.into_iter());
-        }
-
-        // Create mobs from clusters
-        xy_clusters
+        let default_settings = FarmingConfig::default().mob_name_settings();
+        let gap_settings = settings.unwrap_or(&default_settings);
+        let max_gap_x = gap_settings.gap_x;
+        let max_gap_y = gap_settings.gap_y;
+
+        // Cluster connected coordinates in both directions at once
+        cloud
+            .cluster_connected(max_gap_x, max_gap_y)
             .into_iter()
-            .map(|cluster| Target {
-                target_type: mob_type,
-                bounds: cluster.to_bounds(),
+            .map(|cluster| {
+                let bounds = cluster.to_bounds();
+                // Giants have longer names than regular mobs
+                let giant_width = settings.map(|s| s.giant_width).unwrap_or(0);
+                let rank = if giant_width > 0 && bounds.w >= giant_width {
+                    MobRank::Giant
+                } else {
+                    MobRank::Normal
+                };
+                Target {
+                    target_type: mob_type,
+                    bounds,
+                    rank,
+                    track_id: None,
+                }
             })
             .filter(|mob| {
-                if let Some(config) = config {
+                if let Some(settings) = settings {
                     // Filter out small clusters (likely to cause misclicks)
-                    mob.bounds.w > config.min_mobs_name_width()
+                    mob.bounds.w > settings.min_width
                     // Filter out huge clusters (likely to be Violet Magician Troupe)
-                    && mob.bounds.w < config.max_mobs_name_width()
+                    && mob.bounds.w < settings.max_width
                 } else {
                     true
                 }
@@ -318,87 +622,19 @@ impl ImageAnalyzer {
             .collect()
     }
 
-    /// Check if pixel `c` matches reference pixel `r` with the given `tolerance`.
-    #[inline(always)]
-    fn pixel_matches(c: &[u8; 4], r: &[u8; 3], tolerance: u8) -> bool {
-        let matches_inner = |a: u8, b: u8| match (a, b) {
-            (a, b) if a == b => true,
-            (a, b) if a > b => a.saturating_sub(b) <= tolerance,
-            (a, b) if a < b => b.saturating_sub(a) <= tolerance,
-            _ => false,
-        };
-        let perm = [(c[0], r[0]), (c[1], r[1]), (c[2], r[2])];
-        perm.iter().all(|&(a, b)| matches_inner(a, b))
-    }
-
-    pub fn identify_mobs(&self, config: &FarmingConfig) -> Vec<Target> {
+    /// Mob names are detected with the colors of the config given to `capture_frame`.
+    pub fn identify_mobs(&self, settings: &MobNameSettings) -> Vec<Target> {
         let _timer = Timer::start_new("identify_mobs");
 
-        // Create collections for passive and aggro mobs
-        let mut mob_coords_pas: Vec<Point> = Vec::default();
-        let mut mob_coords_agg: Vec<Point> = Vec::default();
-
-        // Reference colors
-        let ref_color_pas_wrapped: [Option<u8>; 3] = config.passive_mobs_colors(); // Passive mobs 234, 234, 149
-        let ref_color_agg_wrapped: [Option<u8>; 3] = config.aggressive_mobs_colors(); // Aggro mobs 179, 23, 23
-        let ref_color_pas: [u8; 3] = [
-            ref_color_pas_wrapped[0].unwrap_or(234),
-            ref_color_pas_wrapped[1].unwrap_or(234),
-            ref_color_pas_wrapped[2].unwrap_or(149),
-        ];
-        let ref_color_agg: [u8; 3] = [
-            ref_color_agg_wrapped[0].unwrap_or(179),
-            ref_color_agg_wrapped[1].unwrap_or(23),
-            ref_color_agg_wrapped[2].unwrap_or(23),
-        ];
-
-        // Collect pixel clouds
-        struct MobPixel(u32, u32, TargetType);
-        let (snd, recv) = sync_channel::<MobPixel>(4096);
-        let image = self.image.as_ref().unwrap();
-        image
-            .enumerate_rows()
-            .par_bridge()
-            .for_each(move |(y, row)| {
-                #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
-                if y <= IGNORE_AREA_TOP || y > image.height() - IGNORE_AREA_BOTTOM {
-                    return;
-                }
-                for (x, _, px) in row {
-                    if px.0[3] != 255 {
-                        return;
-                    } else if x <= 250 && y <= 110 {
-                        // avoid detect the health bar as a monster
-                        continue;
-                    }
-                    if Self::pixel_matches(&px.0, &ref_color_pas, config.passive_tolerence()) {
-                        drop(snd.send(MobPixel(x, y, TargetType::Mob(MobType::Passive))));
-                    } else if Self::pixel_matches(
-                        &px.0,
-                        &ref_color_agg,
-                        config.aggressive_tolerence(),
-                    ) {
-                        drop(snd.send(MobPixel(x, y, TargetType::Mob(MobType::Aggressive))));
-                    }
-                }
-            });
-        while let Ok(px) = recv.recv() {
-            match px.2 {
-                TargetType::Mob(MobType::Passive) => mob_coords_pas.push(Point::new(px.0, px.1)),
-                TargetType::Mob(MobType::Aggressive) => mob_coords_agg.push(Point::new(px.0, px.1)),
-                _ => unreachable!(),
-            }
-        }
-
         // Categorize mobs
         let mobs_pas = Self::merge_cloud_into_mobs(
-            Some(config),
-            &PointCloud::new(mob_coords_pas),
+            Some(settings),
+            &self.detected_points(Detection::Mob(MobType::Passive)),
             TargetType::Mob(MobType::Passive),
         );
         let mobs_agg = Self::merge_cloud_into_mobs(
-            Some(config),
-            &PointCloud::new(mob_coords_agg),
+            Some(settings),
+            &self.detected_points(Detection::Mob(MobType::Aggressive)),
             TargetType::Mob(MobType::Aggressive),
         );
 
@@ -408,28 +644,16 @@ impl ImageAnalyzer {
 
     pub fn identify_target_marker(&self, blank_target: bool) -> Option<Target> {
         let _timer = Timer::start_new("identify_target_marker");
-        let mut coords = Vec::default();
-
-        // Reference color
-        let ref_color: Color = {
-            if !blank_target {
-                Color::new(246, 90, 106)
-            } else {
-                Color::new(164, 180, 226)
-            }
-        };
 
         // Collect pixel clouds
-        let recv = self.pixel_detection(vec![ref_color], 0, 0, 0, 0, None);
-
-        // Receive points from channel
-        while let Ok(point) = recv.recv() {
-            coords.push(point);
-        }
+        let coords = if !blank_target {
+            self.detected_points(Detection::TargetMarker)
+        } else {
+            self.detected_points(Detection::BlankTargetMarker)
+        };
 
         // Identify target marker entities
-        let target_markers =
-            Self::merge_cloud_into_mobs(None, &PointCloud::new(coords), TargetType::TargetMarker);
+        let target_markers = Self::merge_cloud_into_mobs(None, &coords, TargetType::TargetMarker);
 
         if !blank_target && target_markers.is_empty() {
             return self.identify_target_marker(true);
@@ -454,84 +678,34 @@ impl ImageAnalyzer {
 
         (((mid_x - point.x as i32).pow(2) + (mid_y - point.y as i32).pow(2)) as f64).sqrt() as i32
     }
-    /// Distance: `[0..=500]`
-    pub fn find_closest_mob<'a>(
-        &self,
-        mobs: &'a [Target],
-        //avoid_bounds: Option<&Bounds>,
-        avoid_list: Option<&Vec<(Bounds, Instant, u128)>>,
-        max_distance: i32,
-        _logger: &Logger,
-    ) -> Option<&'a Target> {
-        let _timer = Timer::start_new("find_closest_mob");
-
+    /// Where the player stands on screen.
+    pub fn screen_center(&self) -> Point {
         let image = self.image.as_ref().unwrap();
+        Point::new(image.width() / 2, image.height() / 2)
+    }
 
-        // Calculate middle point of player
-        let mid_x = (image.width() / 2) as i32;
-        let mid_y = (image.height() / 2) as i32;
-
-        // Calculate 2D euclidian distances to player
-        let mut distances = Vec::default();
-        for mob in mobs {
-            let point = mob.get_attack_coords();
-            let distance = (((mid_x - point.x as i32).pow(2) + (mid_y - point.y as i32).pow(2))
-                as f64)
-                .sqrt() as i32;
-            distances.push((mob, distance));
-        }
-
-        // Sort by distance
-        distances.sort_by_key(|&(_, distance)| distance);
-        let fdistances = distances.clone();
-        if distances.len() > 1 {
-            // Remove mobs that are too far away
-            distances = distances
-                .into_iter()
-                .filter(|&(mob, distance)| {
-                    distance
-                        <= match mob.target_type {
-                            TargetType::Mob(mob_type) => match mob_type {
-                                MobType::Passive => max_distance,
-                                MobType::Aggressive => max_distance / 2,
-                            },
-                            TargetType::TargetMarker => max_distance,
-                        }
-                })
-                .collect();
-            if distances.len() <= 1 {
-                distances = fdistances.clone();
-            }
-        }
+    /// Pick the candidate with the best score, ignoring the search range if nothing is in it.
+    pub fn find_best_mob<'a>(
+        &self,
+        candidates: &'a [Target],
+        scorer: &dyn TargetScorer,
+        context: &ScoringContext,
+    ) -> Option<&'a Target> {
+        let _timer = Timer::start_new("find_best_mob");
+
+        let best = |context: &ScoringContext| {
+            candidates
+                .iter()
+                .filter_map(|mob| scorer.score(mob, context).map(|score| (mob, score)))
+                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                .map(|(mob, _)| mob)
+        };
 
-        if let Some(avoided_bounds) = avoid_list {
-            // Try finding closest mob that's not the mob to be avoided
-            if let Some((mob, _distance)) = distances.iter().find(|(mob, _distance)| {
-                //*distance > 55
-                let coords = mob.get_attack_coords();
-                let mut result = true;
-                for avoided_item in avoided_bounds {
-                    if avoided_item.0.contains_point(&coords) {
-                        //slog::debug!(logger, ""; "Avoided bounds" => avoided_item.0);
-                        result = false;
-                        break;
-                    }
-                }
-                result // && *distance > 20
-                       // let coords = mob.name_bounds.get_lowest_center_point();
-                       // !avoid_bounds.grow_by(100).contains_point(&coords) && *distance > 200
-            }) {
-                Some(mob)
-            } else {
-                None
-            }
-        } else {
-            // Return closest mob
-            if let Some((mob, _distance)) = distances.first() {
-                Some(*mob)
-            } else {
-                None
-            }
-        }
+        best(context).or_else(|| {
+            best(&ScoringContext {
+                limit_range: false,
+                ..*context
+            })
+        })
     }
 }
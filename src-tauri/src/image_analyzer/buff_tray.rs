@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use libscreenshot::ImageBuffer;
+
+use super::slot_bar::slot_bounds;
+use crate::data::{Anchor, Bounds, FrameLayout};
+
+/// Buff tray of an 800x600 client, under the stat tray.
+const BUFF_TRAY: Bounds = Bounds {
+    x: 4,
+    y: 132,
+    w: 234,
+    h: 52,
+};
+const BUFF_ICON_SIZE: u32 = 24;
+const BUFF_ICON_GAP: u32 = 2;
+
+/// Icons are compared on a grid of this many cells per side.
+const SIGNATURE_SIZE: u32 = 8;
+/// Icon frames are left out of the comparison.
+const ICON_BORDER: u32 = 2;
+/// Cells darker than this are empty.
+const MIN_ICON_BRIGHTNESS: u8 = 40;
+/// Mean difference of two matching icons, as a share of the full color range.
+const MAX_ICON_DIFFERENCE: f32 = 0.12;
+/// A buff that was just cast takes a moment to show up in the tray.
+const BUFF_APPEAR_DELAY: Duration = Duration::from_millis(3000);
+
+/// Average colors of an icon on a small grid, so icons of any size can be compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconSignature {
+    cells: Vec<[u8; 3]>,
+}
+
+impl IconSignature {
+    /// Signature of the icon in `bounds`, `None` if there is no icon.
+    pub fn of(image: &ImageBuffer, bounds: Bounds) -> Option<Self> {
+        let inner = Bounds::new(
+            bounds.x + ICON_BORDER,
+            bounds.y + ICON_BORDER,
+            bounds.w.checked_sub(ICON_BORDER * 2)?,
+            bounds.h.checked_sub(ICON_BORDER * 2)?,
+        );
+        let (width, height) = image.dimensions();
+        if inner.w < SIGNATURE_SIZE
+            || inner.h < SIGNATURE_SIZE
+            || inner.x + inner.w > width
+            || inner.y + inner.h > height
+        {
+            return None;
+        }
+
+        let mut cells = Vec::with_capacity((SIGNATURE_SIZE * SIGNATURE_SIZE) as usize);
+        for row in 0..SIGNATURE_SIZE {
+            for column in 0..SIGNATURE_SIZE {
+                let (x0, x1) = (
+                    inner.x + inner.w * column / SIGNATURE_SIZE,
+                    inner.x + inner.w * (column + 1) / SIGNATURE_SIZE,
+                );
+                let (y0, y1) = (
+                    inner.y + inner.h * row / SIGNATURE_SIZE,
+                    inner.y + inner.h * (row + 1) / SIGNATURE_SIZE,
+                );
+                let mut sum = [0u32; 3];
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let [r, g, b, _] = image.get_pixel(x, y).0;
+                        sum[0] += r as u32;
+                        sum[1] += g as u32;
+                        sum[2] += b as u32;
+                    }
+                }
+                let count = ((x1 - x0) * (y1 - y0)).max(1);
+                cells.push(sum.map(|channel| (channel / count) as u8));
+            }
+        }
+
+        let brightness = cells
+            .iter()
+            .map(|cell| *cell.iter().max().unwrap() as u32)
+            .sum::<u32>()
+            / cells.len() as u32;
+        (brightness >= MIN_ICON_BRIGHTNESS as u32).then(|| Self { cells })
+    }
+
+    /// Mean difference with another icon, from 0 to 1.
+    pub fn difference(&self, other: &IconSignature) -> f32 {
+        let total = self
+            .cells
+            .iter()
+            .zip(&other.cells)
+            .flat_map(|(a, b)| a.iter().zip(b).map(|(a, b)| (a.max(b) - a.min(b)) as u32))
+            .sum::<u32>();
+        total as f32 / (self.cells.len() * 3 * 255) as f32
+    }
+
+    pub fn matches(&self, other: &IconSignature) -> bool {
+        self.difference(other) <= MAX_ICON_DIFFERENCE
+    }
+}
+
+/// What is known about the buff of a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuffState {
+    /// The icon of the slot wasn't captured yet
+    Unknown,
+    /// The slot has no usable icon, the buff can't be watched
+    Untracked,
+    Active,
+    /// Not in the tray, icons blink before the buff expires
+    Missing,
+}
+
+/// What to do about the first buff that isn't active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuffAction {
+    /// Show the slot bar, the icon of its slot is captured from the next frame
+    ShowSlotBar(usize),
+    Cast((usize, usize)),
+}
+
+/// Watches the buff tray for the icons of the buff slots.
+#[derive(Debug, Clone, Default)]
+pub struct BuffTray {
+    references: HashMap<(usize, usize), Option<IconSignature>>,
+    pending: Vec<(usize, usize)>,
+    casts: HashMap<(usize, usize), Instant>,
+    visible: Vec<IconSignature>,
+}
+
+impl BuffTray {
+    /// Capture the icon of a slot from the next frame, its slot bar must be shown by then.
+    pub fn capture_slot_icon(&mut self, slot_index: (usize, usize)) {
+        if !self.pending.contains(&slot_index) {
+            self.pending.push(slot_index);
+        }
+    }
+
+    /// Read the buff tray and the pending slot icons.
+    pub fn update(&mut self, image: &ImageBuffer) {
+        let layout = FrameLayout::new(image.width(), image.height());
+        for slot_index in self.pending.drain(..) {
+            let bounds = slot_bounds(&layout, slot_index.1);
+            self.references
+                .insert(slot_index, IconSignature::of(image, bounds));
+        }
+
+        let tray = layout.place(BUFF_TRAY, Anchor::TopLeft);
+        let step = BUFF_ICON_SIZE + BUFF_ICON_GAP;
+        self.visible = (0..tray.h / step)
+            .flat_map(|row| (0..tray.w / step).map(move |column| (row, column)))
+            .filter_map(|(row, column)| {
+                let bounds = Bounds::new(
+                    tray.x + column * step,
+                    tray.y + row * step,
+                    BUFF_ICON_SIZE,
+                    BUFF_ICON_SIZE,
+                );
+                IconSignature::of(image, bounds)
+            })
+            .collect();
+    }
+
+    pub fn mark_cast(&mut self, slot_index: (usize, usize), now: Instant) {
+        self.casts.insert(slot_index, now);
+    }
+
+    /// Pick the first slot whose buff isn't active, expecting its icon or its cast.
+    pub fn next_action<I>(&mut self, slot_indexes: I, now: Instant) -> Option<BuffAction>
+    where
+        I: IntoIterator<Item = (usize, usize)>,
+    {
+        for slot_index in slot_indexes {
+            match self.state(slot_index, now) {
+                BuffState::Active => continue,
+                BuffState::Unknown => {
+                    self.capture_slot_icon(slot_index);
+                    return Some(BuffAction::ShowSlotBar(slot_index.0));
+                }
+                BuffState::Untracked | BuffState::Missing => {
+                    self.mark_cast(slot_index, now);
+                    return Some(BuffAction::Cast(slot_index));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn state(&self, slot_index: (usize, usize), now: Instant) -> BuffState {
+        let reference = match self.references.get(&slot_index) {
+            None => return BuffState::Unknown,
+            Some(None) => return BuffState::Untracked,
+            Some(Some(reference)) => reference,
+        };
+
+        let just_cast = self.casts.get(&slot_index).map_or(false, |cast| {
+            now.saturating_duration_since(*cast) < BUFF_APPEAR_DELAY
+        });
+        if just_cast || self.visible.iter().any(|icon| icon.matches(reference)) {
+            BuffState::Active
+        } else {
+            BuffState::Missing
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use image::{Rgba, RgbaImage};
+
+    use super::{BuffAction, BuffState, BuffTray};
+
+    /// Draw a two colored icon, split at a third of its width.
+    fn draw_icon(frame: &mut RgbaImage, x: u32, y: u32, size: u32, colors: [[u8; 3]; 2]) {
+        for dy in 0..size {
+            for dx in 0..size {
+                let [r, g, b] = colors[(dx * 3 >= size) as usize];
+                frame.put_pixel(x + dx, y + dy, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_buff_tray() {
+        let now = Instant::now();
+        let haste = [[230, 200, 40], [60, 90, 220]];
+        let heap_up = [[40, 200, 90], [220, 60, 60]];
+        let mut frame = RgbaImage::from_pixel(800, 600, Rgba([10, 10, 10, 255]));
+        // Slots 2 and 5 of the slot bar, 4 is empty
+        draw_icon(&mut frame, 304, 560, 32, haste);
+        draw_icon(&mut frame, 400, 560, 32, heap_up);
+
+        let mut tray = BuffTray::default();
+        assert_eq!(tray.state((0, 2), now), BuffState::Unknown);
+        for slot in [2, 4, 5] {
+            tray.capture_slot_icon((0, slot));
+        }
+        tray.update(&frame);
+        assert_eq!(tray.state((0, 2), now), BuffState::Missing);
+        assert_eq!(tray.state((0, 4), now), BuffState::Untracked);
+
+        // Buff icons are smaller than slot icons
+        draw_icon(&mut frame, 30, 132, 24, haste);
+        tray.update(&frame);
+        assert_eq!(tray.state((0, 2), now), BuffState::Active);
+        assert_eq!(tray.state((0, 5), now), BuffState::Missing);
+
+        // Buffs are expected to show up a moment after being cast
+        tray.mark_cast((0, 5), now);
+        assert_eq!(tray.state((0, 5), now), BuffState::Active);
+        let later = now + Duration::from_secs(5);
+        assert_eq!(tray.state((0, 5), later), BuffState::Missing);
+    }
+
+    #[test]
+    fn test_buff_actions() {
+        let now = Instant::now();
+        let haste = [[230, 200, 40], [60, 90, 220]];
+        let mut frame = RgbaImage::from_pixel(800, 600, Rgba([10, 10, 10, 255]));
+        draw_icon(&mut frame, 304, 560, 32, haste);
+        draw_icon(&mut frame, 30, 132, 24, haste);
+
+        // Icons are captured before anything is cast
+        let mut tray = BuffTray::default();
+        let slots = [(1, 2), (1, 4)];
+        assert_eq!(
+            tray.next_action(slots, now),
+            Some(BuffAction::ShowSlotBar(1))
+        );
+        tray.update(&frame);
+        assert_eq!(
+            tray.next_action(slots, now),
+            Some(BuffAction::ShowSlotBar(1))
+        );
+        tray.update(&frame);
+
+        // The active buff is skipped, the empty slot can't be watched so it's cast
+        assert_eq!(tray.next_action(slots, now), Some(BuffAction::Cast((1, 4))));
+        // Only its slot cooldown keeps it from being cast again
+        assert_eq!(tray.next_action(slots, now), Some(BuffAction::Cast((1, 4))));
+        assert_eq!(tray.next_action([(1, 2)], now), None);
+    }
+}
@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+
+use libscreenshot::ImageBuffer;
+use serde::{Deserialize, Serialize};
+
+use super::{BoundsArea, Color};
+use crate::data::{Bounds, FrameLayout, StatusBarConfig, StatusBarKind};
+
+/// Horizontal runs shorter than this are text or noise, not bars.
+const MIN_BAR_LENGTH: u32 = 16;
+const MIN_BAR_HEIGHT: u32 = 3;
+/// Free space kept around the located bars.
+const BAR_MARGIN: u32 = 3;
+/// Number of shades kept per bar, the most frequent ones.
+const BAR_SHADES: usize = 4;
+
+/// HP bars of an 800x600 client with the default UI scale, the default areas were measured against them.
+const REFERENCE_HP_BAR: Bounds = Bounds {
+    x: 108,
+    y: 36,
+    w: 114,
+    h: 8,
+};
+const REFERENCE_TARGET_HP_BAR: Bounds = Bounds {
+    x: 334,
+    y: 36,
+    w: 200,
+    h: 8,
+};
+
+/// Colors a status bar can be drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BarHue {
+    Red,
+    Blue,
+    Green,
+}
+
+impl BarHue {
+    fn matches(&self, [r, g, b]: [u8; 3]) -> bool {
+        let (r, g, b) = (r as i32, g as i32, b as i32);
+        match self {
+            BarHue::Red => r >= 140 && g <= 60 && b <= 100 && r > b + 60,
+            BarHue::Blue => b >= 150 && r <= 80 && b > g + 20,
+            BarHue::Green => g >= 100 && r <= 60 && b <= 80 && g > r + 60,
+        }
+    }
+}
+
+/// Where a status bar is and which shades it is drawn with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusBarCalibration {
+    pub kind: StatusBarKind,
+    /// Region searched for the bar, large enough to hold it when full
+    pub region: Bounds,
+    pub colors: Vec<Color>,
+}
+
+/// Client layout located in a frame, replaces the hard-coded bars and areas.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationConfig {
+    /// Size of the calibrated frame
+    pub frame_width: u32,
+    pub frame_height: u32,
+    pub status_bars: Vec<StatusBarCalibration>,
+    pub areas: Vec<(BoundsArea, Bounds)>,
+}
+
+impl CalibrationConfig {
+    /// Locate the status bars and text areas in a frame.
+    /// The stat tray must be open with full bars, a target is optional.
+    pub fn locate(image: &ImageBuffer) -> Option<Self> {
+        let (frame_width, frame_height) = image.dimensions();
+        let [hp, mp, fp] = find_stacked(image, &[BarHue::Red, BarHue::Blue, BarHue::Green], None)?;
+        let target = find_stacked(image, &[BarHue::Red, BarHue::Blue], Some(hp))
+            .map(|[target_hp, target_mp]| (target_hp, target_mp));
+
+        let mut calibration = Self {
+            frame_width,
+            frame_height,
+            ..Default::default()
+        };
+
+        // Bars may be a little short, each region holds the longest one of its group
+        let tray_width = hp.w.max(mp.w).max(fp.w);
+        for (kind, bar, hue) in [
+            (StatusBarKind::Hp, hp, BarHue::Red),
+            (StatusBarKind::Mp, mp, BarHue::Blue),
+            (StatusBarKind::Fp, fp, BarHue::Green),
+        ] {
+            calibration.add_bar(image, kind, bar, tray_width, hue);
+        }
+        for area in [BoundsArea::Ping, BoundsArea::Experience] {
+            calibration.add_area(area, REFERENCE_HP_BAR, hp);
+        }
+
+        if let Some((target_hp, target_mp)) = target {
+            let target_width = target_hp.w.max(target_mp.w);
+            calibration.add_bar(
+                image,
+                StatusBarKind::TargetHP,
+                target_hp,
+                target_width,
+                BarHue::Red,
+            );
+            calibration.add_bar(
+                image,
+                StatusBarKind::TargetMP,
+                target_mp,
+                target_width,
+                BarHue::Blue,
+            );
+            for area in [
+                BoundsArea::SelectName,
+                BoundsArea::MobElement,
+                BoundsArea::MobLevel,
+            ] {
+                calibration.add_area(area, REFERENCE_TARGET_HP_BAR, target_hp);
+            }
+        }
+
+        // Dialogs and messages only follow the frame size
+        let layout = calibration.frame_layout();
+        for area in [
+            BoundsArea::Toast,
+            BoundsArea::ReviveDialog,
+            BoundsArea::Screen,
+            BoundsArea::Position,
+        ] {
+            let bounds = layout.place(area.to_rect(), area.anchor());
+            calibration.areas.push((area, bounds));
+        }
+
+        Some(calibration)
+    }
+
+    /// Size of the calibrated frame.
+    pub fn frame_layout(&self) -> FrameLayout {
+        FrameLayout::new(self.frame_width, self.frame_height)
+    }
+
+    /// Search region and colors of a bar, `None` if it wasn't located.
+    pub fn status_bar(&self, kind: StatusBarKind) -> Option<StatusBarConfig> {
+        let bar = self.status_bars.iter().find(|bar| bar.kind == kind)?;
+        Some(StatusBarConfig {
+            min_x: bar.region.x,
+            min_y: bar.region.y,
+            max_x: bar.region.x + bar.region.w,
+            max_y: bar.region.y + bar.region.h,
+            refs: bar.colors.clone(),
+        })
+    }
+
+    /// Bounds of a text area, `None` if it wasn't located.
+    pub fn area(&self, area: BoundsArea) -> Option<Bounds> {
+        self.areas
+            .iter()
+            .find(|(located, _)| *located == area)
+            .map(|(_, bounds)| *bounds)
+    }
+
+    fn add_bar(
+        &mut self,
+        image: &ImageBuffer,
+        kind: StatusBarKind,
+        bar: Bounds,
+        full_width: u32,
+        hue: BarHue,
+    ) {
+        self.status_bars.push(StatusBarCalibration {
+            kind,
+            region: Bounds::new(
+                bar.x.saturating_sub(BAR_MARGIN),
+                bar.y.saturating_sub(BAR_MARGIN),
+                full_width + BAR_MARGIN * 2,
+                bar.h + BAR_MARGIN * 2,
+            ),
+            colors: bar_colors(image, bar, hue),
+        });
+    }
+
+    /// Place an area where it is relative to `reference` in the default layout, relative to `anchor`.
+    fn add_area(&mut self, area: BoundsArea, reference: Bounds, anchor: Bounds) {
+        let scale = anchor.w as f32 / reference.w as f32;
+        let place = |value: u32, reference: u32, anchor: u32| {
+            (anchor as f32 + (value as f32 - reference as f32) * scale).max(0.0) as u32
+        };
+        let default = area.to_rect();
+        let bounds = Bounds::new(
+            place(default.x, reference.x, anchor.x),
+            place(default.y, reference.y, anchor.y),
+            (default.w as f32 * scale) as u32,
+            (default.h as f32 * scale) as u32,
+        );
+        self.areas.push((area, bounds));
+    }
+}
+
+/// Solid horizontal bars of the given hue.
+fn find_bars(image: &ImageBuffer, hue: BarHue) -> Vec<Bounds> {
+    let (width, height) = image.dimensions();
+    let is_bar_pixel = |x, y| {
+        let [r, g, b, _] = image.get_pixel(x, y).0;
+        hue.matches([r, g, b])
+    };
+
+    // Bars and the last row they were extended on
+    let mut bars: Vec<(Bounds, u32)> = vec![];
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            if !is_bar_pixel(x, y) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && is_bar_pixel(x, y) {
+                x += 1;
+            }
+            if x - start < MIN_BAR_LENGTH {
+                continue;
+            }
+
+            let previous_row = bars
+                .iter_mut()
+                .find(|(bar, last_row)| *last_row + 1 == y && start < bar.x + bar.w && x > bar.x);
+            match previous_row {
+                Some((bar, last_row)) => {
+                    let right = (bar.x + bar.w).max(x);
+                    bar.x = bar.x.min(start);
+                    bar.w = right - bar.x;
+                    bar.h += 1;
+                    *last_row = y;
+                }
+                None => bars.push((Bounds::new(start, y, x - start, 1), y)),
+            }
+        }
+    }
+
+    bars.into_iter()
+        .map(|(bar, _)| bar)
+        .filter(|bar| bar.h >= MIN_BAR_HEIGHT)
+        .collect()
+}
+
+/// Whether `lower` is the next bar under `upper` in a stack of left aligned bars.
+fn is_stacked(upper: Bounds, lower: Bounds) -> bool {
+    let tolerance = upper.h.max(4);
+    lower.y >= upper.y + upper.h
+        && lower.y - (upper.y + upper.h) <= tolerance * 2
+        && upper.x.max(lower.x) - upper.x.min(lower.x) <= tolerance
+}
+
+/// First stack of bars with the given hues from top to bottom, top left first.
+fn find_stacked<const N: usize>(
+    image: &ImageBuffer,
+    hues: &[BarHue; N],
+    excluded: Option<Bounds>,
+) -> Option<[Bounds; N]> {
+    let bars = hues.map(|hue| find_bars(image, hue));
+
+    let mut candidates = bars[0]
+        .iter()
+        .filter(|bar| Some(**bar) != excluded)
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|bar| (bar.y, bar.x));
+    candidates.into_iter().find_map(|first| {
+        let mut stack = [*first; N];
+        for index in 1..N {
+            stack[index] = *bars[index]
+                .iter()
+                .find(|bar| is_stacked(stack[index - 1], **bar))?;
+        }
+        Some(stack)
+    })
+}
+
+/// Most frequent shades of a bar.
+fn bar_colors(image: &ImageBuffer, bar: Bounds, hue: BarHue) -> Vec<Color> {
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for y in bar.y..bar.y + bar.h {
+        for x in bar.x..bar.x + bar.w {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            if hue.matches([r, g, b]) {
+                *counts.entry([r, g, b]).or_default() += 1;
+            }
+        }
+    }
+
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .take(BAR_SHADES)
+        .map(|([r, g, b], _)| Color::new(r, g, b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::CalibrationConfig;
+    use crate::{
+        data::{Bounds, StatusBarKind},
+        image_analyzer::{BoundsArea, Color},
+    };
+
+    fn fill(image: &mut RgbaImage, bounds: Bounds, shades: &[[u8; 3]]) {
+        for y in bounds.y..bounds.y + bounds.h {
+            for x in bounds.x..bounds.x + bounds.w {
+                let [r, g, b] = shades[(y - bounds.y) as usize % shades.len()];
+                image.put_pixel(x, y, Rgba([r, g, b, 255]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_calibration() {
+        // A 1600x1200 client with the UI scaled twice
+        let mut frame = RgbaImage::from_pixel(1600, 1200, Rgba([40, 60, 40, 255]));
+        fill(
+            &mut frame,
+            Bounds::new(216, 72, 228, 16),
+            &[[200, 20, 60], [230, 40, 80]],
+        );
+        fill(
+            &mut frame,
+            Bounds::new(216, 100, 200, 16),
+            &[[30, 120, 210]],
+        );
+        fill(&mut frame, Bounds::new(216, 128, 228, 16), &[[30, 170, 30]]);
+        fill(&mut frame, Bounds::new(668, 72, 400, 16), &[[200, 20, 60]]);
+        fill(
+            &mut frame,
+            Bounds::new(668, 100, 400, 12),
+            &[[30, 120, 210]],
+        );
+        // Aggressive mob names are red too, but are no bars
+        fill(&mut frame, Bounds::new(900, 700, 10, 12), &[[179, 23, 23]]);
+        fill(&mut frame, Bounds::new(200, 400, 60, 2), &[[179, 23, 23]]);
+
+        let calibration = CalibrationConfig::locate(&frame).unwrap();
+        assert_eq!(
+            (calibration.frame_width, calibration.frame_height),
+            (1600, 1200)
+        );
+
+        let hp = calibration.status_bar(StatusBarKind::Hp).unwrap();
+        assert_eq!((hp.min_x, hp.min_y, hp.max_x, hp.max_y), (213, 69, 447, 91));
+        assert_eq!(
+            hp.refs,
+            vec![Color::new(200, 20, 60), Color::new(230, 40, 80)]
+        );
+        // The MP bar wasn't full, its region still holds a full bar
+        let mp = calibration.status_bar(StatusBarKind::Mp).unwrap();
+        assert_eq!((mp.min_x, mp.max_x), (213, 447));
+        let target_mp = calibration.status_bar(StatusBarKind::TargetMP).unwrap();
+        assert_eq!((target_mp.min_y, target_mp.max_y), (97, 115));
+
+        // Areas follow their anchors and the UI scale
+        assert_eq!(
+            calibration.area(BoundsArea::SelectName),
+            Some(Bounds::new(666, 0, 404, 56))
+        );
+        assert_eq!(
+            calibration.area(BoundsArea::Experience),
+            Some(Bounds::new(296, 172, 148, 28))
+        );
+        assert_eq!(
+            calibration.area(BoundsArea::Toast),
+            Some(Bounds::new(640, 1060, 350, 100))
+        );
+
+        // Without a target only the stat tray is calibrated
+        let mut frame = RgbaImage::from_pixel(800, 600, Rgba([40, 60, 40, 255]));
+        fill(&mut frame, Bounds::new(108, 36, 114, 8), &[[200, 20, 60]]);
+        fill(&mut frame, Bounds::new(108, 50, 114, 8), &[[30, 120, 210]]);
+        fill(&mut frame, Bounds::new(108, 64, 114, 8), &[[30, 170, 30]]);
+        let calibration = CalibrationConfig::locate(&frame).unwrap();
+        assert!(calibration.status_bar(StatusBarKind::TargetHP).is_none());
+        assert_eq!(calibration.area(BoundsArea::SelectName), None);
+        assert_eq!(
+            calibration.area(BoundsArea::Ping),
+            Some(Bounds::new(2, 110, 120, 20))
+        );
+
+        let empty = RgbaImage::from_pixel(800, 600, Rgba([0, 0, 0, 255]));
+        assert_eq!(CalibrationConfig::locate(&empty), None);
+    }
+}
@@ -0,0 +1,106 @@
+use libscreenshot::ImageBuffer;
+
+use crate::data::ConnectionState;
+
+/// Pixels are sampled on a grid this many pixels apart.
+const SAMPLE_STEP: u32 = 16;
+/// Pixels darker than this are part of a loading screen.
+const MAX_DARK_BRIGHTNESS: u8 = 24;
+/// Share of dark pixels for the frame to be a loading screen.
+const DARK_RATIO: f32 = 0.95;
+
+/// Classify the text read in the middle of an out of game screen.
+pub fn classify_screen_text(text: &str) -> Option<ConnectionState> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let lower = text.to_lowercase();
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+    // Maintenance notices often mention the disconnection too
+    if contains_any(&[
+        "maintenance",
+        "server is down",
+        "server is offline",
+        "server unavailable",
+        "unable to connect",
+        "cannot connect",
+    ]) {
+        Some(ConnectionState::ServerDown)
+    } else if contains_any(&[
+        "disconnected",
+        "connection lost",
+        "connection to the server",
+    ]) {
+        Some(ConnectionState::Disconnected)
+    } else if contains_any(&["password", "account", "log in", "login"]) {
+        Some(ConnectionState::LoginScreen)
+    } else if contains_any(&[
+        "create character",
+        "delete character",
+        "select character",
+        "start game",
+    ]) {
+        Some(ConnectionState::CharacterSelect)
+    } else if contains_any(&["loading"]) {
+        Some(ConnectionState::Loading)
+    } else {
+        None
+    }
+}
+
+/// Whether the frame is almost entirely black.
+pub fn is_dark_screen(image: &ImageBuffer) -> bool {
+    let (width, height) = image.dimensions();
+    let mut samples = 0;
+    let mut dark = 0;
+
+    for y in (0..height).step_by(SAMPLE_STEP as usize) {
+        for x in (0..width).step_by(SAMPLE_STEP as usize) {
+            samples += 1;
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            if r.max(g).max(b) <= MAX_DARK_BRIGHTNESS {
+                dark += 1;
+            }
+        }
+    }
+    samples > 0 && dark as f32 >= samples as f32 * DARK_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{classify_screen_text, is_dark_screen};
+    use crate::data::ConnectionState;
+
+    #[test]
+    fn test_connection_detection() {
+        let classify = |text| classify_screen_text(text);
+        assert_eq!(
+            classify("You have been disconnected\nfrom the server."),
+            Some(ConnectionState::Disconnected)
+        );
+        assert_eq!(
+            classify("Disconnected: the server is under maintenance"),
+            Some(ConnectionState::ServerDown)
+        );
+        assert_eq!(
+            classify("Account\nPassword\nLOGIN"),
+            Some(ConnectionState::LoginScreen)
+        );
+        assert_eq!(
+            classify("Create Character  Delete Character"),
+            Some(ConnectionState::CharacterSelect)
+        );
+        assert_eq!(classify("Loading..."), Some(ConnectionState::Loading));
+        assert_eq!(classify(""), None);
+
+        let mut frame = RgbaImage::from_pixel(160, 120, Rgba([5, 5, 10, 255]));
+        assert!(is_dark_screen(&frame));
+        for y in 0..60 {
+            for x in 0..160 {
+                frame.put_pixel(x, y, Rgba([60, 140, 70, 255]));
+            }
+        }
+        assert!(!is_dark_screen(&frame));
+    }
+}
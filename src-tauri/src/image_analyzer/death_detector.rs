@@ -0,0 +1,91 @@
+use libscreenshot::ImageBuffer;
+
+/// Pixels are sampled on a grid this many pixels apart.
+const SAMPLE_STEP: u32 = 16;
+/// Darker pixels are ignored, black and grey look the same.
+const MIN_BRIGHTNESS: u8 = 48;
+/// Channels of a grey pixel are at most this far apart.
+const GREY_TOLERANCE: u8 = 12;
+/// Share of the sampled pixels that must be bright enough to judge the tint.
+const MIN_BRIGHT_RATIO: f32 = 0.25;
+/// Share of the bright pixels that must be grey for the screen to be tinted.
+const GREY_RATIO: f32 = 0.9;
+
+/// Death cues found in a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeathSignals {
+    /// The world is rendered without colors
+    pub grey_screen: bool,
+    /// The dialog offering to revive is shown
+    pub revive_dialog: bool,
+}
+
+/// Whether the frame has the grey tint of the death screen.
+pub fn is_grey_screen(image: &ImageBuffer) -> bool {
+    let (width, height) = image.dimensions();
+    let mut samples = 0;
+    let mut bright = 0;
+    let mut grey = 0;
+
+    for y in (0..height).step_by(SAMPLE_STEP as usize) {
+        for x in (0..width).step_by(SAMPLE_STEP as usize) {
+            samples += 1;
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            let (min, max) = (r.min(g).min(b), r.max(g).max(b));
+            if max < MIN_BRIGHTNESS {
+                continue;
+            }
+            bright += 1;
+            if max - min <= GREY_TOLERANCE {
+                grey += 1;
+            }
+        }
+    }
+
+    bright > 0
+        && bright as f32 >= samples as f32 * MIN_BRIGHT_RATIO
+        && grey as f32 >= bright as f32 * GREY_RATIO
+}
+
+/// Whether the text read in the middle of the screen is the revive dialog.
+pub fn is_revive_dialog(text: &str) -> bool {
+    let text = text.to_lowercase();
+    ["revive", "resurrect", "lodestar"]
+        .iter()
+        .any(|keyword| text.contains(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{is_grey_screen, is_revive_dialog};
+
+    #[test]
+    fn test_death_detection() {
+        // Black frames are loading screens, not death screens
+        let black = RgbaImage::from_pixel(160, 120, Rgba([0, 0, 0, 255]));
+        assert!(!is_grey_screen(&black));
+
+        let world = RgbaImage::from_pixel(160, 120, Rgba([60, 140, 70, 255]));
+        assert!(!is_grey_screen(&world));
+
+        // The world turns grey, a few colored UI elements don't matter
+        let mut dead = RgbaImage::from_pixel(160, 120, Rgba([120, 118, 125, 255]));
+        dead.put_pixel(0, 0, Rgba([174, 18, 55, 255]));
+        assert!(is_grey_screen(&dead));
+
+        // Mostly dark frames with a grey window
+        let mut loading = black;
+        for y in 0..16 {
+            for x in 0..160 {
+                loading.put_pixel(x, y, Rgba([120, 120, 120, 255]));
+            }
+        }
+        assert!(!is_grey_screen(&loading));
+
+        assert!(is_revive_dialog("Revive at the Lodestar"));
+        assert!(is_revive_dialog("You died.\nRESURRECT"));
+        assert!(!is_revive_dialog("Welcome to Madrigal"));
+    }
+}
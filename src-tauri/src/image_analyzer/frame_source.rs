@@ -0,0 +1,176 @@
+use std::{
+    collections::VecDeque,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use libscreenshot::{ImageBuffer, WindowCaptureProvider};
+use parking_lot::Mutex;
+use slog::Logger;
+
+/// A source of client frames consumed by the `ImageAnalyzer`.
+pub trait FrameSource: fmt::Debug + Send {
+    /// Capture the next frame. Returns `None` if no frame is available.
+    fn capture(&mut self, logger: &Logger) -> Option<ImageBuffer>;
+}
+
+/// Captures frames from a native client window through libscreenshot.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowCaptureSource {
+    window_id: u64,
+}
+
+impl WindowCaptureSource {
+    pub fn new(window_id: u64) -> Self {
+        Self { window_id }
+    }
+}
+
+impl FrameSource for WindowCaptureSource {
+    fn capture(&mut self, logger: &Logger) -> Option<ImageBuffer> {
+        if self.window_id == 0 {
+            return None;
+        }
+
+        let provider = libscreenshot::get_window_capture_provider()?;
+        if let Ok(image) = provider.capture_window(self.window_id) {
+            Some(image)
+        } else {
+            slog::warn!(logger, "Failed to capture window"; "window_id" => self.window_id);
+            None
+        }
+    }
+}
+
+/// Replays a single PNG frame or a directory of PNG frames.
+#[derive(Debug, Clone)]
+pub struct PngReplaySource {
+    frames: Vec<PathBuf>,
+    index: usize,
+    looping: bool,
+}
+
+impl PngReplaySource {
+    /// Open a PNG file or a directory. Directory frames are replayed in file name order.
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let frames = if path.is_dir() {
+            let mut frames = fs::read_dir(path)?
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| Self::is_png(path))
+                .collect::<Vec<_>>();
+            frames.sort();
+            frames
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        Ok(Self {
+            frames,
+            index: 0,
+            looping: false,
+        })
+    }
+
+    /// Start over from the first frame once all frames have been replayed.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn is_png(path: &Path) -> bool {
+        path.extension()
+            .map(|ext| ext.eq_ignore_ascii_case("png"))
+            .unwrap_or(false)
+    }
+}
+
+impl FrameSource for PngReplaySource {
+    fn capture(&mut self, logger: &Logger) -> Option<ImageBuffer> {
+        if self.index >= self.frames.len() {
+            if !self.looping || self.frames.is_empty() {
+                return None;
+            }
+            self.index = 0;
+        }
+
+        let path = &self.frames[self.index];
+        self.index += 1;
+
+        match image::open(path) {
+            Ok(image) => Some(image.into_rgba8()),
+            Err(e) => {
+                slog::warn!(logger, "Failed to load frame"; "path" => path.to_string_lossy().to_string(), "error" => e.to_string());
+                None
+            }
+        }
+    }
+}
+
+/// Hands out frames pushed from elsewhere, e.g. by a replay or a simulation.
+#[derive(Debug, Clone, Default)]
+pub struct QueuedFrameSource {
+    frames: Arc<Mutex<VecDeque<ImageBuffer>>>,
+}
+
+impl QueuedFrameSource {
+    /// Queue a frame. Clones of this source share the same queue.
+    pub fn push(&self, frame: ImageBuffer) {
+        self.frames.lock().push_back(frame);
+    }
+}
+
+impl FrameSource for QueuedFrameSource {
+    fn capture(&mut self, _logger: &Logger) -> Option<ImageBuffer> {
+        self.frames.lock().pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use slog::Logger;
+
+    use super::{FrameSource, PngReplaySource};
+
+    #[test]
+    fn test_png_replay_source() {
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let dir = std::env::temp_dir().join("neuz_test_png_replay_source");
+        drop(std::fs::remove_dir_all(&dir));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (name, value) in [("frame_1.png", 10), ("frame_0.png", 20)] {
+            let frame = image::RgbaImage::from_pixel(4, 4, Rgba([value, 0, 0, 255]));
+            frame.save(dir.join(name)).unwrap();
+        }
+        std::fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+
+        let mut source = PngReplaySource::open(&dir).unwrap();
+        assert_eq!(source.len(), 2);
+        assert_eq!(source.capture(&logger).unwrap().get_pixel(0, 0).0[0], 20);
+        assert_eq!(source.capture(&logger).unwrap().get_pixel(0, 0).0[0], 10);
+        assert!(source.capture(&logger).is_none());
+
+        let mut source = PngReplaySource::open(dir.join("frame_1.png"))
+            .unwrap()
+            .looping(true);
+        assert!(source.capture(&logger).is_some());
+        assert!(source.capture(&logger).is_some());
+
+        drop(std::fs::remove_dir_all(&dir));
+    }
+}
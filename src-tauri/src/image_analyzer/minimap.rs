@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+
+use libscreenshot::ImageBuffer;
+
+use crate::data::{Anchor, Bounds, FrameLayout, MinimapInfo, Point, PointCloud};
+
+/// Minimap of an 800x600 client.
+const MINIMAP: Bounds = Bounds {
+    x: 650,
+    y: 5,
+    w: 145,
+    h: 145,
+};
+/// Smaller arrows are too coarse to tell the heading.
+const MIN_ARROW_PIXELS: usize = 6;
+/// Pixels of the same mob dot are at most this far apart.
+const MOB_DOT_GAP: u32 = 1;
+
+/// The player arrow is yellow.
+fn is_arrow([r, g, b]: [u8; 3]) -> bool {
+    r >= 200 && g >= 170 && b <= 100
+}
+
+/// Mobs are red dots.
+fn is_mob_dot([r, g, b]: [u8; 3]) -> bool {
+    r >= 180 && g <= 80 && b <= 80
+}
+
+/// Bounds of the minimap in a frame.
+pub fn minimap_bounds(layout: &FrameLayout) -> Bounds {
+    layout.place(MINIMAP, Anchor::TopRight)
+}
+
+/// Find the player arrow and the mob dots, `None` if the minimap doesn't show the arrow.
+pub fn read_minimap(image: &ImageBuffer, bounds: Bounds) -> Option<MinimapInfo> {
+    let (width, height) = image.dimensions();
+    let mut arrow = vec![];
+    let mut mobs = PointCloud::default();
+    for y in bounds.y..(bounds.y + bounds.h).min(height) {
+        for x in bounds.x..(bounds.x + bounds.w).min(width) {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            if is_arrow([r, g, b]) {
+                arrow.push(Point::new(x, y));
+            } else if is_mob_dot([r, g, b]) {
+                mobs.push(Point::new(x, y));
+            }
+        }
+    }
+    if arrow.is_empty() {
+        return None;
+    }
+
+    let count = arrow.len() as f32;
+    let center_x = arrow.iter().map(|point| point.x as f32).sum::<f32>() / count;
+    let center_y = arrow.iter().map(|point| point.y as f32).sum::<f32>() / count;
+    // The tip is the pixel furthest from the center of the arrow
+    let distance = |point: &Point| (point.x as f32 - center_x).hypot(point.y as f32 - center_y);
+    let heading = arrow
+        .iter()
+        .max_by(|a, b| {
+            distance(a)
+                .partial_cmp(&distance(b))
+                .unwrap_or(Ordering::Equal)
+        })
+        .filter(|_| arrow.len() >= MIN_ARROW_PIXELS)
+        .map(|tip| {
+            (tip.x as f32 - center_x)
+                .atan2(center_y - tip.y as f32)
+                .to_degrees()
+                .rem_euclid(360.0)
+        });
+
+    Some(MinimapInfo {
+        player: Point::new(center_x.round() as u32, center_y.round() as u32),
+        heading,
+        mobs: mobs
+            .cluster_connected(MOB_DOT_GAP, MOB_DOT_GAP)
+            .iter()
+            .map(|dot| dot.to_bounds().center())
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{minimap_bounds, read_minimap};
+    use crate::data::{FrameLayout, Point, WorldPoint};
+
+    #[test]
+    fn test_read_minimap() {
+        let layout = FrameLayout::new(1280, 720);
+        let bounds = minimap_bounds(&layout);
+        let mut image = RgbaImage::from_pixel(1280, 720, Rgba([40, 70, 50, 255]));
+        assert_eq!(read_minimap(&image, bounds), None);
+
+        // Arrow pointing east, its tip at the right
+        let (center_x, center_y) = (bounds.x + 72, bounds.y + 72);
+        for dx in 0..9u32 {
+            let half_width = (8 - dx) / 2;
+            for dy in 0..=half_width * 2 {
+                image.put_pixel(
+                    center_x - 3 + dx,
+                    center_y - half_width + dy,
+                    Rgba([240, 210, 40, 255]),
+                );
+            }
+        }
+        // Two mobs, north west and south east
+        for (x, y) in [
+            (bounds.x + 30, bounds.y + 20),
+            (bounds.x + 100, bounds.y + 110),
+        ] {
+            for dx in 0..3 {
+                for dy in 0..3 {
+                    image.put_pixel(x + dx, y + dy, Rgba([220, 30, 30, 255]));
+                }
+            }
+        }
+
+        let minimap = read_minimap(&image, bounds).unwrap();
+        assert!(minimap.player.distance_to(&Point::new(center_x, center_y)) <= 2.0);
+        let heading = minimap.heading.unwrap();
+        assert!((heading - 90.0).abs() < 10.0, "heading {}", heading);
+        assert_eq!(
+            minimap.mobs,
+            vec![
+                Point::new(bounds.x + 31, bounds.y + 21),
+                Point::new(bounds.x + 101, bounds.y + 111),
+            ]
+        );
+
+        // Mobs north west of the player are at lower coordinates
+        let position = WorldPoint::new(1000.0, 1000.0);
+        let mobs = minimap.mob_positions(position, 2.0);
+        assert!(mobs[0].x < position.x && mobs[0].z < position.z);
+        assert!(mobs[1].x > position.x && mobs[1].z > position.z);
+
+        // Zooming the minimap out spreads them further
+        let far_mobs = minimap.mob_positions(position, 4.0);
+        assert_eq!(far_mobs[0].x - position.x, (mobs[0].x - position.x) * 2.0);
+        assert_eq!(far_mobs[1].z - position.z, (mobs[1].z - position.z) * 2.0);
+    }
+}
@@ -0,0 +1,257 @@
+use std::{
+    error::Error,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use image::imageops::{self, FilterType};
+use imageproc::contrast::threshold;
+use libscreenshot::ImageBuffer;
+use tesseract::{PageSegMode, Tesseract};
+
+use crate::data::Bounds;
+
+/// Preprocessing applied to a region before it is read.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OcrOptions {
+    /// Upscale factor, small fonts are read more reliably once enlarged
+    pub scale: u32,
+    /// Binarize the grayscale region at this luma value
+    pub threshold: Option<u8>,
+    /// Only recognize these characters
+    pub whitelist: Option<&'static str>,
+    /// Read the region as a single line of text
+    pub single_line: bool,
+}
+
+/// Raw pixels fed to Tesseract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OcrFrame {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    bytes_per_pixel: u32,
+}
+
+impl OcrFrame {
+    /// Crop `bounds` out of the image and apply the preprocessing options.
+    fn new(image: &ImageBuffer, bounds: Bounds, options: &OcrOptions) -> Option<Self> {
+        let mut crop = imageops::crop_imm(image, bounds.x, bounds.y, bounds.w, bounds.h).to_image();
+        if crop.width() == 0 || crop.height() == 0 {
+            return None;
+        }
+
+        if options.scale > 1 {
+            crop = imageops::resize(
+                &crop,
+                crop.width() * options.scale,
+                crop.height() * options.scale,
+                FilterType::Triangle,
+            );
+        }
+
+        let (width, height) = crop.dimensions();
+        if let Some(luma) = options.threshold {
+            let gray = threshold(&imageops::grayscale(&crop), luma);
+            Some(Self {
+                data: gray.into_raw(),
+                width,
+                height,
+                bytes_per_pixel: 1,
+            })
+        } else {
+            Some(Self {
+                data: crop.into_raw(),
+                width,
+                height,
+                bytes_per_pixel: 4,
+            })
+        }
+    }
+}
+
+/// Long-lived Tesseract instance, initialized on first use.
+pub struct OcrEngine {
+    language: &'static str,
+    tesseract: Option<Tesseract>,
+    /// Why Tesseract couldn't be initialized, it isn't tried again
+    init_error: Option<String>,
+    init_error_reported: bool,
+}
+
+impl fmt::Debug for OcrEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OcrEngine")
+            .field("language", &self.language)
+            .field("initialized", &self.tesseract.is_some())
+            .field("init_error", &self.init_error)
+            .finish()
+    }
+}
+
+impl OcrEngine {
+    pub fn new(language: &'static str) -> Self {
+        Self {
+            language,
+            tesseract: None,
+            init_error: None,
+            init_error_reported: false,
+        }
+    }
+
+    /// Why Tesseract couldn't be initialized, only the first time so it's logged once.
+    pub fn take_init_error(&mut self) -> Option<String> {
+        if self.init_error_reported {
+            return None;
+        }
+        self.init_error_reported = self.init_error.is_some();
+        self.init_error.clone()
+    }
+
+    /// Read the text inside `bounds` of the image.
+    pub fn read(
+        &mut self,
+        image: &ImageBuffer,
+        bounds: Bounds,
+        options: &OcrOptions,
+    ) -> Result<String, Box<dyn Error>> {
+        // Missing language data won't show up later, don't retry on every read
+        if let Some(error) = &self.init_error {
+            return Err(error.clone().into());
+        }
+        let frame = OcrFrame::new(image, bounds, options).ok_or("empty OCR region")?;
+
+        // Tesseract calls consume the instance, a failing call drops it and the next read recreates it
+        let tesseract = match self.tesseract.take() {
+            Some(tesseract) => tesseract,
+            None => Tesseract::new(None, Some(self.language)).map_err(|error| {
+                self.init_error = Some(error.to_string());
+                error
+            })?,
+        };
+
+        // Always set the whitelist so it doesn't leak from a previous read
+        let mut tesseract =
+            tesseract.set_variable("tessedit_char_whitelist", options.whitelist.unwrap_or(""))?;
+        tesseract.set_page_seg_mode(if options.single_line {
+            PageSegMode::PsmSingleLine
+        } else {
+            PageSegMode::PsmAuto
+        });
+
+        let mut tesseract = tesseract
+            .set_frame(
+                &frame.data,
+                frame.width as i32,
+                frame.height as i32,
+                frame.bytes_per_pixel as i32,
+                (frame.width * frame.bytes_per_pixel) as i32,
+            )?
+            .recognize()?;
+        let text = tesseract.get_text();
+        self.tesseract = Some(tesseract);
+
+        Ok(text?)
+    }
+}
+
+/// Text of an area read again only once `interval` passed, for areas read while nothing
+/// else tells what the client shows, which can last for many frames.
+#[derive(Debug, Clone)]
+pub struct ThrottledText {
+    interval: Duration,
+    last_read: Option<Instant>,
+    text: Option<String>,
+}
+
+impl ThrottledText {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_read: None,
+            text: None,
+        }
+    }
+
+    /// Whether the area should be read again.
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.last_read
+            .map(|last_read| now.saturating_duration_since(last_read) >= self.interval)
+            .unwrap_or(true)
+    }
+
+    pub fn update(&mut self, text: Option<String>, now: Instant) {
+        self.last_read = Some(now);
+        self.text = text;
+    }
+
+    /// Text of the last read.
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    /// Forget the last read, so the area is read as soon as it's needed again.
+    pub fn reset(&mut self) {
+        self.last_read = None;
+        self.text = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use image::{Rgba, RgbaImage};
+
+    use super::{OcrFrame, OcrOptions, ThrottledText};
+    use crate::data::Bounds;
+
+    #[test]
+    fn test_ocr_frame() {
+        let mut image = RgbaImage::from_pixel(20, 10, Rgba([10, 10, 10, 255]));
+        image.put_pixel(18, 8, Rgba([250, 250, 250, 255]));
+
+        // Raw RGBA pixels, clamped to the image
+        let frame = OcrFrame::new(&image, Bounds::new(15, 5, 10, 10), &OcrOptions::default());
+        let frame = frame.unwrap();
+        assert_eq!(
+            (frame.width, frame.height, frame.bytes_per_pixel),
+            (5, 5, 4)
+        );
+        assert_eq!(frame.data.len(), 5 * 5 * 4);
+
+        // Scaled and binarized grayscale pixels
+        let options = OcrOptions {
+            scale: 2,
+            threshold: Some(128),
+            ..Default::default()
+        };
+        let frame = OcrFrame::new(&image, Bounds::new(18, 8, 2, 2), &options).unwrap();
+        assert_eq!(
+            (frame.width, frame.height, frame.bytes_per_pixel),
+            (4, 4, 1)
+        );
+        assert!(frame.data.iter().all(|&luma| luma == 0 || luma == 255));
+        assert_eq!(frame.data[0], 255);
+        assert_eq!(frame.data[15], 0);
+
+        assert!(OcrFrame::new(&image, Bounds::new(30, 30, 5, 5), &options).is_none());
+    }
+
+    #[test]
+    fn test_throttled_text() {
+        let now = Instant::now();
+        let mut text = ThrottledText::new(Duration::from_secs(1));
+        assert!(text.is_due(now));
+        assert_eq!(text.text(), None);
+
+        text.update(Some("Connecting".to_string()), now);
+        assert!(!text.is_due(now + Duration::from_millis(500)));
+        assert_eq!(text.text(), Some("Connecting"));
+        assert!(text.is_due(now + Duration::from_secs(1)));
+
+        text.reset();
+        assert!(text.is_due(now));
+        assert_eq!(text.text(), None);
+    }
+}
@@ -0,0 +1,219 @@
+use std::{collections::HashMap, hash::Hash};
+
+use libscreenshot::ImageBuffer;
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSlice};
+
+use super::Color;
+use crate::{
+    data::{Bounds, Point, PointCloud},
+    platform::{IGNORE_AREA_BOTTOM, IGNORE_AREA_TOP},
+};
+
+/// Colors searched for in a region of the frame.
+#[derive(Debug, Clone)]
+pub struct PixelQuery {
+    colors: Vec<Color>,
+    tolerance: u8,
+    min_x: u32,
+    min_y: u32,
+    /// Exclusive, 0 means the frame width
+    max_x: u32,
+    /// Inclusive, 0 means the frame height
+    max_y: u32,
+    exclude: Option<Bounds>,
+}
+
+impl PixelQuery {
+    /// Search the whole frame for pixels matching any of `colors`.
+    pub fn new(colors: Vec<Color>, tolerance: u8) -> Self {
+        Self {
+            colors,
+            tolerance,
+            min_x: 0,
+            min_y: 0,
+            max_x: 0,
+            max_y: 0,
+            exclude: None,
+        }
+    }
+
+    /// Restrict the search to the given region.
+    pub fn within(mut self, min_x: u32, min_y: u32, max_x: u32, max_y: u32) -> Self {
+        self.min_x = min_x;
+        self.min_y = min_y;
+        self.max_x = max_x;
+        self.max_y = max_y;
+        self
+    }
+
+    /// Ignore pixels inside the given bounds.
+    pub fn excluding(mut self, bounds: Bounds) -> Self {
+        self.exclude = Some(bounds);
+        self
+    }
+
+    fn covers_row(&self, y: u32) -> bool {
+        y >= self.min_y && (self.max_y == 0 || y <= self.max_y)
+    }
+
+    #[inline(always)]
+    fn matches(&self, point: &Point, pixel: &[u8; 4]) -> bool {
+        if point.x < self.min_x || (self.max_x != 0 && point.x >= self.max_x) {
+            return false;
+        }
+        if let Some(exclude) = &self.exclude {
+            if exclude.contains_point(point) {
+                return false;
+            }
+        }
+
+        self.colors
+            .iter()
+            .any(|color| Self::pixel_matches(pixel, &color.refs, self.tolerance))
+    }
+
+    /// Check if pixel `c` matches reference pixel `r` with the given `tolerance`.
+    #[inline(always)]
+    fn pixel_matches(c: &[u8; 4], r: &[u8; 3], tolerance: u8) -> bool {
+        let matches_inner = |a: u8, b: u8| a.max(b) - a.min(b) <= tolerance;
+        let perm = [(c[0], r[0]), (c[1], r[1]), (c[2], r[2])];
+        perm.iter().all(|&(a, b)| matches_inner(a, b))
+    }
+}
+
+/// Runs many pixel queries over a frame while walking each row only once.
+#[derive(Debug, Clone)]
+pub struct PixelScan<K> {
+    queries: Vec<(K, PixelQuery)>,
+}
+
+impl<K> Default for PixelScan<K> {
+    fn default() -> Self {
+        Self { queries: vec![] }
+    }
+}
+
+impl<K> PixelScan<K>
+where
+    K: Copy + Eq + Hash + Send + Sync,
+{
+    pub fn add(&mut self, key: K, query: PixelQuery) {
+        self.queries.push((key, query));
+    }
+
+    /// Scan the frame and return the matching points of every query.
+    pub fn run(&self, image: &ImageBuffer) -> HashMap<K, PointCloud> {
+        let (width, height) = image.dimensions();
+        let mut clouds: HashMap<K, PointCloud> = self
+            .queries
+            .iter()
+            .map(|(key, _)| (*key, PointCloud::default()))
+            .collect();
+        if width == 0 {
+            return clouds;
+        }
+
+        // Rows are scanned in parallel but collected in order
+        let rows = image
+            .as_raw()
+            .par_chunks(width as usize * 4)
+            .enumerate()
+            .map(|(y, row)| self.scan_row(y as u32, height, row))
+            .collect::<Vec<_>>();
+
+        for (index, point) in rows.into_iter().flatten() {
+            if let Some(cloud) = clouds.get_mut(&self.queries[index].0) {
+                cloud.push(point);
+            }
+        }
+        clouds
+    }
+
+    fn scan_row(&self, y: u32, height: u32, row: &[u8]) -> Vec<(usize, Point)> {
+        // Skip this row if it's in an ignored area
+        #[allow(clippy::absurd_extreme_comparisons)] // not always 0 (macOS)
+        if y <= IGNORE_AREA_TOP || y > height.checked_sub(IGNORE_AREA_BOTTOM).unwrap_or(height) {
+            return vec![];
+        }
+
+        let queries = self
+            .queries
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, query))| query.covers_row(y))
+            .map(|(index, (_, query))| (index, query))
+            .collect::<Vec<_>>();
+        if queries.is_empty() {
+            return vec![];
+        }
+
+        let mut points = vec![];
+        for (x, pixel) in row.chunks_exact(4).enumerate() {
+            let pixel = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            if pixel[3] != 255 {
+                break;
+            }
+
+            let point = Point::new(x as u32, y);
+            for (index, query) in &queries {
+                if query.matches(&point, &pixel) {
+                    points.push((*index, point));
+                }
+            }
+        }
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::{PixelQuery, PixelScan};
+    use crate::{
+        data::{Bounds, PointCloud},
+        image_analyzer::Color,
+        platform::IGNORE_AREA_TOP,
+    };
+
+    #[test]
+    fn test_pixel_scan() {
+        let red = Rgba([200, 20, 20, 255]);
+        let blue = Rgba([20, 20, 200, 255]);
+        let mut image = RgbaImage::from_pixel(400, 400, Rgba([0, 0, 0, 255]));
+        let y = IGNORE_AREA_TOP + 10;
+        for (x, y, color) in [
+            (10, y, red),
+            (50, y, red),
+            (12, y + 2, blue),
+            (60, y + 2, blue),
+        ] {
+            image.put_pixel(x, y, color);
+        }
+        // Everything after a transparent pixel is ignored
+        image.put_pixel(100, y + 4, Rgba([0, 0, 0, 0]));
+        image.put_pixel(110, y + 4, red);
+
+        let tray = Bounds::new(0, 0, 20, y + 5);
+        let mut scan = PixelScan::default();
+        scan.add("red", PixelQuery::new(vec![Color::new(202, 18, 20)], 5));
+        scan.add(
+            "red_left",
+            PixelQuery::new(vec![Color::new(200, 20, 20)], 0).within(0, 0, 50, 0),
+        );
+        scan.add(
+            "blue",
+            PixelQuery::new(vec![Color::new(20, 20, 200)], 0).excluding(tray),
+        );
+        scan.add(
+            "none",
+            PixelQuery::new(vec![Color::new(20, 20, 200)], 0).within(0, y + 3, 0, 0),
+        );
+
+        let clouds = scan.run(&image);
+        assert_eq!(clouds["red"], PointCloud::from([(10, y), (50, y)]));
+        assert_eq!(clouds["red_left"], PointCloud::from([(10, y)]));
+        assert_eq!(clouds["blue"], PointCloud::from([(60, y + 2)]));
+        assert_eq!(clouds["none"], PointCloud::default());
+    }
+}
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, time::Instant};
+
+use guard::guard;
+use libscreenshot::ImageBuffer;
+
+use crate::data::{Anchor, Bounds, FrameLayout};
+
+/// Slot bar of an 800x600 client, slots are laid out from left to right.
+const SLOT_BAR: Bounds = Bounds {
+    x: 240,
+    y: 560,
+    w: 320,
+    h: 32,
+};
+const SLOT_SIZE: u32 = 32;
+pub const SLOT_COUNT: usize = 10;
+
+/// Pixels of the cooldown sweep are dark and colorless.
+const SWEEP_MAX_BRIGHTNESS: u8 = 120;
+const SWEEP_MAX_SATURATION: u8 = 20;
+/// Share of the slot covered by the sweep, above the share the ready icon already has.
+const MIN_SWEEP_SHARE: f32 = 0.2;
+
+/// Cooldown state of a slot as last seen on the slot bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotObservation {
+    pub ready: bool,
+    pub seen_at: Instant,
+}
+
+/// Observations of every slot of every slot bar, only the shown slot bar has some.
+pub type SlotObservations = [[Option<SlotObservation>; SLOT_COUNT]; 9];
+
+/// Bounds of a slot of the shown slot bar.
+pub fn slot_bounds(layout: &FrameLayout, slot: usize) -> Bounds {
+    let bar = layout.place(SLOT_BAR, Anchor::BottomCenter);
+    Bounds::new(bar.x + slot as u32 * SLOT_SIZE, bar.y, SLOT_SIZE, SLOT_SIZE)
+}
+
+/// Share of the slot covered by dark colorless pixels.
+fn sweep_share(image: &ImageBuffer, bounds: Bounds) -> Option<f32> {
+    let (width, height) = image.dimensions();
+    if bounds.x + bounds.w > width || bounds.y + bounds.h > height {
+        return None;
+    }
+
+    let mut swept = 0;
+    for y in bounds.y..bounds.y + bounds.h {
+        for x in bounds.x..bounds.x + bounds.w {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            let (min, max) = (r.min(g).min(b), r.max(g).max(b));
+            if max <= SWEEP_MAX_BRIGHTNESS && max - min <= SWEEP_MAX_SATURATION {
+                swept += 1;
+            }
+        }
+    }
+    Some(swept as f32 / bounds.size() as f32)
+}
+
+/// Reads the cooldown sweep of the shown slot bar.
+#[derive(Debug, Clone, Default)]
+pub struct SlotBarReader {
+    /// Sweep share of each slot in the last frame
+    shares: [Option<f32>; SLOT_COUNT],
+    seen_at: Option<Instant>,
+    /// Lowest share seen per idle slot, dark icons are not on cooldown
+    baselines: HashMap<(usize, usize), f32>,
+}
+
+impl SlotBarReader {
+    pub fn update(&mut self, image: &ImageBuffer, now: Instant) {
+        let layout = FrameLayout::new(image.width(), image.height());
+        for (slot, share) in self.shares.iter_mut().enumerate() {
+            *share = sweep_share(image, slot_bounds(&layout, slot));
+        }
+        self.seen_at = Some(now);
+    }
+
+    /// Observations of the last frame, which showed `slot_bar`.
+    /// Only slots `last_usage` says are idle set what a ready icon looks like,
+    /// a slot may already be sweeping the first time its bar is shown.
+    pub fn observations(
+        &mut self,
+        slot_bar: Option<usize>,
+        last_usage: &[[Option<Instant>; SLOT_COUNT]; 9],
+    ) -> SlotObservations {
+        let mut observations = [[None; SLOT_COUNT]; 9];
+        guard!(let Some(slot_bar) = slot_bar else { return observations });
+        guard!(let Some(seen_at) = self.seen_at else { return observations });
+
+        for (slot, share) in self.shares.iter().enumerate() {
+            guard!(let Some(share) = *share else { continue });
+            let key = (slot_bar, slot);
+            if last_usage[slot_bar][slot].is_none() {
+                let baseline = self.baselines.entry(key).or_insert(share);
+                *baseline = baseline.min(share);
+            }
+            guard!(let Some(baseline) = self.baselines.get(&key) else { continue });
+            observations[slot_bar][slot] = Some(SlotObservation {
+                ready: share - baseline < MIN_SWEEP_SHARE,
+                seen_at,
+            });
+        }
+        observations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::SlotBarReader;
+
+    const IDLE: [[Option<Instant>; 10]; 9] = [[None; 10]; 9];
+
+    fn draw_slot(frame: &mut RgbaImage, slot: u32, swept_rows: u32) {
+        for dy in 0..32 {
+            for dx in 0..32 {
+                let color = if dy < swept_rows {
+                    Rgba([50, 50, 55, 255])
+                } else {
+                    Rgba([200, 120, 40, 255])
+                };
+                frame.put_pixel(240 + slot * 32 + dx, 560 + dy, color);
+            }
+        }
+    }
+
+    #[test]
+    fn test_slot_cooldowns() {
+        let now = Instant::now();
+        let mut frame = RgbaImage::from_pixel(800, 600, Rgba([20, 20, 20, 255]));
+        draw_slot(&mut frame, 0, 0);
+        draw_slot(&mut frame, 1, 0);
+
+        let mut reader = SlotBarReader::default();
+        reader.update(&frame, now);
+        let observations = reader.observations(Some(2), &IDLE);
+        assert!(observations[2][0].unwrap().ready);
+        assert_eq!(observations[2][0].unwrap().seen_at, now);
+        assert_eq!(observations[0][0], None);
+        // Empty slots are dark, but never swept
+        assert!(observations[2][5].unwrap().ready);
+
+        // Half of the first slot is still cooling down
+        draw_slot(&mut frame, 0, 16);
+        draw_slot(&mut frame, 1, 4);
+        reader.update(&frame, now);
+        let mut last_usage = IDLE;
+        last_usage[2][0] = Some(now);
+        let observations = reader.observations(Some(2), &last_usage);
+        assert!(!observations[2][0].unwrap().ready);
+        assert!(observations[2][1].unwrap().ready);
+
+        assert_eq!(reader.observations(None, &IDLE), [[None; 10]; 9]);
+    }
+
+    #[test]
+    fn test_slot_shown_mid_sweep() {
+        let now = Instant::now();
+        let mut frame = RgbaImage::from_pixel(800, 600, Rgba([20, 20, 20, 255]));
+        let mut last_usage = IDLE;
+        last_usage[0][0] = Some(now);
+
+        // The bar shows up while the slot just pressed is sweeping, the sweep shrinking
+        // must not pass for a ready icon
+        let mut reader = SlotBarReader::default();
+        draw_slot(&mut frame, 0, 24);
+        reader.update(&frame, now);
+        assert_eq!(reader.observations(Some(0), &last_usage)[0][0], None);
+        draw_slot(&mut frame, 0, 12);
+        reader.update(&frame, now);
+        assert_eq!(reader.observations(Some(0), &last_usage)[0][0], None);
+
+        // Once its cooldown is over, the ready icon is learnt
+        draw_slot(&mut frame, 0, 0);
+        reader.update(&frame, now);
+        assert!(reader.observations(Some(0), &IDLE)[0][0].unwrap().ready);
+
+        // And the next sweep is seen
+        draw_slot(&mut frame, 0, 16);
+        reader.update(&frame, now);
+        let observation = reader.observations(Some(0), &last_usage)[0][0];
+        assert!(!observation.unwrap().ready);
+    }
+}
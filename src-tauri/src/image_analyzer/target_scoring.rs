@@ -0,0 +1,207 @@
+use std::time::Instant;
+
+use crate::{
+    data::{Bounds, MobRank, MobType, Point, Target, TargetType, TrackId},
+    ipc::ScoringWeights,
+};
+
+/// Mobs closer than this to a recently avoided spot are likely behind the same obstacle.
+const AVOIDED_MARGIN: u32 = 100;
+
+/// Everything a candidate is scored against.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoringContext<'a> {
+    /// Where the player stands on screen
+    pub center: Point,
+    /// Every visible mob, including the candidates
+    pub mobs: &'a [Target],
+    pub avoided_bounds: &'a [(Bounds, Instant, u128)],
+    pub avoided_tracks: &'a [(TrackId, Instant, u128)],
+    /// Search range, halved for aggressive mobs
+    pub max_distance: i32,
+    /// Discard mobs out of the search range
+    pub limit_range: bool,
+    pub allow_giants: bool,
+    pub hp: u32,
+    pub mp: u32,
+}
+
+/// Rates farming targets, the candidate with the highest score is attacked.
+pub trait TargetScorer {
+    /// Score of `mob`, `None` if it must not be attacked.
+    fn score(&self, mob: &Target, context: &ScoringContext) -> Option<f32>;
+}
+
+/// Weighted sum of distance, mob type, crowd around the mob and avoided spots.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedScorer {
+    weights: ScoringWeights,
+}
+
+impl WeightedScorer {
+    pub fn new(weights: ScoringWeights) -> Self {
+        Self { weights }
+    }
+
+    /// Number of other mobs around `mob`.
+    fn neighbours(&self, mob: &Target, mobs: &[Target]) -> usize {
+        let coords = mob.get_attack_coords();
+        mobs.iter()
+            .filter(|other| other.bounds != mob.bounds)
+            .filter(|other| {
+                other.get_attack_coords().distance_to(&coords) <= self.weights.density_radius as f32
+            })
+            .count()
+    }
+}
+
+impl TargetScorer for WeightedScorer {
+    fn score(&self, mob: &Target, context: &ScoringContext) -> Option<f32> {
+        if mob.rank == MobRank::Giant && !context.allow_giants {
+            return None;
+        }
+
+        let is_avoided_track = mob.track_id.map_or(false, |id| {
+            context
+                .avoided_tracks
+                .iter()
+                .any(|(avoided, _, _)| *avoided == id)
+        });
+        let coords = mob.get_attack_coords();
+        if is_avoided_track
+            || context
+                .avoided_bounds
+                .iter()
+                .any(|(bounds, _, _)| bounds.contains_point(&coords))
+        {
+            return None;
+        }
+
+        // Aggressive mobs far away would drag us around
+        let is_aggressive = mob.target_type == TargetType::Mob(MobType::Aggressive);
+        let range = if is_aggressive {
+            context.max_distance / 2
+        } else {
+            context.max_distance
+        };
+        let distance = coords.distance_to(&context.center);
+        if context.limit_range && distance > range as f32 {
+            return None;
+        }
+
+        let weights = &self.weights;
+        let mut score = -weights.distance * distance / context.max_distance.max(1) as f32;
+        if is_aggressive {
+            score += weights.aggressive;
+        }
+
+        // Crowds are worse the lower our HP or MP is
+        let missing = 1.0 - context.hp.min(context.mp).min(100) as f32 / 100.0;
+        let crowd_penalty = weights.density + weights.low_resources * missing;
+        score -= self.neighbours(mob, context.mobs) as f32 * crowd_penalty;
+
+        if context
+            .avoided_bounds
+            .iter()
+            .any(|(bounds, _, _)| bounds.grow_by(AVOIDED_MARGIN).contains_point(&coords))
+        {
+            score -= weights.avoided;
+        }
+
+        Some(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::{ScoringContext, TargetScorer, WeightedScorer};
+    use crate::{
+        data::{Bounds, MobRank, MobType, Point, Target, TargetType},
+        ipc::FarmingConfig,
+    };
+
+    fn mob(mob_type: MobType, x: u32, y: u32) -> Target {
+        Target {
+            target_type: TargetType::Mob(mob_type),
+            bounds: Bounds::new(x, y, 40, 5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_weighted_scorer() {
+        let scorer = WeightedScorer::new(FarmingConfig::default().scoring_weights());
+        let close = mob(MobType::Passive, 380, 250);
+        let blocked = mob(MobType::Passive, 580, 250);
+        let aggressive = mob(MobType::Aggressive, 480, 250);
+        let far_aggressive = mob(MobType::Aggressive, 680, 250);
+        let pack = [
+            mob(MobType::Passive, 330, 400),
+            mob(MobType::Passive, 380, 420),
+            mob(MobType::Passive, 420, 400),
+        ];
+        let avoided = [(Bounds::new(590, 265, 20, 20), Instant::now(), 5000)];
+
+        let mobs = [close, blocked, aggressive, far_aggressive];
+        let context = ScoringContext {
+            center: Point::new(400, 300),
+            mobs: &mobs,
+            avoided_bounds: &avoided,
+            avoided_tracks: &[],
+            max_distance: 325,
+            limit_range: true,
+            allow_giants: true,
+            hp: 100,
+            mp: 100,
+        };
+        let score = |mob: &Target, context: &ScoringContext| scorer.score(mob, context);
+
+        // Aggressive mobs are preferred, blocked and out of range ones are discarded
+        assert!(score(&aggressive, &context) > score(&close, &context));
+        assert!(score(&close, &context).is_some());
+        assert_eq!(score(&blocked, &context), None);
+        assert_eq!(score(&far_aggressive, &context), None);
+        let unlimited = ScoringContext {
+            limit_range: false,
+            ..context
+        };
+        assert!(score(&far_aggressive, &unlimited).is_some());
+
+        // Pulling a pack is fine at full HP but not at low HP
+        let mut mobs = vec![mob(MobType::Passive, 100, 300)];
+        mobs.extend(pack);
+        let lone = mobs[0];
+        let context = ScoringContext {
+            mobs: &mobs,
+            avoided_bounds: &[],
+            ..context
+        };
+        assert!(score(&pack[1], &context) > score(&lone, &context));
+        let low_hp = ScoringContext { hp: 30, ..context };
+        assert!(score(&pack[1], &low_hp) < score(&lone, &low_hp));
+
+        let giant = Target {
+            rank: MobRank::Giant,
+            ..close
+        };
+        assert!(score(&giant, &context).is_some());
+        let no_giants = ScoringContext {
+            allow_giants: false,
+            ..context
+        };
+        assert_eq!(score(&giant, &no_giants), None);
+
+        let tracked = Target {
+            track_id: Some(17),
+            ..close
+        };
+        let avoided = [(17, Instant::now(), 10000)];
+        let context = ScoringContext {
+            avoided_tracks: &avoided,
+            ..context
+        };
+        assert_eq!(score(&tracked, &context), None);
+    }
+}
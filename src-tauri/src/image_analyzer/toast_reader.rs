@@ -0,0 +1,75 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use crate::data::GameEvent;
+
+/// Toasts aren't read every frame since they stay on screen for a few seconds.
+const READ_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Turns the text of the toast area into events, reporting each message once while it's shown.
+#[derive(Debug, Default, Clone)]
+pub struct ToastReader {
+    shown: HashSet<String>,
+    last_read: Option<Instant>,
+}
+
+impl ToastReader {
+    /// Whether enough time passed since the last read.
+    pub fn should_read(&self, now: Instant) -> bool {
+        self.last_read
+            .map(|last_read| now.saturating_duration_since(last_read) >= READ_INTERVAL)
+            .unwrap_or(true)
+    }
+
+    /// Classify the messages that appeared since the last read.
+    pub fn read(&mut self, text: &str, now: Instant) -> Vec<GameEvent> {
+        self.last_read = Some(now);
+
+        let shown = text
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|line| !line.is_empty())
+            .collect::<HashSet<_>>();
+        let events = shown
+            .iter()
+            .filter(|line| !self.shown.contains(*line))
+            .filter_map(|line| GameEvent::classify(line))
+            .collect();
+
+        self.shown = shown;
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ToastReader;
+    use crate::data::GameEvent;
+
+    #[test]
+    fn test_toast_reader() {
+        let now = Instant::now();
+        let mut reader = ToastReader::default();
+        assert!(reader.should_read(now));
+
+        let events = reader.read("Not enough MP.\n\n", now);
+        assert_eq!(events, vec![GameEvent::NotEnoughMp]);
+        assert!(!reader.should_read(now + Duration::from_millis(100)));
+
+        // Messages still on screen aren't reported again
+        let later = now + Duration::from_millis(500);
+        assert!(reader.should_read(later));
+        let events = reader.read("Not enough  MP.\nYour inventory is full.", later);
+        assert_eq!(events, vec![GameEvent::InventoryFull]);
+
+        reader.read("", later);
+        assert_eq!(
+            reader.read("Not enough MP.", later),
+            vec![GameEvent::NotEnoughMp]
+        );
+    }
+}
@@ -2,6 +2,9 @@ mod bot_config;
 mod frontend_info;
 
 pub use self::{
-    bot_config::{BotConfig, BotMode, FarmingConfig, ShoutConfig, Slot, SlotType, SupportConfig},
-    frontend_info::FrontendInfo,
+    bot_config::{
+        BotConfig, BotMode, DeathPolicy, FarmingConfig, GiantPolicy, ScoringWeights, ShoutConfig,
+        Slot, SlotType, SupportConfig,
+    },
+    frontend_info::{FrontendAlert, FrontendInfo},
 };
@@ -1,7 +1,17 @@
-use std::{fmt, fs::File, time::Instant};
+use std::{
+    fmt,
+    fs::File,
+    time::{Duration, Instant},
+};
 
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    data::{MobNameSettings, RecoverySettings, TargetInfo, WorldPoint},
+    image_analyzer::{CalibrationConfig, SlotObservation, SlotObservations},
+    movement::PatrolLeg,
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SlotType {
     Unused,
@@ -34,6 +44,69 @@ impl fmt::Display for SlotType {
     }
 }
 
+/// How giants and bosses are dealt with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GiantPolicy {
+    #[default]
+    Attack,
+    Avoid,
+    AttackAtFullHp,
+    FleeIfAggroed,
+}
+
+impl GiantPolicy {
+    /// Whether a giant can be targeted with the given HP.
+    pub fn allows_attack(&self, hp: u32) -> bool {
+        match self {
+            GiantPolicy::Attack => true,
+            GiantPolicy::AttackAtFullHp => hp >= 100,
+            GiantPolicy::Avoid | GiantPolicy::FleeIfAggroed => false,
+        }
+    }
+}
+
+/// What to do once the character died, unless the client is closed on death.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathPolicy {
+    /// Keep the revive dialog open until someone resurrects the character
+    WaitForResurrection,
+    #[default]
+    ReviveAtLodestar,
+    /// Stop acting and let the user know
+    StopAndAlert,
+}
+
+/// Weights of the terms used to score farming targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoringWeights {
+    /// Penalty for the distance to the player, relative to the search range
+    pub distance: f32,
+    /// Bonus for aggressive mobs, they'll come at us anyway
+    pub aggressive: f32,
+    /// Penalty for each other mob around the candidate
+    pub density: f32,
+    /// Extra penalty for each other mob around the candidate, scaled by missing HP/MP
+    pub low_resources: f32,
+    /// Penalty for mobs next to a recently avoided spot
+    pub avoided: f32,
+    /// Radius in pixels in which other mobs count as nearby
+    pub density_radius: u32,
+}
+
+/// The cooldown sweep shows up a moment after the key press.
+const SWEEP_DELAY: Duration = Duration::from_millis(300);
+
+/// Whether a slot can be used, from its configured cooldown and what the slot bar shows.
+fn is_slot_ready(last_usage: Option<Instant>, observation: Option<SlotObservation>) -> bool {
+    match (last_usage, observation) {
+        // Seen since it was used, the slot bar knows better than the configured cooldown
+        (Some(used), Some(seen)) if seen.seen_at >= used + SWEEP_DELAY => seen.ready,
+        // Used by the player
+        (None, Some(seen)) => seen.ready,
+        (last_usage, _) => last_usage.is_none(),
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SlotBar {
     slots: Option<[Slot; 10]>,
@@ -64,6 +137,7 @@ impl SlotBar {
         slot_type: SlotType,
         threshold: Option<u32>,
         last_slots_usage: [[Option<Instant>; 10]; 9],
+        observations: &SlotObservations,
         slot_bar_index: usize,
     ) -> Option<(usize, usize)> {
         self.slots()
@@ -73,7 +147,10 @@ impl SlotBar {
                 slot.slot_type == slot_type
                     && slot.slot_enabled
                     && slot.slot_threshold.unwrap_or(100) >= threshold.unwrap_or(0)
-                    && last_slots_usage[slot_bar_index][*index].is_none()
+                    && is_slot_ready(
+                        last_slots_usage[slot_bar_index][*index],
+                        observations[slot_bar_index][*index],
+                    )
             })
             .min_by(|x, y| x.1.slot_threshold.cmp(&y.1.slot_threshold))
             //.choose(rng)
@@ -86,6 +163,7 @@ impl SlotBar {
         slot_type: SlotType,
         threshold: Option<u32>,
         last_slots_usage: [[Option<Instant>; 10]; 9],
+        observations: &SlotObservations,
         slot_bar_index: usize,
     ) -> Vec<(usize, usize)> {
         self.slots()
@@ -95,7 +173,10 @@ impl SlotBar {
                 slot.slot_type == slot_type
                     && slot.slot_enabled
                     && slot.slot_threshold.unwrap_or(100) >= threshold.unwrap_or(0)
-                    && last_slots_usage[slot_bar_index][*index].is_none()
+                    && is_slot_ready(
+                        last_slots_usage[slot_bar_index][*index],
+                        observations[slot_bar_index][*index],
+                    )
             })
             .map(|(index, _)| (slot_bar_index, index))
             .collect()
@@ -136,6 +217,7 @@ pub enum BotMode {
     Farming,
     Support,
     AutoShout,
+    Scripted,
 }
 
 impl ToString for BotMode {
@@ -144,6 +226,7 @@ impl ToString for BotMode {
             BotMode::Farming => "farming",
             BotMode::Support => "support",
             BotMode::AutoShout => "auto_shout",
+            BotMode::Scripted => "scripted",
         }
         .to_string()
     }
@@ -156,6 +239,16 @@ pub struct FarmingConfig {
 
     /// Search for mob circle pattern
     circle_pattern_rotation_duration: Option<u64>,
+    /// Movement sequence of the profile walked instead of the circle pattern
+    circle_pattern_sequence: Option<String>,
+    /// Patrol legs, coordinates to walk to or names of movement sequences
+    patrol_route: Option<Vec<String>>,
+    /// Center of the farming zone, where patrolling started if unset
+    patrol_anchor: Option<String>,
+    /// Walk back to the anchor when further than this, 0 to roam freely
+    patrol_radius: Option<u32>,
+    /// World units covered by a minimap pixel, depends on the minimap zoom
+    minimap_units_per_pixel: Option<f32>,
 
     /// Disable farming
     farming_enabled: Option<bool>,
@@ -171,15 +264,41 @@ pub struct FarmingConfig {
 
     obstacle_avoidance_cooldown: Option<u64>,
     obstacle_avoidance_max_try: Option<u32>,
+    /// Movement sequence of the profile played to get unstuck
+    obstacle_avoidance_sequence: Option<String>,
 
     min_mobs_name_width: Option<u32>,
     max_mobs_name_width: Option<u32>,
+    /// Max horizontal/vertical gap between pixels of the same mob name
+    mobs_name_gap_x: Option<u32>,
+    mobs_name_gap_y: Option<u32>,
+
+    /// Only attack mobs whose name contains one of these
+    allowed_mobs_names: Option<Vec<String>>,
+    /// Never attack mobs whose name contains one of these
+    forbidden_mobs_names: Option<Vec<String>>,
+    min_mobs_level: Option<u32>,
+    max_mobs_level: Option<u32>,
+
+    giant_policy: Option<GiantPolicy>,
+    /// Mobs whose name contains one of these are giants
+    giant_mobs_names: Option<Vec<String>>,
+    /// Mob names at least this wide are giants, 0 disables it
+    giant_min_name_width: Option<u32>,
+
+    distance_weight: Option<f32>,
+    aggressive_weight: Option<f32>,
+    density_weight: Option<f32>,
+    low_resources_weight: Option<f32>,
+    avoided_weight: Option<f32>,
+    density_radius: Option<u32>,
 
     min_hp_attack: Option<u32>,
     pickup_duration: Option<u32>,
     hit_and_run: Option<bool>,
     auto_bird_view: Option<bool>,
     on_death_disconnect: Option<bool>,
+    on_death: Option<DeathPolicy>,
     interval_between_buffs: Option<u64>,
     mobs_timeout: Option<u64>,
 }
@@ -197,10 +316,50 @@ impl FarmingConfig {
         self.on_death_disconnect.unwrap_or(false)
     }
 
+    pub fn on_death(&self) -> DeathPolicy {
+        self.on_death.unwrap_or_default()
+    }
+
     pub fn circle_pattern_rotation_duration(&self) -> u64 {
         self.circle_pattern_rotation_duration.unwrap_or(30)
     }
 
+    pub fn circle_pattern_sequence(&self) -> Option<&str> {
+        self.circle_pattern_sequence
+            .as_deref()
+            .filter(|name| !name.is_empty())
+    }
+
+    pub fn obstacle_avoidance_sequence(&self) -> Option<&str> {
+        self.obstacle_avoidance_sequence
+            .as_deref()
+            .filter(|name| !name.is_empty())
+    }
+
+    pub fn patrol_route(&self) -> Vec<PatrolLeg> {
+        self.patrol_route
+            .iter()
+            .flatten()
+            .filter_map(|line| PatrolLeg::parse(line))
+            .collect()
+    }
+
+    pub fn patrol_anchor(&self) -> Option<WorldPoint> {
+        self.patrol_anchor.as_deref().and_then(WorldPoint::parse)
+    }
+
+    pub fn patrol_radius(&self) -> Option<f32> {
+        self.patrol_radius
+            .filter(|radius| *radius > 0)
+            .map(|radius| radius as f32)
+    }
+
+    pub fn minimap_units_per_pixel(&self) -> f32 {
+        self.minimap_units_per_pixel
+            .filter(|units| *units > 0.0)
+            .unwrap_or(2.0)
+    }
+
     pub fn obstacle_avoidance_cooldown(&self) -> u128 {
         self.obstacle_avoidance_cooldown.unwrap_or(5000).into()
     }
@@ -217,6 +376,24 @@ impl FarmingConfig {
         self.max_mobs_name_width.unwrap_or(180)
     }
 
+    pub fn mobs_name_gap_x(&self) -> u32 {
+        self.mobs_name_gap_x.unwrap_or(10)
+    }
+
+    pub fn mobs_name_gap_y(&self) -> u32 {
+        self.mobs_name_gap_y.unwrap_or(3)
+    }
+
+    pub fn mob_name_settings(&self) -> MobNameSettings {
+        MobNameSettings {
+            gap_x: self.mobs_name_gap_x(),
+            gap_y: self.mobs_name_gap_y(),
+            min_width: self.min_mobs_name_width(),
+            max_width: self.max_mobs_name_width(),
+            giant_width: self.giant_min_name_width(),
+        }
+    }
+
     pub fn min_hp_attack(&self) -> u32 {
         self.min_hp_attack.unwrap_or(0)
     }
@@ -280,12 +457,14 @@ impl FarmingConfig {
         slot_type: SlotType,
         threshold: Option<u32>,
         last_slots_usage: [[Option<Instant>; 10]; 9],
+        observations: &SlotObservations,
     ) -> Option<(usize, usize)> {
         for n in 0..9 {
             let found_index = self.slot_bars()[n].get_usable_slot_index(
                 slot_type,
                 threshold,
                 last_slots_usage,
+                observations,
                 n,
             );
             if let Some(found_index) = found_index {
@@ -300,6 +479,7 @@ impl FarmingConfig {
         slot_type: SlotType,
         threshold: Option<u32>,
         last_slots_usage: [[Option<Instant>; 10]; 9],
+        observations: &SlotObservations,
     ) -> Vec<(usize, usize)> {
         let mut indexes = Vec::new();
         for n in 0..9 {
@@ -307,6 +487,7 @@ impl FarmingConfig {
                 slot_type,
                 threshold,
                 last_slots_usage,
+                observations,
                 n,
             );
             indexes.extend(found_indexes);
@@ -321,13 +502,109 @@ impl FarmingConfig {
     pub fn prevent_already_attacked(&self) -> bool {
         self.prevent_already_attacked.unwrap_or(true)
     }
+
+    pub fn allowed_mobs_names(&self) -> Vec<String> {
+        self.allowed_mobs_names.clone().unwrap_or_default()
+    }
+
+    pub fn forbidden_mobs_names(&self) -> Vec<String> {
+        self.forbidden_mobs_names.clone().unwrap_or_default()
+    }
+
+    pub fn min_mobs_level(&self) -> u32 {
+        self.min_mobs_level.unwrap_or(0)
+    }
+
+    pub fn max_mobs_level(&self) -> u32 {
+        self.max_mobs_level.unwrap_or(0)
+    }
+
+    pub fn giant_policy(&self) -> GiantPolicy {
+        self.giant_policy.unwrap_or_default()
+    }
+
+    pub fn giant_mobs_names(&self) -> Vec<String> {
+        self.giant_mobs_names
+            .clone()
+            .unwrap_or_else(|| vec!["Giant".to_string()])
+    }
+
+    pub fn giant_min_name_width(&self) -> u32 {
+        self.giant_min_name_width.unwrap_or(0)
+    }
+
+    pub fn scoring_weights(&self) -> ScoringWeights {
+        ScoringWeights {
+            distance: self.distance_weight.unwrap_or(1.0),
+            aggressive: self.aggressive_weight.unwrap_or(2.0),
+            density: self.density_weight.unwrap_or(0.2),
+            low_resources: self.low_resources_weight.unwrap_or(1.0),
+            avoided: self.avoided_weight.unwrap_or(0.5),
+            density_radius: self.density_radius.unwrap_or(150),
+        }
+    }
+
+    /// Whether the name read from a target is the one of a giant.
+    pub fn is_giant_name(&self, name: &str) -> bool {
+        let name = Self::normalize_name(name);
+        Self::normalized_names(&self.giant_mobs_names()).any(|giant| name.contains(&giant))
+    }
+
+    /// Whether targets have to be read to be filtered by name or level.
+    /// Giants are told by the width of their name when it's set, without reading it.
+    pub fn has_target_filters(&self) -> bool {
+        let has_names = |names: Vec<String>| Self::normalized_names(&names).next().is_some();
+        has_names(self.allowed_mobs_names())
+            || has_names(self.forbidden_mobs_names())
+            || self.min_mobs_level() > 0
+            || self.max_mobs_level() > 0
+            || (self.giant_policy() != GiantPolicy::Attack && self.giant_min_name_width() == 0)
+    }
+
+    /// Check the target against the name lists and the level range.
+    /// Names match case insensitively and ignoring spaces, an unreadable level is allowed.
+    pub fn is_target_allowed(&self, info: &TargetInfo) -> bool {
+        let name = Self::normalize_name(&info.name);
+        let allowed = Self::normalized_names(&self.allowed_mobs_names()).collect::<Vec<_>>();
+        if !allowed.is_empty() && !allowed.iter().any(|allowed| name.contains(allowed)) {
+            return false;
+        }
+        if Self::normalized_names(&self.forbidden_mobs_names())
+            .any(|forbidden| name.contains(&forbidden))
+        {
+            return false;
+        }
+
+        match info.level {
+            Some(level) if level < self.min_mobs_level() => false,
+            Some(level) if self.max_mobs_level() > 0 && level > self.max_mobs_level() => false,
+            _ => true,
+        }
+    }
+
+    fn normalized_names(names: &[String]) -> impl Iterator<Item = String> + '_ {
+        names
+            .iter()
+            .map(|name| Self::normalize_name(name))
+            .filter(|name| !name.is_empty())
+    }
+
+    fn normalize_name(name: &str) -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct SupportConfig {
     slot_bars: Option<[SlotBar; 9]>,
     obstacle_avoidance_cooldown: Option<u64>,
+    /// Movement sequence of the profile played instead of the circle pattern when far from the target
+    obstacle_avoidance_sequence: Option<String>,
     on_death_disconnect: Option<bool>,
+    on_death: Option<DeathPolicy>,
     interval_between_buffs: Option<u64>,
 }
 
@@ -340,10 +617,20 @@ impl SupportConfig {
         self.on_death_disconnect.unwrap_or(true)
     }
 
+    pub fn on_death(&self) -> DeathPolicy {
+        self.on_death.unwrap_or_default()
+    }
+
     pub fn obstacle_avoidance_cooldown(&self) -> u128 {
         self.obstacle_avoidance_cooldown.unwrap_or(0).into()
     }
 
+    pub fn obstacle_avoidance_sequence(&self) -> Option<&str> {
+        self.obstacle_avoidance_sequence
+            .as_deref()
+            .filter(|name| !name.is_empty())
+    }
+
     pub fn slot_bars(&self) -> Vec<SlotBar> {
         self.slot_bars
             .map(|slots| slots.into_iter().collect::<Vec<_>>())
@@ -364,12 +651,14 @@ impl SupportConfig {
         slot_type: SlotType,
         threshold: Option<u32>,
         last_slots_usage: [[Option<Instant>; 10]; 9],
+        observations: &SlotObservations,
     ) -> Option<(usize, usize)> {
         for n in 0..9 {
             let found_index = self.slot_bars()[n].get_usable_slot_index(
                 slot_type,
                 threshold,
                 last_slots_usage,
+                observations,
                 n,
             );
             if let Some(found_index) = found_index {
@@ -384,6 +673,7 @@ impl SupportConfig {
         slot_type: SlotType,
         threshold: Option<u32>,
         last_slots_usage: [[Option<Instant>; 10]; 9],
+        observations: &SlotObservations,
     ) -> Vec<(usize, usize)> {
         let mut indexes = Vec::new();
         for n in 0..9 {
@@ -391,6 +681,7 @@ impl SupportConfig {
                 slot_type,
                 threshold,
                 last_slots_usage,
+                observations,
                 n,
             );
             indexes.extend(found_indexes);
@@ -415,6 +706,43 @@ impl ShoutConfig {
     }
 }
 
+/// Routine of the scripted mode, mobs and slots come from the farming config.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ScriptConfig {
+    script: Option<String>,
+}
+
+impl ScriptConfig {
+    pub fn script(&self) -> &str {
+        self.script.as_deref().unwrap_or_default()
+    }
+}
+
+/// How the bot gets back in game after losing the connection.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ConnectionConfig {
+    auto_reconnect: Option<bool>,
+    /// Position of the character on the selection screen, starting at 0
+    character_slot: Option<u32>,
+    max_reconnect_attempts: Option<u32>,
+    reconnect_delay: Option<u64>,
+    max_reconnect_delay: Option<u64>,
+    load_timeout: Option<u64>,
+}
+
+impl ConnectionConfig {
+    pub fn recovery_settings(&self) -> RecoverySettings {
+        RecoverySettings {
+            enabled: self.auto_reconnect.unwrap_or(true),
+            character_slot: self.character_slot.unwrap_or(0),
+            max_attempts: self.max_reconnect_attempts.unwrap_or(10),
+            delay: Duration::from_millis(self.reconnect_delay.unwrap_or(2000)),
+            max_delay: Duration::from_millis(self.max_reconnect_delay.unwrap_or(60000)),
+            load_timeout: Duration::from_millis(self.load_timeout.unwrap_or(60000)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct BotConfig {
     /// Change id to sync changes between frontend and backend
@@ -429,6 +757,13 @@ pub struct BotConfig {
     farming_config: FarmingConfig,
     support_config: SupportConfig,
     shout_config: ShoutConfig,
+    #[serde(default)]
+    script_config: ScriptConfig,
+    #[serde(default)]
+    connection_config: ConnectionConfig,
+    /// Bars and areas located by the calibration, defaults are used without it
+    #[serde(default)]
+    calibration_config: Option<CalibrationConfig>,
 }
 
 impl BotConfig {
@@ -461,6 +796,18 @@ impl BotConfig {
         &self.shout_config
     }
 
+    pub fn script_config(&self) -> &ScriptConfig {
+        &self.script_config
+    }
+
+    pub fn connection_config(&self) -> &ConnectionConfig {
+        &self.connection_config
+    }
+
+    pub fn calibration_config(&self) -> Option<&CalibrationConfig> {
+        self.calibration_config.as_ref()
+    }
+
     pub fn mode(&self) -> Option<BotMode> {
         self.mode.clone()
     }
@@ -486,3 +833,73 @@ impl BotConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::{is_slot_ready, FarmingConfig};
+    use crate::{data::TargetInfo, image_analyzer::SlotObservation};
+
+    #[test]
+    fn test_target_filters() {
+        let config: FarmingConfig = serde_json::from_value(json!({})).unwrap();
+        assert!(!config.has_target_filters());
+        // Giants need their name read unless the name width tells them apart
+        let config: FarmingConfig =
+            serde_json::from_value(json!({ "giant_policy": "Avoid" })).unwrap();
+        assert!(config.has_target_filters());
+        let config: FarmingConfig = serde_json::from_value(json!({
+            "giant_policy": "Avoid",
+            "giant_min_name_width": 60,
+        }))
+        .unwrap();
+        assert!(!config.has_target_filters());
+
+        let config: FarmingConfig = serde_json::from_value(json!({
+            "allowed_mobs_names": ["Aibatt", "Mushpang", ""],
+            "forbidden_mobs_names": ["Giant"],
+            "min_mobs_level": 5,
+            "max_mobs_level": 20,
+        }))
+        .unwrap();
+        assert!(config.has_target_filters());
+
+        let target = |name: &str, level: &str| TargetInfo::parse(name, level).unwrap();
+        assert_eq!(
+            target(" Small  Aibatt\n", "Lv. 7"),
+            TargetInfo {
+                name: "Small Aibatt".to_string(),
+                level: Some(7),
+            }
+        );
+        assert!(TargetInfo::parse(" \n", "7").is_none());
+
+        assert!(config.is_target_allowed(&target("Small Aibatt", "7")));
+        assert!(config.is_target_allowed(&target("mush pang", "")));
+        assert!(!config.is_target_allowed(&target("Giant Aibatt", "7")));
+        assert!(!config.is_target_allowed(&target("Lawolf", "7")));
+        assert!(!config.is_target_allowed(&target("Aibatt", "3")));
+        assert!(!config.is_target_allowed(&target("Aibatt", "21")));
+    }
+
+    #[test]
+    fn test_slot_readiness() {
+        let used = Instant::now();
+        let seen = |ready, after_ms| {
+            Some(SlotObservation {
+                ready,
+                seen_at: used + Duration::from_millis(after_ms),
+            })
+        };
+        assert!(is_slot_ready(None, None));
+        assert!(!is_slot_ready(Some(used), None));
+        // The sweep isn't shown right after the key press
+        assert!(!is_slot_ready(Some(used), seen(true, 100)));
+        assert!(is_slot_ready(Some(used), seen(true, 1000)));
+        // Still cooling down after the configured cooldown
+        assert!(!is_slot_ready(None, seen(false, 1000)));
+    }
+}
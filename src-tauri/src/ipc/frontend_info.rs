@@ -1,5 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+use crate::data::ExperienceStats;
+
+/// Something that needs the attention of the user.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum FrontendAlert {
+    Died,
+    ConnectionLost,
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Copy, Clone)]
 pub struct FrontendInfo {
     /* enemy_bounds: Option<Vec<Bounds>>,
@@ -9,9 +18,11 @@ pub struct FrontendInfo {
     last_search_duration: u64,
     kill_min_avg: f32,
     kill_hour_avg: f32,
+    exp_stats: ExperienceStats,
     is_attacking: bool,
     is_running: bool,
     is_alive: bool,
+    alert: Option<FrontendAlert>,
 }
 
 impl FrontendInfo {
@@ -36,6 +47,10 @@ impl FrontendInfo {
         self.last_search_duration = action_duration.0.try_into().unwrap_or(0);
         self.last_fight_duration = action_duration.1.try_into().unwrap_or(0);
     }
+    pub fn set_exp_stats(&mut self, exp_stats: ExperienceStats) {
+        self.exp_stats = exp_stats;
+    }
+
     pub fn set_is_attacking(&mut self, is_attacking: bool) {
         self.is_attacking = is_attacking;
     }
@@ -51,6 +66,10 @@ impl FrontendInfo {
     pub fn is_alive(&mut self) -> bool {
         self.is_alive
     }
+
+    pub fn set_alert(&mut self, alert: Option<FrontendAlert>) {
+        self.alert = alert;
+    }
     /// Serialize config to disk
     /* pub fn serialize(&self) {
         let config = {
@@ -9,6 +9,8 @@ mod image_analyzer;
 mod ipc;
 mod movement;
 mod platform;
+mod recorder;
+mod script;
 mod utils;
 
 use std::{
@@ -23,15 +25,18 @@ use guard::guard;
 use ipc::FrontendInfo;
 use parking_lot::RwLock;
 use slog::{Drain, Level, Logger};
-use tauri::{LogicalSize, Manager, Size, Window};
+use tauri::{LogicalSize, Manager, Window};
 
 use crate::{
-    behavior::{Behavior, FarmingBehavior, ShoutBehavior, SupportBehavior},
-    image_analyzer::ImageAnalyzer,
-    ipc::{BotConfig, BotMode},
-    movement::MovementAccessor,
-    platform::{eval_send_key, KeyMode, remove_all_markers},
-    utils::Timer,
+    behavior::{BotRunner, Iteration},
+    image_analyzer::{
+        CalibrationConfig, FrameSource, ImageAnalyzer, PngReplaySource, WindowCaptureSource,
+    },
+    ipc::BotConfig,
+    movement::{MovementAccessor, MovementSequences},
+    platform::{InputSink, RecordingInputSink, WebviewInputSink},
+    recorder::{RecorderConfig, SessionRecorder, SessionReplay},
+    utils::{Clock, SystemClock, Timer},
 };
 
 struct AppState {
@@ -69,6 +74,12 @@ fn main() {
     let drain = sentry_slog::SentryDrain::new(drain).fuse();
     let logger = Logger::root(drain.fuse(), slog::o!());
 
+    // Replay a recorded session segment instead of starting the app
+    if let Ok(path) = std::env::var("NEUZ_REPLAY_SESSION") {
+        replay_session(&path, &logger);
+        return;
+    }
+
     // Build app
     tauri::Builder::default()
         // .menu(tauri::Menu::os_default(&context.package_info().name))
@@ -84,6 +95,7 @@ fn main() {
             reset_profile,
             focus_client,
             toggle_main_size,
+            calibrate,
         ])
         .run(context)
         .expect("error while running tauri application");
@@ -124,6 +136,32 @@ fn toggle_main_size(
     }
 }
 
+/// Locate the status bars and text areas in a PNG, or in the client window without a path.
+#[tauri::command]
+fn calibrate(
+    path: Option<String>,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Option<CalibrationConfig> {
+    let logger = &state.logger;
+    let frame = match path {
+        Some(path) => PngReplaySource::open(path).ok()?.capture(logger),
+        None => {
+            let window = app_handle.get_window("client")?;
+            WindowCaptureSource::new(platform::get_window_id(&window)?).capture(logger)
+        }
+    }?;
+
+    let calibration = CalibrationConfig::locate(&frame);
+    match &calibration {
+        Some(calibration) => {
+            slog::info!(logger, "Calibrated client layout"; "bars" => calibration.status_bars.len(), "areas" => calibration.areas.len())
+        }
+        None => slog::warn!(logger, "Calibration failed, the stat tray was not found"),
+    }
+    calibration
+}
+
 #[tauri::command]
 fn focus_client(_state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
     let window = app_handle.get_window("client");
@@ -311,13 +349,34 @@ async fn create_window(profile_id: String, app_handle: tauri::AppHandle) {
     drop(main_window.set_title(format!("{} Neuz | MadrigalStreetCartel", profile_id).as_str()));
     //window.once_global("tauri://close-requested", move |_| app_handle.restart());
 }
-fn should_disconnect(config: &BotConfig) -> bool {
-    return match config.mode().unwrap() {
-        BotMode::Farming => config.farming_config().on_death_disconnect(),
-        BotMode::Support => config.support_config().on_death_disconnect(),
-        BotMode::AutoShout => true,
+fn replay_session(path: &str, logger: &Logger) {
+    let replay = match SessionReplay::open(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            slog::error!(logger, "Failed to open session"; "path" => path, "error" => e.to_string());
+            return;
+        }
     };
+
+    if replay.is_empty() {
+        slog::warn!(logger, "No frames to replay"; "path" => path);
+        return;
+    }
+
+    slog::info!(logger, "Replaying session"; "path" => path, "frames" => replay.len());
+    let steps = replay.run(logger);
+    for step in steps.iter().filter(|step| step.diverged()) {
+        slog::warn!(logger, "Replay diverged";
+            "iteration" => step.record.iteration,
+            "recorded_state" => &step.record.state,
+            "replayed_state" => &step.state,
+            "recorded_inputs" => format!("{:?}", step.record.inputs.iter().map(|input| &input.action).collect::<Vec<_>>()),
+            "replayed_inputs" => format!("{:?}", step.inputs));
+    }
+    let diverged = steps.iter().filter(|step| step.diverged()).count();
+    slog::info!(logger, "Replay finished"; "frames" => steps.len(), "diverged" => diverged);
 }
+
 #[tauri::command]
 fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: tauri::AppHandle) {
     let logger = state.logger.clone();
@@ -331,6 +390,8 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
         profile_id
     )
     .clone();
+    let sequences_path =
+        PathBuf::from(config_folder_path(&app_handle, &profile_id)).join("sequences");
 
     std::thread::spawn(move || {
         let logger = logger.clone();
@@ -378,18 +439,65 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
         send_config(&config.read());
 
         let window = app_handle.get_window("client").unwrap();
-        let mut image_analyzer: ImageAnalyzer = ImageAnalyzer::new(&window);
-        image_analyzer.window_id = platform::get_window_id(&window).unwrap_or(0);
+        let window_id = platform::get_window_id(&window).unwrap_or(0);
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let mut image_analyzer: ImageAnalyzer =
+            ImageAnalyzer::new(WindowCaptureSource::new(window_id), clock.clone());
+
+        // Replay recorded frames instead of capturing the client window
+        if let Ok(path) = std::env::var("NEUZ_REPLAY_FRAMES") {
+            match PngReplaySource::open(&path) {
+                Ok(source) if !source.is_empty() => {
+                    slog::info!(logger, "Replaying frames"; "path" => &path, "frames" => source.len());
+                    image_analyzer.set_frame_source(source.looping(true));
+                }
+                Ok(_) => slog::warn!(logger, "No frames to replay"; "path" => &path),
+                Err(e) => {
+                    slog::error!(logger, "Failed to open replay frames"; "path" => &path, "error" => e.to_string())
+                }
+            }
+        }
+
+        // Record the session for later replay
+        let mut recorder = std::env::var("NEUZ_RECORD_SESSION").ok().and_then(|path| {
+            let mut recorder_config = RecorderConfig::new(&path);
+            if let Some(scale) = std::env::var("NEUZ_RECORD_SCALE")
+                .ok()
+                .and_then(|scale| scale.parse().ok())
+            {
+                recorder_config.scale = scale;
+            }
+            match SessionRecorder::start(recorder_config, clock.clone(), &logger) {
+                Ok(recorder) => {
+                    slog::info!(logger, "Recording session"; "path" => &path);
+                    Some(recorder)
+                }
+                Err(e) => {
+                    slog::error!(logger, "Failed to start session recording"; "path" => &path, "error" => e.to_string());
+                    None
+                }
+            }
+        });
+
+        // Create input sink, keeping a log of the inputs when recording
+        let webview_input: Arc<dyn InputSink> = Arc::new(WebviewInputSink::new(window.clone()));
+        let recorded_input = recorder.as_ref().map(|_| {
+            Arc::new(RecordingInputSink::forwarding(
+                webview_input.clone(),
+                clock.clone(),
+            ))
+        });
+        let input: Arc<dyn InputSink> = match &recorded_input {
+            Some(recorded_input) => recorded_input.clone(),
+            None => webview_input,
+        };
 
         // Create movement accessor
-        let movement = MovementAccessor::new(window.clone() /*&accessor*/);
+        let movement = MovementAccessor::spawn(input.clone(), clock.clone() /*&accessor*/)
+            .with_sequences(MovementSequences::load(&sequences_path, &logger));
 
-        // Instantiate behaviors
-        let mut farming_behavior = FarmingBehavior::new(&logger, &movement, &window);
-        let mut shout_behavior = ShoutBehavior::new(&logger, &movement, &window);
-        let mut support_behavior = SupportBehavior::new(&logger, &movement, &window);
+        let mut bot_runner = BotRunner::new(&logger, &movement, &*input, &*clock);
 
-        let mut last_mode: Option<BotMode> = None;
         let mut frontend_info: Arc<RwLock<FrontendInfo>> =
             Arc::new(RwLock::new(FrontendInfo::deserialize_or_default()));
         send_info(&frontend_info.read());
@@ -415,11 +523,14 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
                 );
                 send_config(config);
                 last_config_change_id = config.change_id();
+                image_analyzer.set_calibration(config.calibration_config().cloned());
 
                 // Update behaviors
-                farming_behavior.update(config);
-                shout_behavior.update(config);
-                support_behavior.update(config);
+                bot_runner.update(config);
+
+                if let Some(recorder) = &recorder {
+                    recorder.set_config(config);
+                }
             }
 
             // Client window is closed
@@ -430,25 +541,17 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
 
             // Continue early if the bot is not engaged
             if !config.is_running() {
-                if !window.is_resizable().unwrap() {
-                    drop(window.set_resizable(true));
-                }
+                // Don't leave keys held down after disengaging
+                movement.cancel_all();
                 std::thread::sleep(std::time::Duration::from_millis(250));
                 timer.silence();
-                remove_all_markers(&window);
+                input.clear_overlay();
+                if let Some(recorded_input) = &recorded_input {
+                    recorded_input.take();
+                }
                 continue;
             }
 
-            if !config.farming_config().is_stop_fighting() {
-                drop(window.set_size(Size::Logical(LogicalSize {
-                    width: 800.0,
-                    height: 600.0,
-                })));
-                drop(window.set_resizable(false));
-            } else {
-                drop(window.set_resizable(true));
-            }
-
             frontend_info_mut.set_is_running(true);
 
             // Make sure an operation mode is set
@@ -458,107 +561,43 @@ fn start_bot(profile_id: String, state: tauri::State<AppState>, app_handle: taur
                 continue;
             });
 
-            // Check if mode is different from last mode
-            if let Some(last_mode) = last_mode.as_ref() {
-                if &mode != last_mode {
-                    slog::info!(logger, "Mode changed"; "old_mode" => last_mode.to_string(), "new_mode" => mode.to_string());
-
-                    // Stop all behaviors
-                    farming_behavior.stop(config);
-                    support_behavior.stop(config);
-                    shout_behavior.stop(config);
-
-                    // Start the current behavior
-                    match mode {
-                        BotMode::Farming => farming_behavior.start(config),
-                        BotMode::Support => support_behavior.start(config),
-                        BotMode::AutoShout => shout_behavior.start(config),
-                    }
-                }
-            }
-
             // Capture client window
-            image_analyzer.capture_window(&logger, config.farming_config());
+            image_analyzer.capture_frame(&logger, config.farming_config());
 
             // Try capturing the window contents
             if image_analyzer.image_is_some() {
-                // Update stats
-                image_analyzer
-                    .client_stats
-                    .update(&image_analyzer.clone(), &logger);
-
-                // Check disconnect
-                image_analyzer.detect_disconnect(&logger);
-
                 // Run the current behavior
-                guard!(let Some(mode) = config.mode() else { continue; });
-
-                // Reconnect
-                let is_disconnect = image_analyzer.is_disconnect;
-
-                if is_disconnect {
-                    eval_send_key(&window, "Enter", KeyMode::Press);
-                    std::thread::sleep(Duration::from_millis(1500));
-                    continue;
+                let iteration = bot_runner.run_iteration(
+                    &mode,
+                    config,
+                    &mut frontend_info_mut,
+                    &mut image_analyzer,
+                );
+                if iteration == Iteration::Exit {
+                    app_handle.exit(0);
+                    return;
                 }
 
-                // Stop bot in case of death
-                let is_alive = image_analyzer.client_stats.is_alive();
-
-                if !is_alive {
-                    if frontend_info_mut.is_alive() {
-                        let should_disconnect = should_disconnect(config);
-                        if should_disconnect {
-                            app_handle.exit(0);
-                            return;
-                        }
-
-                        frontend_info_mut.set_is_alive(false);
-                        frontend_info = Arc::new(RwLock::new(frontend_info_mut));
-                        // Send infos to frontend
-                        send_info(&frontend_info.read());
-                    } else {
-                        eval_send_key(&window, "Enter", KeyMode::Press);
-                        std::thread::sleep(Duration::from_millis(500));
-                    }
-                    continue;
-                } else if is_alive && !frontend_info_mut.is_alive() {
-                    frontend_info_mut.set_is_alive(true);
-                    let should_disconnect = should_disconnect(config);
-                    if !should_disconnect {
-                        eval_send_key(&window, "Escape", KeyMode::Press);
-                    }
+                // Record this iteration
+                if let (Some(recorder), Some(recorded_input)) =
+                    (recorder.as_mut(), recorded_input.as_ref())
+                {
+                    let (targets, state) = bot_runner.describe(&mode, config, &image_analyzer);
+                    recorder.record(
+                        &image_analyzer,
+                        Some(mode),
+                        targets,
+                        state,
+                        recorded_input.take(),
+                    );
                 }
-                match mode {
-                    BotMode::Farming => {
-                        farming_behavior.run_iteration(
-                            &mut frontend_info_mut,
-                            config,
-                            &mut image_analyzer,
-                        );
-                    }
-                    BotMode::AutoShout => {
-                        shout_behavior.run_iteration(
-                            &mut frontend_info_mut,
-                            config,
-                            &mut image_analyzer,
-                        );
-                    }
-                    BotMode::Support => {
-                        support_behavior.run_iteration(
-                            &mut frontend_info_mut,
-                            config,
-                            &mut image_analyzer,
-                        );
-                    }
+
+                if iteration != Iteration::Waited {
+                    frontend_info = Arc::new(RwLock::new(frontend_info_mut));
+                    // Send infos to frontend
+                    send_info(&frontend_info.read());
                 }
-                frontend_info = Arc::new(RwLock::new(frontend_info_mut));
-                // Send infos to frontend
-                send_info(&frontend_info.read());
             }
-
-            // Update last mode
-            last_mode = config.mode();
         }
     });
 }
@@ -1,5 +1,8 @@
 mod movement_accessor;
 mod movement_coordinator;
+mod movement_scheduler;
+mod movement_sequence;
+mod patrol;
 
 pub use self::{
     movement_accessor::MovementAccessor,
@@ -10,6 +13,21 @@ pub use self::{
         MovementDirection,
         RotationDirection,
     },
+    movement_scheduler::{
+        MovementHandle,
+        MovementPriority,
+        MovementScheduler,
+    },
+    movement_sequence::{
+        MovementSequences,
+        MovementStep,
+    },
+    patrol::{
+        Patrol,
+        PatrolLeg,
+        PatrolMove,
+        PatrolSight,
+    },
 };
 
 pub mod prelude {
@@ -25,8 +43,6 @@ pub mod prelude {
 macro_rules! play {
     ($scheduler:expr => [ $($movement:expr,)+ $(,)? ]) => {{
         let scheduler: &MovementAccessor = &$scheduler;
-        scheduler.schedule(|coordinator| {
-            coordinator.play([$($movement),+]);
-        });
+        scheduler.play([$($movement),+]);
     }};
 }
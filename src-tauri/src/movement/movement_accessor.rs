@@ -1,25 +1,73 @@
-use parking_lot::Mutex;
-use tauri::Window;
+use std::sync::Arc;
 
 //use crate::platform::PlatformAccessor;
-use super::MovementCoordinator;
+use super::{
+    Movement, MovementCoordinator, MovementHandle, MovementPriority, MovementScheduler,
+    MovementSequences, MovementStep,
+};
+use crate::{platform::InputSink, utils::Clock};
 
 pub struct MovementAccessor {
-    coordinator: Mutex<MovementCoordinator>,
+    scheduler: MovementScheduler,
+    sequences: MovementSequences,
 }
 
 impl MovementAccessor {
-    pub fn new(window: Window /*platform: &'a PlatformAccessor<'a>*/) -> Self {
+    /// Movements are played on the calling thread.
+    pub fn new(
+        input: Arc<dyn InputSink>,
+        clock: Arc<dyn Clock>, /*platform: &'a PlatformAccessor<'a>*/
+    ) -> Self {
         Self {
-            coordinator: Mutex::new(MovementCoordinator::new(window /*platform*/)),
+            scheduler: MovementScheduler::inline(MovementCoordinator::new(
+                input, clock, /*platform*/
+            )),
+            sequences: MovementSequences::default(),
         }
     }
 
-    pub fn schedule<F>(&self, func: F)
+    /// Movements are played on a thread of their own, see `start`.
+    pub fn spawn(input: Arc<dyn InputSink>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            scheduler: MovementScheduler::spawn(MovementCoordinator::new(input, clock)),
+            sequences: MovementSequences::default(),
+        }
+    }
+
+    /// Sequences behaviors can play by name.
+    pub fn with_sequences(mut self, sequences: MovementSequences) -> Self {
+        self.sequences = sequences;
+        self
+    }
+
+    /// Start playing movements without waiting for them.
+    pub fn start<'a, M>(&self, movements: M, priority: MovementPriority) -> MovementHandle
+    where
+        M: AsRef<[Movement<'a>]>,
+    {
+        let steps = movements.as_ref().iter().map(MovementStep::from).collect();
+        self.scheduler.start(steps, priority)
+    }
+
+    /// Play movements and wait until they're done.
+    pub fn play<'a, M>(&self, movements: M)
     where
-        F: Fn(&mut MovementCoordinator),
+        M: AsRef<[Movement<'a>]>,
     {
-        let mut coordinator = self.coordinator.lock();
-        func(&mut coordinator);
+        self.start(movements, MovementPriority::Normal).wait();
+    }
+
+    /// Start playing a sequence of the profile, `None` if it has none with this name.
+    pub fn play_sequence(&self, name: &str) -> Option<MovementHandle> {
+        let steps = self.sequences.get(name)?;
+        Some(
+            self.scheduler
+                .start(steps.to_vec(), MovementPriority::Normal),
+        )
+    }
+
+    /// Stop every movement and release the keys they held.
+    pub fn cancel_all(&self) {
+        self.scheduler.cancel_all();
     }
 }
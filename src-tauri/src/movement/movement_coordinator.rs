@@ -1,12 +1,19 @@
-use std::{ops::Range, thread, time::Duration};
+use std::{ops::Range, sync::Arc, time::Duration};
 
-use rand::Rng;
-use tauri::Window;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-use crate::platform::{eval_send_key, eval_send_message /* , PlatformAccessor*/, KeyMode};
+use super::MovementHandle;
+use crate::{
+    platform::{InputSink /* , PlatformAccessor*/, KeyMode},
+    utils::Clock,
+};
+
+/// Longest sleep between two checks for cancellation.
+const SLEEP_SLICE: Duration = Duration::from_millis(50);
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MovementDirection {
     Forward,
     Backward,
@@ -14,7 +21,7 @@ pub enum MovementDirection {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RotationDirection {
     Left,
     Right,
@@ -22,14 +29,14 @@ pub enum RotationDirection {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActionDuration {
     Fixed(u64),
     Random(Range<u64>),
 }
 
 impl ActionDuration {
-    fn to_duration(&self, rng: &mut rand::rngs::ThreadRng) -> Duration {
+    fn to_duration(&self, rng: &mut impl Rng) -> Duration {
         match self {
             Self::Fixed(ms) => Duration::from_millis(*ms),
             Self::Random(range) => Duration::from_millis(rng.gen_range(range.clone())),
@@ -55,17 +62,22 @@ pub enum Movement<'a> {
 }
 
 pub struct MovementCoordinator {
-    rng: rand::rngs::ThreadRng,
-    window: Window,
+    rng: StdRng,
+    input: Arc<dyn InputSink>,
+    clock: Arc<dyn Clock>,
+    /// Keys held down until a movement releases them
+    held_keys: Vec<String>,
 }
 
 impl<'a> MovementCoordinator {
-    pub fn new(window: Window) -> Self {
-        let rng = rand::thread_rng();
+    pub fn new(input: Arc<dyn InputSink>, clock: Arc<dyn Clock>) -> Self {
+        let rng = StdRng::from_entropy();
 
         Self {
             rng, /*, platform */
-            window,
+            input,
+            clock,
+            held_keys: vec![],
         }
     }
 
@@ -82,21 +94,50 @@ impl<'a> MovementCoordinator {
 
     // Movement functions
 
-    pub fn play<M>(&mut self, movements: M)
+    /// Play movements until they're done or the handle is cancelled.
+    /// Keys held by cancelled movements are released.
+    pub fn play<M>(&mut self, movements: M, handle: &MovementHandle)
     where
         M: AsRef<[Movement<'a>]>,
     {
-        for movement in movements.as_ref() {
-            self.play_single(movement.clone());
+        let held_before = self.held_keys.clone();
+        self.play_all(movements.as_ref(), handle);
+
+        if handle.is_cancelled() {
+            let held = self
+                .held_keys
+                .iter()
+                .filter(|key| !held_before.contains(key))
+                .cloned()
+                .collect::<Vec<_>>();
+            for key in held {
+                self.send_key(&key, KeyMode::Release);
+            }
+        }
+    }
+
+    /// Release every key still held down.
+    pub fn release_held_keys(&mut self) {
+        for key in std::mem::take(&mut self.held_keys) {
+            self.input.send_key(&key, KeyMode::Release);
+        }
+    }
+
+    fn play_all(&mut self, movements: &[Movement], handle: &MovementHandle) {
+        for movement in movements {
+            if handle.is_cancelled() {
+                return;
+            }
+            self.play_single(movement.clone(), handle);
         }
     }
 
-    fn play_single(&mut self, movement: Movement) {
+    fn play_single(&mut self, movement: Movement, handle: &MovementHandle) {
         match movement {
             Movement::Jump => {
-                eval_send_key(&self.window, "Space", KeyMode::Hold);
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                eval_send_key(&self.window, "Space", KeyMode::Release);
+                self.send_key("Space", KeyMode::Hold);
+                self.sleep(Duration::from_millis(500), handle);
+                self.send_key("Space", KeyMode::Release);
             }
             Movement::Move(direction, duration) => {
                 let key = match direction {
@@ -110,9 +151,10 @@ impl<'a> MovementCoordinator {
                         }
                     }
                 };
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.send_key(key, KeyMode::Hold);
+                let duration = duration.to_duration(&mut self.rng);
+                self.sleep(duration, handle);
+                self.send_key(key, KeyMode::Release);
             }
             Movement::Rotate(direction, duration) => {
                 let key = match direction {
@@ -126,43 +168,71 @@ impl<'a> MovementCoordinator {
                         }
                     }
                 };
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.send_key(key, KeyMode::Hold);
+                let duration = duration.to_duration(&mut self.rng);
+                self.sleep(duration, handle);
+                self.send_key(key, KeyMode::Release);
+            }
+            Movement::Wait(duration) => {
+                let duration = duration.to_duration(&mut self.rng);
+                self.sleep(duration, handle);
             }
-            Movement::Wait(duration) => thread::sleep(duration.to_duration(&mut self.rng)),
             Movement::Type(text) => {
-                eval_send_message(&self.window, &text);
+                self.input.send_message(&text);
             }
             Movement::PressKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Press);
+                self.send_key(key, KeyMode::Press);
             }
             Movement::HoldKeyFor(key, duration) => {
-                eval_send_key(&self.window, key, KeyMode::Hold);
-                thread::sleep(duration.to_duration(&mut self.rng));
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.send_key(key, KeyMode::Hold);
+                let duration = duration.to_duration(&mut self.rng);
+                self.sleep(duration, handle);
+                self.send_key(key, KeyMode::Release);
             }
             Movement::HoldKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Hold);
+                self.send_key(key, KeyMode::Hold);
             }
             Movement::HoldKeys(keys) => {
                 for key in keys {
-                    eval_send_key(&self.window, key, KeyMode::Hold);
+                    self.send_key(key, KeyMode::Hold);
                 }
             }
             Movement::ReleaseKey(key) => {
-                eval_send_key(&self.window, key, KeyMode::Release);
+                self.send_key(key, KeyMode::Release);
             }
             Movement::ReleaseKeys(keys) => {
                 for key in keys {
-                    eval_send_key(&self.window, key, KeyMode::Release);
+                    self.send_key(key, KeyMode::Release);
                 }
             }
             Movement::Repeat(times, movements) => {
                 for _ in 0..times {
-                    self.play(&movements);
+                    self.play_all(&movements, handle);
                 }
             }
         }
     }
+
+    fn send_key(&mut self, key: &str, mode: KeyMode) {
+        match mode {
+            KeyMode::Hold if !self.held_keys.iter().any(|held| held == key) => {
+                self.held_keys.push(key.to_string());
+            }
+            KeyMode::Release => self.held_keys.retain(|held| held != key),
+            _ => {}
+        }
+        self.input.send_key(key, mode);
+    }
+
+    /// Sleep in slices so cancelled movements stop early.
+    fn sleep(&self, duration: Duration, handle: &MovementHandle) {
+        let end = self.clock.now() + duration;
+        while !handle.is_cancelled() {
+            let left = end.saturating_duration_since(self.clock.now());
+            if left.is_zero() {
+                return;
+            }
+            self.clock.sleep(left.min(SLEEP_SLICE));
+        }
+    }
 }
@@ -0,0 +1,322 @@
+use std::{
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use super::{MovementCoordinator, MovementStep};
+
+/// How urgent a movement is, urgent ones cancel the running movement instead of waiting for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MovementPriority {
+    Normal,
+    Urgent,
+}
+
+#[derive(Debug, Default)]
+struct HandleState {
+    cancelled: AtomicBool,
+    finished: Mutex<bool>,
+    done: Condvar,
+}
+
+/// Handle of a scheduled movement.
+#[derive(Debug, Clone, Default)]
+pub struct MovementHandle(Arc<HandleState>);
+
+impl MovementHandle {
+    /// Stop the movement, keys it still holds are released.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Whether the movement is done, cancelled or not.
+    pub fn is_finished(&self) -> bool {
+        *self.0.finished.lock()
+    }
+
+    /// Block until the movement is done.
+    pub fn wait(&self) {
+        let mut finished = self.0.finished.lock();
+        while !*finished {
+            self.0.done.wait(&mut finished);
+        }
+    }
+
+    fn finish(&self) {
+        *self.0.finished.lock() = true;
+        self.0.done.notify_all();
+    }
+}
+
+/// Finishes the handle even when playing panics, so nobody waits on it forever.
+struct FinishOnDrop<'a>(&'a MovementHandle);
+
+impl Drop for FinishOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+struct Job {
+    steps: Vec<MovementStep>,
+    priority: MovementPriority,
+    handle: MovementHandle,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    running: Option<(MovementPriority, MovementHandle)>,
+    shutdown: bool,
+}
+
+struct Shared {
+    coordinator: Mutex<MovementCoordinator>,
+    queue: Mutex<Queue>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    fn run(&self, job: &Job) {
+        let _finish = FinishOnDrop(&job.handle);
+        if !job.handle.is_cancelled() {
+            let movements = job
+                .steps
+                .iter()
+                .map(MovementStep::movement)
+                .collect::<Vec<_>>();
+            self.coordinator.lock().play(&movements, &job.handle);
+        }
+    }
+
+    fn work(&self) {
+        loop {
+            let job = {
+                let mut queue = self.queue.lock();
+                loop {
+                    if queue.shutdown {
+                        return;
+                    }
+                    if let Some(job) = queue.jobs.pop_front() {
+                        queue.running = Some((job.priority, job.handle.clone()));
+                        break job;
+                    }
+                    self.wakeup.wait(&mut queue);
+                }
+            };
+            // A broken movement must not take the worker down with it, nor leave keys down
+            if panic::catch_unwind(AssertUnwindSafe(|| self.run(&job))).is_err() {
+                self.coordinator.lock().release_held_keys();
+            }
+            self.queue.lock().running = None;
+        }
+    }
+}
+
+/// Plays movements one after the other, either on the calling thread or on a worker thread.
+pub struct MovementScheduler {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl MovementScheduler {
+    /// Play movements on the calling thread, `start` returns once they're done.
+    pub fn inline(coordinator: MovementCoordinator) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                coordinator: Mutex::new(coordinator),
+                queue: Mutex::new(Queue::default()),
+                wakeup: Condvar::new(),
+            }),
+            worker: None,
+        }
+    }
+
+    /// Play movements on a thread of their own so callers don't block.
+    pub fn spawn(coordinator: MovementCoordinator) -> Self {
+        let mut scheduler = Self::inline(coordinator);
+        let shared = scheduler.shared.clone();
+        scheduler.worker = Some(thread::spawn(move || shared.work()));
+        scheduler
+    }
+
+    /// Queue movements after the ones of the same or a higher priority.
+    /// A lower priority movement that is playing gets cancelled.
+    pub fn start(&self, steps: Vec<MovementStep>, priority: MovementPriority) -> MovementHandle {
+        let handle = MovementHandle::default();
+        let job = Job {
+            steps,
+            priority,
+            handle: handle.clone(),
+        };
+        if self.worker.is_none() {
+            self.shared.run(&job);
+            return handle;
+        }
+
+        let mut queue = self.shared.queue.lock();
+        if let Some((running_priority, running)) = &queue.running {
+            if *running_priority < priority {
+                running.cancel();
+            }
+        }
+        let position = queue
+            .jobs
+            .iter()
+            .position(|queued| queued.priority < priority)
+            .unwrap_or(queue.jobs.len());
+        queue.jobs.insert(position, job);
+        self.shared.wakeup.notify_one();
+        handle
+    }
+
+    /// Cancel every queued and playing movement and release all held keys.
+    pub fn cancel_all(&self) {
+        {
+            let mut queue = self.shared.queue.lock();
+            for job in queue.jobs.drain(..) {
+                job.handle.cancel();
+                job.handle.finish();
+            }
+            if let Some((_, running)) = &queue.running {
+                running.cancel();
+            }
+        }
+        // Waits for the playing movement to notice it was cancelled
+        self.shared.coordinator.lock().release_held_keys();
+    }
+}
+
+impl Drop for MovementScheduler {
+    fn drop(&mut self) {
+        self.shared.queue.lock().shutdown = true;
+        self.shared.wakeup.notify_all();
+        if let Some(worker) = self.worker.take() {
+            drop(worker.join());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::{MovementPriority, MovementScheduler};
+    use crate::{
+        movement::{ActionDuration, MovementCoordinator, MovementStep},
+        platform::{InputAction, KeyMode, RecordingInputSink},
+        utils::VirtualClock,
+    };
+
+    #[test]
+    fn test_movement_scheduler() {
+        let input = Arc::new(RecordingInputSink::new());
+        let coordinator = MovementCoordinator::new(input.clone(), Arc::new(VirtualClock::new()));
+        let scheduler = MovementScheduler::spawn(coordinator);
+        let key = |key: &str, mode| InputAction::Key(key.to_string(), mode);
+        // Lasts for years of virtual time, only ends when cancelled
+        let long_walk = || {
+            vec![
+                MovementStep::HoldKeys(vec!["W".to_string(), "Space".to_string()]),
+                MovementStep::Wait(ActionDuration::Fixed(1 << 40)),
+                MovementStep::ReleaseKeys(vec!["Space".to_string(), "W".to_string()]),
+            ]
+        };
+        let wait_for_actions = |count| {
+            while input.actions().len() < count {
+                thread::yield_now();
+            }
+        };
+
+        // Starting doesn't block and cancelling releases the held keys
+        let walk = scheduler.start(long_walk(), MovementPriority::Normal);
+        wait_for_actions(2);
+        let queued = scheduler.start(
+            vec![MovementStep::PressKey("Z".to_string())],
+            MovementPriority::Normal,
+        );
+        assert!(!walk.is_finished());
+        walk.cancel();
+        walk.wait();
+        queued.wait();
+        assert_eq!(
+            input.actions(),
+            vec![
+                key("W", KeyMode::Hold),
+                key("Space", KeyMode::Hold),
+                key("W", KeyMode::Release),
+                key("Space", KeyMode::Release),
+                key("Z", KeyMode::Press),
+            ]
+        );
+
+        // Urgent movements preempt the playing one
+        input.take();
+        let walk = scheduler.start(long_walk(), MovementPriority::Normal);
+        wait_for_actions(2);
+        let heal = scheduler.start(
+            vec![MovementStep::PressKey("1".to_string())],
+            MovementPriority::Urgent,
+        );
+        heal.wait();
+        assert!(walk.is_cancelled());
+        assert_eq!(
+            input.actions()[2..],
+            [
+                key("W", KeyMode::Release),
+                key("Space", KeyMode::Release),
+                key("1", KeyMode::Press),
+            ]
+        );
+
+        // Keys held on purpose stay down until everything is cancelled
+        input.take();
+        scheduler
+            .start(
+                vec![MovementStep::HoldKey("W".to_string())],
+                MovementPriority::Normal,
+            )
+            .wait();
+        scheduler.cancel_all();
+        assert_eq!(
+            input.actions(),
+            vec![key("W", KeyMode::Hold), key("W", KeyMode::Release)]
+        );
+
+        // A movement that panics still finishes and the worker plays the next one
+        input.take();
+        let broken = scheduler.start(
+            vec![
+                MovementStep::HoldKey("W".to_string()),
+                MovementStep::Wait(ActionDuration::Random(200..200)),
+            ],
+            MovementPriority::Normal,
+        );
+        broken.wait();
+        scheduler
+            .start(
+                vec![MovementStep::PressKey("Z".to_string())],
+                MovementPriority::Normal,
+            )
+            .wait();
+        assert_eq!(
+            input.actions(),
+            vec![
+                key("W", KeyMode::Hold),
+                key("W", KeyMode::Release),
+                key("Z", KeyMode::Press),
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use guard::guard;
+use serde::{Deserialize, Serialize};
+use slog::Logger;
+
+use super::{ActionDuration, Movement, MovementDirection, RotationDirection};
+
+/// Owned `Movement` that can be stored in files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovementStep {
+    Jump,
+    Move(MovementDirection, ActionDuration),
+    Rotate(RotationDirection, ActionDuration),
+    PressKey(String),
+    HoldKeyFor(String, ActionDuration),
+    HoldKey(String),
+    HoldKeys(Vec<String>),
+    ReleaseKey(String),
+    ReleaseKeys(Vec<String>),
+    Repeat(u64, Vec<MovementStep>),
+    Type(String),
+    Wait(ActionDuration),
+}
+
+impl MovementStep {
+    pub fn movement(&self) -> Movement<'_> {
+        fn keys(keys: &[String]) -> Vec<&str> {
+            keys.iter().map(String::as_str).collect()
+        }
+
+        match self {
+            Self::Jump => Movement::Jump,
+            Self::Move(direction, duration) => Movement::Move(*direction, duration.clone()),
+            Self::Rotate(direction, duration) => Movement::Rotate(*direction, duration.clone()),
+            Self::PressKey(key) => Movement::PressKey(key),
+            Self::HoldKeyFor(key, duration) => Movement::HoldKeyFor(key, duration.clone()),
+            Self::HoldKey(key) => Movement::HoldKey(key),
+            Self::HoldKeys(held) => Movement::HoldKeys(keys(held)),
+            Self::ReleaseKey(key) => Movement::ReleaseKey(key),
+            Self::ReleaseKeys(released) => Movement::ReleaseKeys(keys(released)),
+            Self::Repeat(times, steps) => {
+                Movement::Repeat(*times, steps.iter().map(Self::movement).collect())
+            }
+            Self::Type(text) => Movement::Type(text.clone()),
+            Self::Wait(duration) => Movement::Wait(duration.clone()),
+        }
+    }
+
+    /// Random durations need a range to pick from, an empty one can't be played.
+    fn check(&self) -> Result<(), String> {
+        match self {
+            Self::Move(_, duration)
+            | Self::Rotate(_, duration)
+            | Self::HoldKeyFor(_, duration)
+            | Self::Wait(duration) => match duration {
+                ActionDuration::Random(range) if range.is_empty() => Err(format!(
+                    "`{}..{}` is an empty range",
+                    range.start, range.end
+                )),
+                _ => Ok(()),
+            },
+            Self::Repeat(_, steps) => steps.iter().try_for_each(Self::check),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<&Movement<'_>> for MovementStep {
+    fn from(movement: &Movement) -> Self {
+        fn keys(keys: &[&str]) -> Vec<String> {
+            keys.iter().map(|key| key.to_string()).collect()
+        }
+
+        match movement {
+            Movement::Jump => Self::Jump,
+            Movement::Move(direction, duration) => Self::Move(*direction, duration.clone()),
+            Movement::Rotate(direction, duration) => Self::Rotate(*direction, duration.clone()),
+            Movement::PressKey(key) => Self::PressKey(key.to_string()),
+            Movement::HoldKeyFor(key, duration) => {
+                Self::HoldKeyFor(key.to_string(), duration.clone())
+            }
+            Movement::HoldKey(key) => Self::HoldKey(key.to_string()),
+            Movement::HoldKeys(held) => Self::HoldKeys(keys(held)),
+            Movement::ReleaseKey(key) => Self::ReleaseKey(key.to_string()),
+            Movement::ReleaseKeys(released) => Self::ReleaseKeys(keys(released)),
+            Movement::Repeat(times, movements) => {
+                Self::Repeat(*times, movements.iter().map(Self::from).collect())
+            }
+            Movement::Type(text) => Self::Type(text.clone()),
+            Movement::Wait(duration) => Self::Wait(duration.clone()),
+        }
+    }
+}
+
+/// Sequences of a profile, one `<name>.json` file each.
+#[derive(Debug, Clone, Default)]
+pub struct MovementSequences {
+    sequences: HashMap<String, Vec<MovementStep>>,
+}
+
+impl MovementSequences {
+    /// Load every sequence of a folder, files that can't be read are skipped.
+    pub fn load<P>(dir: P, logger: &Logger) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let mut sequences = HashMap::new();
+        guard!(let Ok(entries) = fs::read_dir(dir) else {
+            return Self { sequences };
+        });
+
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+                continue;
+            }
+            guard!(let Some(name) = path.file_stem() else { continue });
+            let name = name.to_string_lossy().to_string();
+            let steps = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|json| {
+                    serde_json::from_str::<Vec<MovementStep>>(&json)
+                        .map_err(|error| error.to_string())
+                })
+                .and_then(|steps| {
+                    steps.iter().try_for_each(MovementStep::check)?;
+                    Ok(steps)
+                });
+            match steps {
+                Ok(steps) => {
+                    sequences.insert(name, steps);
+                }
+                Err(error) => {
+                    slog::warn!(logger, "Invalid movement sequence"; "name" => name, "error" => error)
+                }
+            }
+        }
+        slog::info!(logger, "Loaded movement sequences"; "count" => sequences.len());
+        Self { sequences }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[MovementStep]> {
+        self.sequences.get(name).map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use slog::Logger;
+
+    use super::{MovementSequences, MovementStep};
+    use crate::movement::{ActionDuration, Movement, RotationDirection};
+
+    #[test]
+    fn test_movement_sequences() {
+        let dir = std::env::temp_dir().join(format!(
+            "neuz_test_movement_sequences_{}",
+            std::process::id()
+        ));
+        drop(fs::remove_dir_all(&dir));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("loot_dance.json"),
+            r#"[
+                { "HoldKeys": ["W", "Space"] },
+                { "Repeat": [2, [{ "Rotate": ["Left", { "Random": { "start": 100, "end": 200 } }] }]] },
+                { "Wait": { "Fixed": 500 } },
+                { "ReleaseKeys": ["Space", "W"] }
+            ]"#,
+        )
+        .unwrap();
+        fs::write(dir.join("broken.json"), r#"[{ "Fly": 5 }]"#).unwrap();
+        fs::write(
+            dir.join("empty_range.json"),
+            r#"[{ "Wait": { "Random": { "start": 200, "end": 200 } } }]"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("inverted_range.json"),
+            r#"[{ "Repeat": [2, [{ "Move": ["Forward", { "Random": { "start": 400, "end": 200 } }] }]] }]"#,
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a sequence").unwrap();
+
+        let logger = Logger::root(slog::Discard, slog::o!());
+        let sequences = MovementSequences::load(&dir, &logger);
+        assert!(sequences.get("broken").is_none());
+        assert!(sequences.get("notes").is_none());
+        // Empty ranges would panic once played
+        assert!(sequences.get("empty_range").is_none());
+        assert!(sequences.get("inverted_range").is_none());
+
+        let steps = sequences.get("loot_dance").unwrap();
+        assert_eq!(
+            steps[1],
+            MovementStep::Repeat(
+                2,
+                vec![MovementStep::Rotate(
+                    RotationDirection::Left,
+                    ActionDuration::Random(100..200)
+                )]
+            )
+        );
+        assert!(matches!(
+            steps[0].movement(),
+            Movement::HoldKeys(keys) if keys == vec!["W", "Space"]
+        ));
+
+        // Missing folders have no sequences
+        let sequences = MovementSequences::load(dir.join("missing"), &logger);
+        assert!(sequences.get("loot_dance").is_none());
+
+        drop(fs::remove_dir_all(&dir));
+    }
+}
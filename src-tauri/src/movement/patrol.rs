@@ -0,0 +1,316 @@
+use std::cmp::Ordering;
+
+use super::{ActionDuration, Movement, MovementDirection, RotationDirection};
+use crate::data::WorldPoint;
+
+/// Waypoints closer than this are reached.
+const WAYPOINT_RADIUS: f32 = 5.0;
+/// Time the arrow keys take to turn the camera by a degree.
+const TURN_MS_PER_DEGREE: f32 = 4.0;
+/// Time it takes to walk a unit of distance.
+const WALK_MS_PER_UNIT: f32 = 150.0;
+/// Longest walk before the position is read again.
+const MAX_WALK_MS: u64 = 3000;
+/// Walk that tells which way the character faces.
+const PROBE_WALK_MS: u64 = 1000;
+/// Walks shorter than this are too short to tell the heading.
+const MIN_HEADING_DISTANCE: f32 = 1.0;
+
+/// A leg of a patrol route.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatrolLeg {
+    /// Play a movement sequence of the profile
+    Sequence(String),
+    /// Walk to a position
+    Waypoint(WorldPoint),
+}
+
+impl PatrolLeg {
+    /// Coordinates are waypoints, anything else names a sequence.
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        Some(match WorldPoint::parse(line) {
+            Some(point) => Self::Waypoint(point),
+            None => Self::Sequence(line.to_string()),
+        })
+    }
+}
+
+/// What to do for the next leg.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatrolMove<'a> {
+    Sequence(&'a str),
+    /// Turn by degrees, positive to the right, then walk forward for the duration in ms
+    Walk {
+        turn: f32,
+        duration: u64,
+    },
+}
+
+impl PatrolMove<'_> {
+    /// Movements of a walk, sequences are played by name.
+    pub fn movements(&self) -> Vec<Movement<'static>> {
+        let (turn, duration) = match self {
+            Self::Walk { turn, duration } => (turn, duration),
+            Self::Sequence(_) => return vec![],
+        };
+
+        let mut movements = vec![];
+        let turn_duration = (turn.abs() * TURN_MS_PER_DEGREE) as u64;
+        if turn_duration > 0 {
+            let direction = if *turn > 0.0 {
+                RotationDirection::Right
+            } else {
+                RotationDirection::Left
+            };
+            movements.push(Movement::Rotate(
+                direction,
+                ActionDuration::Fixed(turn_duration),
+            ));
+        }
+        movements.push(Movement::Move(
+            MovementDirection::Forward,
+            ActionDuration::Fixed(*duration),
+        ));
+        movements
+    }
+}
+
+/// What is known about the surroundings of the character.
+#[derive(Debug, Clone, Default)]
+pub struct PatrolSight {
+    pub position: Option<WorldPoint>,
+    /// Compass heading, told by the way the last walk went when unknown
+    pub heading: Option<f32>,
+    /// Mobs seen on the minimap
+    pub mobs: Vec<WorldPoint>,
+}
+
+/// Walks a route between mob searches and keeps the character close to its anchor.
+#[derive(Debug, Clone, Default)]
+pub struct Patrol {
+    leg: usize,
+    anchor: Option<WorldPoint>,
+    /// Position before the last walk, the way it went tells the heading
+    walked_from: Option<WorldPoint>,
+}
+
+impl Patrol {
+    /// The character moved on its own, the last walk no longer tells the heading.
+    pub fn interrupt(&mut self) {
+        self.walked_from = None;
+    }
+
+    /// Whether the position is further than the leash from the anchor.
+    /// The anchor is where patrolling started unless one is given.
+    pub fn is_outside(&self, position: WorldPoint, anchor: Option<WorldPoint>, leash: f32) -> bool {
+        anchor
+            .or(self.anchor)
+            .map_or(false, |anchor| position.distance_to(&anchor) > leash)
+    }
+
+    /// Next move along the route, back to the anchor first when further than the leash.
+    /// Mobs of the minimap inside the leash come before the route.
+    /// The anchor is where patrolling started unless one is given.
+    pub fn next<'a>(
+        &mut self,
+        route: &'a [PatrolLeg],
+        anchor: Option<WorldPoint>,
+        leash: Option<f32>,
+        sight: &PatrolSight,
+    ) -> Option<PatrolMove<'a>> {
+        let walked_from = self.walked_from.take();
+        if let Some(position) = sight.position {
+            let anchor = anchor.or(self.anchor).unwrap_or(position);
+            self.anchor = Some(anchor);
+            if leash.map_or(false, |leash| position.distance_to(&anchor) > leash) {
+                return Some(self.walk_to(anchor, position, sight.heading, walked_from));
+            }
+
+            let closest_mob = sight
+                .mobs
+                .iter()
+                .filter(|mob| !leash.map_or(false, |leash| mob.distance_to(&anchor) > leash))
+                .min_by(|a, b| {
+                    a.distance_to(&position)
+                        .partial_cmp(&b.distance_to(&position))
+                        .unwrap_or(Ordering::Equal)
+                });
+            if let Some(mob) = closest_mob {
+                return Some(self.walk_to(*mob, position, sight.heading, walked_from));
+            }
+        }
+
+        for _ in 0..route.len() {
+            match &route[self.leg % route.len()] {
+                PatrolLeg::Sequence(name) => {
+                    self.leg = (self.leg + 1) % route.len();
+                    return Some(PatrolMove::Sequence(name));
+                }
+                PatrolLeg::Waypoint(point) => match sight.position {
+                    Some(position) if position.distance_to(point) > WAYPOINT_RADIUS => {
+                        return Some(self.walk_to(*point, position, sight.heading, walked_from));
+                    }
+                    // Reached, or can't be found without a position
+                    _ => self.leg = (self.leg + 1) % route.len(),
+                },
+            }
+        }
+        None
+    }
+
+    fn walk_to(
+        &mut self,
+        target: WorldPoint,
+        position: WorldPoint,
+        heading: Option<f32>,
+        walked_from: Option<WorldPoint>,
+    ) -> PatrolMove<'static> {
+        self.walked_from = Some(position);
+        let heading = heading.or_else(|| {
+            walked_from
+                .filter(|from| from.distance_to(&position) >= MIN_HEADING_DISTANCE)
+                .map(|from| from.heading_to(&position))
+        });
+
+        match heading {
+            Some(heading) => PatrolMove::Walk {
+                turn: WorldPoint::turn_between(heading, position.heading_to(&target)),
+                duration: ((position.distance_to(&target) * WALK_MS_PER_UNIT) as u64)
+                    .min(MAX_WALK_MS),
+            },
+            None => PatrolMove::Walk {
+                turn: 0.0,
+                duration: PROBE_WALK_MS,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Patrol, PatrolLeg, PatrolMove, PatrolSight, PROBE_WALK_MS};
+    use crate::{
+        data::WorldPoint,
+        movement::{ActionDuration, Movement, RotationDirection},
+    };
+
+    #[test]
+    fn test_patrol() {
+        let route = ["loot_dance", "100, 0, 50", ""]
+            .into_iter()
+            .filter_map(PatrolLeg::parse)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            route,
+            vec![
+                PatrolLeg::Sequence("loot_dance".to_string()),
+                PatrolLeg::Waypoint(WorldPoint::new(100.0, 50.0)),
+            ]
+        );
+
+        let at = |x, z| PatrolSight {
+            position: Some(WorldPoint::new(x, z)),
+            ..Default::default()
+        };
+        let mut patrol = Patrol::default();
+        assert_eq!(
+            patrol.next(&route, None, Some(60.0), &at(100.0, 100.0)),
+            Some(PatrolMove::Sequence("loot_dance"))
+        );
+        // Walks ahead to learn the heading, then turns towards the waypoint
+        let probe = PatrolMove::Walk {
+            turn: 0.0,
+            duration: PROBE_WALK_MS,
+        };
+        assert_eq!(
+            patrol.next(&route, None, Some(60.0), &at(100.0, 100.0)),
+            Some(probe.clone())
+        );
+        let turn = match patrol.next(&route, None, Some(60.0), &at(110.0, 100.0)) {
+            Some(PatrolMove::Walk { turn, .. }) => turn,
+            other => panic!("no walk: {:?}", other),
+        };
+        assert!((turn + 101.3).abs() < 0.1, "turn {}", turn);
+        // Reached the waypoint, the route starts over
+        assert_eq!(
+            patrol.next(&route, None, Some(60.0), &at(101.0, 52.0)),
+            Some(PatrolMove::Sequence("loot_dance"))
+        );
+
+        // Too far from where it started, walks back first
+        assert!(!patrol.is_outside(WorldPoint::new(150.0, 100.0), None, 60.0));
+        assert!(patrol.is_outside(WorldPoint::new(200.0, 100.0), None, 60.0));
+        patrol.interrupt();
+        assert_eq!(
+            patrol.next(&route, None, Some(60.0), &at(200.0, 100.0)),
+            Some(probe)
+        );
+        let (turn, duration) = match patrol.next(&route, None, Some(60.0), &at(200.0, 110.0)) {
+            Some(PatrolMove::Walk { turn, duration }) => (turn, duration),
+            other => panic!("no walk: {:?}", other),
+        };
+        assert!((turn - 95.7).abs() < 0.1, "turn {}", turn);
+        assert_eq!(duration, 3000);
+
+        // The minimap tells the heading right away
+        let sight = PatrolSight {
+            heading: Some(270.0),
+            ..at(200.0, 100.0)
+        };
+        let walk_back = PatrolMove::Walk {
+            turn: 0.0,
+            duration: 3000,
+        };
+        assert_eq!(
+            patrol.next(&route, None, Some(60.0), &sight),
+            Some(walk_back.clone())
+        );
+
+        // Walks to the closest mob of the minimap inside the leash
+        let sight = PatrolSight {
+            heading: Some(0.0),
+            mobs: vec![WorldPoint::new(100.0, 30.0), WorldPoint::new(130.0, 100.0)],
+            ..at(110.0, 100.0)
+        };
+        assert_eq!(
+            patrol.next(&route, None, Some(60.0), &sight),
+            Some(PatrolMove::Walk {
+                turn: 90.0,
+                duration: 3000,
+            })
+        );
+        // Mobs outside are left alone, it goes on with the waypoint
+        let sight = PatrolSight {
+            mobs: vec![WorldPoint::new(100.0, 30.0)],
+            ..sight
+        };
+        let turn = match patrol.next(&route, None, Some(60.0), &sight) {
+            Some(PatrolMove::Walk { turn, .. }) => turn,
+            other => panic!("no walk: {:?}", other),
+        };
+        assert!((turn + 11.3).abs() < 0.1, "turn {}", turn);
+
+        // Waypoints need a position
+        let waypoints = &route[1..];
+        assert_eq!(
+            patrol.next(waypoints, None, None, &PatrolSight::default()),
+            None
+        );
+
+        assert!(matches!(
+            PatrolMove::Walk {
+                turn: -90.0,
+                duration: 500
+            }
+            .movements()[..],
+            [
+                Movement::Rotate(RotationDirection::Left, ActionDuration::Fixed(360)),
+                Movement::Move(_, ActionDuration::Fixed(500)),
+            ]
+        ));
+    }
+}
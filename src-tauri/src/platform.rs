@@ -1,6 +1,11 @@
 pub mod shared;
 pub use self::shared::*;
 
+mod input_sink;
+pub use self::input_sink::{
+    InputAction, InputSink, RecordedInput, RecordingInputSink, WebviewInputSink,
+};
+
 //
 // Windows
 //
@@ -0,0 +1,217 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, Window};
+
+use super::{
+    draw_bounds_rect, eval_mob_click, eval_mouse_move, eval_send_key, eval_send_message,
+    remove_all_markers, KeyMode,
+};
+use crate::{
+    data::{Bounds, Point},
+    image_analyzer::Color,
+    utils::{Clock, SystemClock},
+};
+
+/// Receives every input and overlay action emitted by behaviors.
+pub trait InputSink: Send + Sync {
+    /// Press, hold or release a key.
+    fn send_key(&self, key: &str, mode: KeyMode);
+
+    /// Move the mouse cursor to the given position.
+    fn mouse_move(&self, pos: Point);
+
+    /// Move the mouse cursor to the given position and click on the target below it.
+    fn mouse_click(&self, pos: Point);
+
+    /// Enter text into the chat input.
+    fn send_message(&self, text: &str);
+
+    /// Draw a debug rectangle on top of the client.
+    fn draw_bounds(&self, bounds: Bounds, color: Color);
+
+    /// Remove all debug rectangles.
+    fn clear_overlay(&self);
+
+    /// Close the application.
+    fn exit_app(&self);
+
+    /// Press the key mapped to a slot of the given slot bar.
+    fn send_slot(&self, slot_bar_index: usize, slot_index: usize) {
+        self.send_key(&format!("F{}", slot_bar_index + 1), KeyMode::Press);
+        self.send_key(&slot_index.to_string(), KeyMode::Press);
+    }
+}
+
+/// Sends inputs to the client webview by evaluating JavaScript.
+pub struct WebviewInputSink {
+    window: Window,
+}
+
+impl WebviewInputSink {
+    pub fn new(window: Window) -> Self {
+        Self { window }
+    }
+}
+
+impl InputSink for WebviewInputSink {
+    fn send_key(&self, key: &str, mode: KeyMode) {
+        eval_send_key(&self.window, key, mode);
+    }
+
+    fn mouse_move(&self, pos: Point) {
+        eval_mouse_move(&self.window, pos);
+    }
+
+    fn mouse_click(&self, pos: Point) {
+        self.mouse_move(pos);
+        thread::sleep(Duration::from_millis(25));
+        eval_mob_click(&self.window, pos);
+    }
+
+    fn send_message(&self, text: &str) {
+        eval_send_message(&self.window, text);
+    }
+
+    fn draw_bounds(&self, bounds: Bounds, color: Color) {
+        draw_bounds_rect(&self.window, bounds.x, bounds.y, bounds.w, bounds.h, color);
+    }
+
+    fn clear_overlay(&self) {
+        remove_all_markers(&self.window);
+    }
+
+    fn exit_app(&self) {
+        self.window.app_handle().exit(0);
+    }
+}
+
+/// A single action received by an `InputSink`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputAction {
+    Key(String, KeyMode),
+    MouseMove(Point),
+    MouseClick(Point),
+    Message(String),
+    DrawBounds(Bounds, Color),
+    ClearOverlay,
+    ExitApp,
+}
+
+/// An `InputAction` along with the time it was received at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedInput {
+    /// Time elapsed since the recorder was created.
+    pub elapsed: Duration,
+    pub action: InputAction,
+}
+
+/// Records all received actions into a timestamped log, optionally forwarding them to another sink.
+pub struct RecordingInputSink {
+    inner: Option<Arc<dyn InputSink>>,
+    clock: Arc<dyn Clock>,
+    start: Instant,
+    log: Mutex<Vec<RecordedInput>>,
+}
+
+impl RecordingInputSink {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Create a recorder timestamping actions with the given clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: None,
+            start: clock.now(),
+            clock,
+            log: Mutex::new(vec![]),
+        }
+    }
+
+    /// Create a recorder sending every action on to `inner` after recording it.
+    pub fn forwarding(inner: Arc<dyn InputSink>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: Some(inner),
+            ..Self::with_clock(clock)
+        }
+    }
+
+    /// Get the recorded actions without their timestamps.
+    #[cfg(test)]
+    pub fn actions(&self) -> Vec<InputAction> {
+        self.log
+            .lock()
+            .iter()
+            .map(|input| input.action.clone())
+            .collect()
+    }
+
+    /// Take the recorded log, leaving an empty one behind.
+    pub fn take(&self) -> Vec<RecordedInput> {
+        std::mem::take(&mut *self.log.lock())
+    }
+
+    fn record(&self, action: InputAction) -> Option<&dyn InputSink> {
+        self.log.lock().push(RecordedInput {
+            elapsed: self.clock.elapsed(self.start),
+            action,
+        });
+        self.inner.as_deref()
+    }
+}
+
+impl Default for RecordingInputSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputSink for RecordingInputSink {
+    fn send_key(&self, key: &str, mode: KeyMode) {
+        if let Some(inner) = self.record(InputAction::Key(key.to_string(), mode)) {
+            inner.send_key(key, mode);
+        }
+    }
+
+    fn mouse_move(&self, pos: Point) {
+        if let Some(inner) = self.record(InputAction::MouseMove(pos)) {
+            inner.mouse_move(pos);
+        }
+    }
+
+    fn mouse_click(&self, pos: Point) {
+        if let Some(inner) = self.record(InputAction::MouseClick(pos)) {
+            inner.mouse_click(pos);
+        }
+    }
+
+    fn send_message(&self, text: &str) {
+        if let Some(inner) = self.record(InputAction::Message(text.to_string())) {
+            inner.send_message(text);
+        }
+    }
+
+    fn draw_bounds(&self, bounds: Bounds, color: Color) {
+        if let Some(inner) = self.record(InputAction::DrawBounds(bounds, color)) {
+            inner.draw_bounds(bounds, color);
+        }
+    }
+
+    fn clear_overlay(&self) {
+        if let Some(inner) = self.record(InputAction::ClearOverlay) {
+            inner.clear_overlay();
+        }
+    }
+
+    fn exit_app(&self) {
+        if let Some(inner) = self.record(InputAction::ExitApp) {
+            inner.exit_app();
+        }
+    }
+}
@@ -1,11 +1,10 @@
-use std::time::Duration;
-
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
+use serde::{Deserialize, Serialize};
 use tauri::Window;
 
 use crate::{data::Point, image_analyzer::Color};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum KeyMode {
     Press,
     Hold,
@@ -13,6 +12,7 @@ pub enum KeyMode {
 }
 
 // For visual recognition: Avoids mouse clicks outside the window by ignoring monster names that are too close to the bottom of the GUI
+// Measured from the bottom edge, the action bar keeps its size whatever the window size
 pub const IGNORE_AREA_BOTTOM: u32 = 110;
 
 /// Get the native window id.
@@ -57,16 +57,6 @@ pub fn eval_send_key(window: &Window, key: &str, mode: KeyMode) {
     }
 }
 
-pub fn send_slot_eval(window: &Window, slot_bar_index: usize, k: usize) {
-    eval_send_key(
-        window,
-        format!("F{}", slot_bar_index + 1).to_string().as_str(),
-        KeyMode::Press,
-    );
-    eval_send_key(window, k.to_string().as_str(), KeyMode::Press);
-    // println!("send_slot_eval k:{} slot_bar_index:{}", k.to_string().as_str(), format!("F{}", slot_bar_index + 1));
-}
-
 pub fn draw_bounds_rect(window: &Window, x: u32, y: u32, w: u32, h: u32, color: Color) {
     drop(
         window.eval(
@@ -134,8 +124,6 @@ pub fn eval_mouse_move(window: &Window, pos: Point) {
 }
 
 pub fn eval_mob_click(window: &Window, pos: Point) {
-    eval_mouse_move(window, pos);
-    std::thread::sleep(Duration::from_millis(25));
     drop(
         window.eval(
             format!(
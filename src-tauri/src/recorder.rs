@@ -0,0 +1,16 @@
+//! Session recording and replay for post-mortem debugging.
+//!
+//! Sessions are split into segments, each one holding the bot config it was recorded with,
+//! a `records.jsonl` file with one `FrameRecord` per main loop iteration and the captured frames.
+
+mod frame_record;
+pub use self::frame_record::{FrameRecord, StatsRecord};
+
+mod session_recorder;
+pub use self::session_recorder::{RecorderConfig, SessionRecorder};
+
+mod session_replay;
+pub use self::session_replay::SessionReplay;
+
+const CONFIG_FILE: &str = "config.json";
+const RECORDS_FILE: &str = "records.jsonl";
@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{ClientStats, Target},
+    ipc::BotMode,
+    platform::RecordedInput,
+};
+
+/// Stat values read from a frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsRecord {
+    pub hp: u32,
+    pub mp: u32,
+    pub fp: u32,
+    pub target_hp: u32,
+    pub target_mp: u32,
+}
+
+impl From<&ClientStats> for StatsRecord {
+    fn from(stats: &ClientStats) -> Self {
+        Self {
+            hp: stats.hp.value,
+            mp: stats.mp.value,
+            fp: stats.fp.value,
+            target_hp: stats.target_hp.value,
+            target_mp: stats.target_mp.value,
+        }
+    }
+}
+
+/// Everything the bot saw and did during a single main loop iteration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub iteration: u64,
+    /// Time the frame was captured at, relative to the start of the session
+    pub elapsed: Duration,
+    /// File name of the frame inside the segment directory
+    pub frame: Option<String>,
+    /// Size of the frame before it was downscaled
+    pub frame_size: (u32, u32),
+    pub mode: Option<BotMode>,
+    pub stats: StatsRecord,
+    pub targets: Vec<Target>,
+    pub state: String,
+    pub inputs: Vec<RecordedInput>,
+}
@@ -0,0 +1,275 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use image::imageops::FilterType;
+use libscreenshot::ImageBuffer;
+use slog::Logger;
+
+use super::{FrameRecord, StatsRecord, CONFIG_FILE, RECORDS_FILE};
+use crate::{
+    data::Target,
+    image_analyzer::ImageAnalyzer,
+    ipc::{BotConfig, BotMode},
+    platform::RecordedInput,
+    utils::Clock,
+};
+
+/// Settings of a `SessionRecorder`.
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    /// Directory sessions are recorded into
+    pub path: PathBuf,
+    /// Frames are downscaled by this factor before being saved, half their size by default
+    pub scale: u32,
+    /// Number of iterations recorded into a single segment
+    pub frames_per_segment: usize,
+    /// Oldest segments are removed once a session holds more than this
+    pub max_segments: usize,
+}
+
+impl RecorderConfig {
+    pub fn new<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            path: path.into(),
+            scale: 2,
+            frames_per_segment: 300,
+            max_segments: 20,
+        }
+    }
+}
+
+enum Message {
+    Config(Box<BotConfig>),
+    Frame(FrameRecord, Option<ImageBuffer>),
+}
+
+/// Records main loop iterations to disk from a background thread.
+pub struct SessionRecorder {
+    sender: Option<SyncSender<Message>>,
+    writer: Option<JoinHandle<()>>,
+    clock: Arc<dyn Clock>,
+    start: Instant,
+    iteration: u64,
+    logger: Logger,
+}
+
+impl SessionRecorder {
+    /// Start recording a new session into a timestamped directory below `config.path`.
+    pub fn start(
+        config: RecorderConfig,
+        clock: Arc<dyn Clock>,
+        logger: &Logger,
+    ) -> io::Result<Self> {
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+        let path = config.path.join(session.to_string());
+        fs::create_dir_all(&path)?;
+
+        let (sender, receiver) = sync_channel(16);
+        let writer = SegmentWriter {
+            path,
+            config,
+            bot_config: None,
+            segment: None,
+            segment_count: 0,
+        };
+        let writer_logger = logger.clone();
+        let writer = thread::spawn(move || writer.run(receiver, writer_logger));
+
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            start: clock.now(),
+            clock,
+            iteration: 0,
+            logger: logger.clone(),
+        })
+    }
+
+    /// Start a new segment recorded with the given config.
+    pub fn set_config(&self, config: &BotConfig) {
+        self.send(Message::Config(Box::new(config.clone())));
+    }
+
+    /// Record the current frame of the analyzer along with what the behavior made of it.
+    pub fn record(
+        &mut self,
+        analyzer: &ImageAnalyzer,
+        mode: Option<BotMode>,
+        targets: Vec<Target>,
+        state: String,
+        inputs: Vec<RecordedInput>,
+    ) {
+        let frame = analyzer.image().cloned();
+        let captured_at = analyzer.captured_at().unwrap_or_else(|| self.clock.now());
+        let record = FrameRecord {
+            iteration: self.iteration,
+            elapsed: captured_at.saturating_duration_since(self.start),
+            frame: frame
+                .as_ref()
+                .map(|_| format!("frame_{:06}.png", self.iteration)),
+            frame_size: frame
+                .as_ref()
+                .map(|frame| frame.dimensions())
+                .unwrap_or_default(),
+            mode,
+            stats: StatsRecord::from(&analyzer.client_stats),
+            targets,
+            state,
+            inputs,
+        };
+        self.iteration += 1;
+        self.send(Message::Frame(record, frame));
+    }
+
+    fn send(&self, message: Message) {
+        if let Some(sender) = &self.sender {
+            // Never block the bot, drop the record if the writer can't keep up
+            if let Err(TrySendError::Full(_)) = sender.try_send(message) {
+                slog::warn!(
+                    self.logger,
+                    "Session recorder is lagging behind, dropping record"
+                );
+            }
+        }
+    }
+}
+
+impl Drop for SessionRecorder {
+    fn drop(&mut self) {
+        // Close the channel and wait for pending records to be written
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            drop(writer.join());
+        }
+    }
+}
+
+struct Segment {
+    path: PathBuf,
+    records: BufWriter<File>,
+    len: usize,
+}
+
+struct SegmentWriter {
+    path: PathBuf,
+    config: RecorderConfig,
+    bot_config: Option<BotConfig>,
+    segment: Option<Segment>,
+    segment_count: u64,
+}
+
+impl SegmentWriter {
+    fn run(mut self, receiver: Receiver<Message>, logger: Logger) {
+        while let Ok(message) = receiver.recv() {
+            if let Err(e) = self.handle(message) {
+                slog::error!(logger, "Failed to write session record"; "error" => e.to_string());
+            }
+        }
+    }
+
+    fn handle(&mut self, message: Message) -> io::Result<()> {
+        match message {
+            Message::Config(config) => {
+                self.bot_config = Some(*config);
+                if self.segment.as_ref().map(|s| s.len > 0).unwrap_or(false) {
+                    self.next_segment()?;
+                } else if let Some(segment) = &self.segment {
+                    self.write_config(&segment.path)?;
+                }
+            }
+            Message::Frame(record, frame) => {
+                let is_full = self
+                    .segment
+                    .as_ref()
+                    .map(|segment| segment.len >= self.config.frames_per_segment)
+                    .unwrap_or(true);
+                if is_full {
+                    self.next_segment()?;
+                }
+
+                let scale = self.config.scale.max(1);
+                let segment = self.segment.as_mut().unwrap();
+                if let (Some(frame), Some(name)) = (frame, &record.frame) {
+                    let frame = if scale > 1 {
+                        image::imageops::resize(
+                            &frame,
+                            frame.width() / scale,
+                            frame.height() / scale,
+                            FilterType::Nearest,
+                        )
+                    } else {
+                        frame
+                    };
+                    frame
+                        .save(segment.path.join(name))
+                        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+                }
+
+                serde_json::to_writer(&mut segment.records, &record)?;
+                segment.records.write_all(b"\n")?;
+                segment.records.flush()?;
+                segment.len += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_segment(&mut self) -> io::Result<()> {
+        if let Some(mut segment) = self.segment.take() {
+            segment.records.flush()?;
+        }
+
+        self.segment_count += 1;
+        let path = self.path.join(format!("segment_{:06}", self.segment_count));
+        fs::create_dir_all(&path)?;
+        self.write_config(&path)?;
+        let records = BufWriter::new(File::create(path.join(RECORDS_FILE))?);
+        self.segment = Some(Segment {
+            path,
+            records,
+            len: 0,
+        });
+
+        self.remove_old_segments()
+    }
+
+    fn write_config(&self, path: &Path) -> io::Result<()> {
+        if let Some(config) = &self.bot_config {
+            let file = File::create(path.join(CONFIG_FILE))?;
+            serde_json::to_writer_pretty(file, config)?;
+        }
+        Ok(())
+    }
+
+    fn remove_old_segments(&self) -> io::Result<()> {
+        let mut segments = fs::read_dir(&self.path)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        segments.sort();
+
+        let excess = segments
+            .len()
+            .saturating_sub(self.config.max_segments.max(1));
+        for segment in segments.into_iter().take(excess) {
+            fs::remove_dir_all(segment)?;
+        }
+        Ok(())
+    }
+}
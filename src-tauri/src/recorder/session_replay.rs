@@ -0,0 +1,256 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use image::imageops::FilterType;
+use libscreenshot::ImageBuffer;
+use slog::Logger;
+
+use super::{FrameRecord, CONFIG_FILE, RECORDS_FILE};
+use crate::{
+    behavior::BotRunner,
+    image_analyzer::{ImageAnalyzer, QueuedFrameSource},
+    ipc::{BotConfig, FrontendInfo},
+    movement::MovementAccessor,
+    platform::{InputAction, RecordingInputSink},
+    utils::VirtualClock,
+};
+
+/// A recorded iteration along with what the behavior did when it was replayed.
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    pub record: FrameRecord,
+    pub state: String,
+    pub inputs: Vec<InputAction>,
+    /// Pixels positions may be off by, frames saved downscaled lose some precision
+    pub tolerance: u32,
+}
+
+impl ReplayStep {
+    /// Whether the replayed behavior acted differently than during the recording.
+    pub fn diverged(&self) -> bool {
+        let recorded = self.record.inputs.iter().map(|input| &input.action);
+        if self.tolerance == 0 {
+            return self.state != self.record.state || recorded.ne(self.inputs.iter());
+        }
+
+        // States hold the bounds of their target, only which state it is can be compared
+        let name = |state: &str| state.split('(').next().unwrap_or_default().to_string();
+        name(&self.state) != name(&self.record.state)
+            || recorded.len() != self.inputs.len()
+            || recorded
+                .zip(&self.inputs)
+                .any(|(recorded, replayed)| !self.is_close(recorded, replayed))
+    }
+
+    fn is_close(&self, recorded: &InputAction, replayed: &InputAction) -> bool {
+        let near = |a: u32, b: u32| a.max(b) - a.min(b) <= self.tolerance;
+        match (recorded, replayed) {
+            (InputAction::MouseMove(a), InputAction::MouseMove(b))
+            | (InputAction::MouseClick(a), InputAction::MouseClick(b)) => {
+                near(a.x, b.x) && near(a.y, b.y)
+            }
+            (InputAction::DrawBounds(a, a_color), InputAction::DrawBounds(b, b_color)) => {
+                a_color == b_color
+                    && near(a.x, b.x)
+                    && near(a.y, b.y)
+                    && near(a.w, b.w)
+                    && near(a.h, b.h)
+            }
+            _ => recorded == replayed,
+        }
+    }
+}
+
+/// Feeds a recorded segment back through the `ImageAnalyzer` and the behaviors.
+#[derive(Debug, Clone)]
+pub struct SessionReplay {
+    path: PathBuf,
+    config: BotConfig,
+    records: Vec<FrameRecord>,
+}
+
+impl SessionReplay {
+    /// Open a segment directory written by a `SessionRecorder`.
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let config = serde_json::from_str(&fs::read_to_string(path.join(CONFIG_FILE))?)?;
+        let records = BufReader::new(File::open(path.join(RECORDS_FILE))?)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<io::Result<Vec<FrameRecord>>>()?;
+
+        Ok(Self {
+            path,
+            config,
+            records,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Replay every recorded iteration on a virtual clock following the recorded timestamps.
+    /// Behaviors start fresh, so the first steps of a segment may differ from the recording.
+    pub fn run(&self, logger: &Logger) -> Vec<ReplayStep> {
+        let clock = Arc::new(VirtualClock::new());
+        let input = Arc::new(RecordingInputSink::with_clock(clock.clone()));
+        let movement = MovementAccessor::new(input.clone(), clock.clone());
+        let frames = QueuedFrameSource::default();
+        let mut image_analyzer = ImageAnalyzer::new(frames.clone(), clock.clone());
+        image_analyzer.set_calibration(self.config.calibration_config().cloned());
+
+        let mut bot_runner = BotRunner::new(logger, &movement, &*input, &*clock);
+        bot_runner.update(&self.config);
+
+        let mut frontend_info = FrontendInfo::default();
+        let mut steps = vec![];
+        let mut tolerance = 0;
+        for record in &self.records {
+            clock.advance_to(record.elapsed);
+            if let Some((frame, scale)) = self.load_frame(record, logger) {
+                frames.push(frame);
+                tolerance = if scale > 1 { scale } else { 0 };
+            }
+
+            // Same iteration as the main loop
+            image_analyzer.capture_frame(logger, self.config.farming_config());
+            let state = match &record.mode {
+                Some(mode) => {
+                    bot_runner.run_iteration(
+                        mode,
+                        &self.config,
+                        &mut frontend_info,
+                        &mut image_analyzer,
+                    );
+                    bot_runner.describe(mode, &self.config, &image_analyzer).1
+                }
+                None => String::new(),
+            };
+
+            steps.push(ReplayStep {
+                record: record.clone(),
+                state,
+                inputs: input.take().into_iter().map(|input| input.action).collect(),
+                tolerance,
+            });
+        }
+        steps
+    }
+
+    /// Load the frame of a record, scaled back up to the size it was captured at.
+    /// Comes with the factor it was downscaled by.
+    fn load_frame(&self, record: &FrameRecord, logger: &Logger) -> Option<(ImageBuffer, u32)> {
+        let name = record.frame.as_ref()?;
+        let frame = match image::open(self.path.join(name)) {
+            Ok(frame) => frame.into_rgba8(),
+            Err(e) => {
+                slog::warn!(logger, "Failed to load recorded frame"; "frame" => name, "error" => e.to_string());
+                return None;
+            }
+        };
+
+        let (width, height) = record.frame_size;
+        if frame.dimensions() != (width, height) && width > 0 && height > 0 {
+            let scale = width / frame.width().max(1);
+            Some((
+                image::imageops::resize(&frame, width, height, FilterType::Nearest),
+                scale,
+            ))
+        } else {
+            Some((frame, 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use serde_json::json;
+
+    use super::SessionReplay;
+    use crate::{
+        behavior::{
+            simulation::{farming_config, Scene, SimulationEnv},
+            BotRunner,
+        },
+        data::MobType,
+        image_analyzer::{ImageAnalyzer, QueuedFrameSource},
+        ipc::FrontendInfo,
+        recorder::{RecorderConfig, SessionRecorder},
+    };
+
+    #[test]
+    fn test_record_and_replay() {
+        let dir = std::env::temp_dir().join(format!(
+            "neuz_test_record_and_replay_{}",
+            std::process::id()
+        ));
+        drop(fs::remove_dir_all(&dir));
+
+        let env = SimulationEnv::new();
+        let config = farming_config(json!({}));
+        let mode = config.mode().unwrap();
+        let mut recorder =
+            SessionRecorder::start(RecorderConfig::new(&dir), env.clock.clone(), &env.logger)
+                .unwrap();
+        recorder.set_config(&config);
+
+        // Record a full kill cycle the way the main loop does
+        let frames = QueuedFrameSource::default();
+        let mut analyzer = ImageAnalyzer::new(frames.clone(), env.clock.clone());
+        let mut bot_runner = BotRunner::new(&env.logger, &env.movement, &*env.input, &*env.clock);
+        bot_runner.update(&config);
+        let mut frontend_info = FrontendInfo::default();
+        let mob = Scene::default().with_mob(MobType::Passive, 380, 250);
+        let scenes = [
+            mob.clone(),
+            mob.clone(),
+            mob.clone().with_target(100, 100),
+            mob.clone().with_target(40, 100),
+            Scene::default(),
+            Scene::default(),
+        ];
+        for scene in &scenes {
+            env.clock.advance(Duration::from_millis(100));
+            frames.push(scene.render());
+            analyzer.capture_frame(&env.logger, config.farming_config());
+            bot_runner.run_iteration(&mode, &config, &mut frontend_info, &mut analyzer);
+            let (targets, state) = bot_runner.describe(&mode, &config, &analyzer);
+            recorder.record(
+                &analyzer,
+                Some(mode.clone()),
+                targets,
+                state,
+                env.input.take(),
+            );
+        }
+        drop(recorder);
+
+        let session = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let replay = SessionReplay::open(session.join("segment_000001")).unwrap();
+        assert_eq!(replay.len(), scenes.len());
+
+        // Frames are saved at half their size
+        let steps = replay.run(&env.logger);
+        assert_eq!(steps[0].tolerance, 2);
+        assert_eq!(steps[0].record.targets.len(), 1);
+        assert_eq!(steps[0].record.stats.hp, 100);
+        assert!(steps.iter().all(|step| !step.diverged()), "{:#?}", steps);
+        assert!(steps[3].state.starts_with("Attacking"), "{:#?}", steps);
+
+        drop(fs::remove_dir_all(&dir));
+    }
+}
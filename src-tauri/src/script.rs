@@ -0,0 +1,28 @@
+//! Small scripting language for custom routines.
+//!
+//! Scripts have one statement per line, blocks are closed with `end`:
+//!
+//! ```text
+//! # Attack whatever is around, eat when low
+//! loop
+//!     if hp < 50
+//!         use Food
+//!     end
+//!     if mobs > 0 and not target
+//!         attack
+//!         wait until target_hp == 0 timeout 10000
+//!     else
+//!         rotate random 200..400
+//!         move forward 1000
+//!     end
+//! end
+//! ```
+
+mod parser;
+mod program;
+mod runner;
+
+pub use self::{
+    program::{Script, ScriptAction, ScriptValues},
+    runner::ScriptRunner,
+};
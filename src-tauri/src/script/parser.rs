@@ -0,0 +1,493 @@
+use std::time::Duration;
+
+use super::program::{
+    Comparison, Condition, Instruction, Operand, Script, ScriptAction, ScriptError, ScriptValue,
+};
+use crate::{
+    ipc::SlotType,
+    movement::{ActionDuration, MovementDirection, RotationDirection},
+};
+
+/// Blocks waiting for their `end`, with the position of the jumps to patch.
+#[derive(Debug, Clone, Copy)]
+enum Block {
+    If { check: usize },
+    Else { skip: usize },
+    Loop { start: usize },
+    While { start: usize, check: usize },
+    Repeat { start: usize },
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    script: Script,
+    blocks: Vec<(Block, usize)>,
+    line: usize,
+}
+
+impl Script {
+    /// Parse a script, one statement per line.
+    pub fn parse(source: &str) -> Result<Self, ScriptError> {
+        let mut parser = Parser::default();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            parser.line = index + 1;
+            parser.statement(line)?;
+        }
+
+        if let Some((_, line)) = parser.blocks.last() {
+            return Err(ScriptError {
+                line: *line,
+                message: "block is never closed with `end`".to_string(),
+            });
+        }
+        Ok(parser.script)
+    }
+}
+
+impl Parser {
+    fn error(&self, message: impl Into<String>) -> ScriptError {
+        ScriptError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn push(&mut self, instruction: Instruction) -> usize {
+        self.script.instructions.push(instruction);
+        self.script.lines.push(self.line);
+        self.script.instructions.len() - 1
+    }
+
+    /// Point the jump at `position` to `target`.
+    fn patch(&mut self, position: usize, target: usize) {
+        if let Instruction::JumpUnless(_, jump)
+        | Instruction::Jump(jump)
+        | Instruction::Countdown(_, jump) = &mut self.script.instructions[position]
+        {
+            *jump = target;
+        }
+    }
+
+    fn statement(&mut self, line: &str) -> Result<(), ScriptError> {
+        let (keyword, rest) = line
+            .split_once(char::is_whitespace)
+            .map(|(keyword, rest)| (keyword, rest.trim()))
+            .unwrap_or((line, ""));
+        let arguments = rest.split_whitespace().collect::<Vec<_>>();
+        let expect_arguments = |count: usize| {
+            if arguments.len() == count {
+                Ok(())
+            } else {
+                Err(self.error(format!("`{}` takes {} argument(s)", keyword, count)))
+            }
+        };
+
+        let instruction = match keyword {
+            "jump" => {
+                expect_arguments(0)?;
+                Instruction::Act(ScriptAction::Jump)
+            }
+            "move" => {
+                expect_arguments(2)?;
+                let direction = match arguments[0] {
+                    "forward" => MovementDirection::Forward,
+                    "backward" => MovementDirection::Backward,
+                    "random" => MovementDirection::Random,
+                    other => return Err(self.error(format!("unknown direction `{}`", other))),
+                };
+                Instruction::Act(ScriptAction::Move(direction, self.duration(arguments[1])?))
+            }
+            "rotate" => {
+                expect_arguments(2)?;
+                let direction = match arguments[0] {
+                    "left" => RotationDirection::Left,
+                    "right" => RotationDirection::Right,
+                    "random" => RotationDirection::Random,
+                    other => return Err(self.error(format!("unknown direction `{}`", other))),
+                };
+                Instruction::Act(ScriptAction::Rotate(
+                    direction,
+                    self.duration(arguments[1])?,
+                ))
+            }
+            "press" => {
+                expect_arguments(1)?;
+                Instruction::Act(ScriptAction::PressKey(arguments[0].to_string()))
+            }
+            "hold" => {
+                let duration = match arguments.len() {
+                    1 => None,
+                    2 => Some(self.duration(arguments[1])?),
+                    _ => return Err(self.error("`hold` takes a key and a duration")),
+                };
+                Instruction::Act(ScriptAction::HoldKey(arguments[0].to_string(), duration))
+            }
+            "release" => {
+                expect_arguments(1)?;
+                Instruction::Act(ScriptAction::ReleaseKey(arguments[0].to_string()))
+            }
+            "play" => {
+                expect_arguments(1)?;
+                Instruction::Act(ScriptAction::PlaySequence(arguments[0].to_string()))
+            }
+            "type" | "log" => {
+                if rest.is_empty() {
+                    return Err(self.error(format!("`{}` needs a text", keyword)));
+                }
+                let text = rest.trim_matches('"').to_string();
+                Instruction::Act(if keyword == "type" {
+                    ScriptAction::Type(text)
+                } else {
+                    ScriptAction::Log(text)
+                })
+            }
+            "wait" => match rest.strip_prefix("until ") {
+                Some(condition) => {
+                    let (condition, timeout) = match condition.rsplit_once(" timeout ") {
+                        Some((condition, timeout)) => (
+                            condition,
+                            Some(Duration::from_millis(self.number(timeout)?)),
+                        ),
+                        None => (condition, None),
+                    };
+                    Instruction::WaitUntil(self.condition(condition)?, timeout)
+                }
+                None => {
+                    expect_arguments(1)?;
+                    Instruction::Act(ScriptAction::Wait(self.duration(arguments[0])?))
+                }
+            },
+            "slot" => {
+                expect_arguments(2)?;
+                let (bar, slot) = (self.number(arguments[0])?, self.number(arguments[1])?);
+                if !(1..=9).contains(&bar) || slot > 9 {
+                    return Err(self.error("slot bars go from 1 to 9 and slots from 0 to 9"));
+                }
+                Instruction::Act(ScriptAction::Slot(bar as usize - 1, slot as usize))
+            }
+            "use" => {
+                expect_arguments(1)?;
+                let slot_type = serde_json::from_value::<SlotType>(serde_json::json!(arguments[0]))
+                    .map_err(|_| self.error(format!("unknown slot type `{}`", arguments[0])))?;
+                Instruction::Act(ScriptAction::UseSlot(slot_type))
+            }
+            "attack" => {
+                expect_arguments(0)?;
+                Instruction::Act(ScriptAction::Attack)
+            }
+            "stop" => {
+                expect_arguments(0)?;
+                Instruction::Stop
+            }
+            "if" => {
+                let check = self.push(Instruction::JumpUnless(self.condition(rest)?, 0));
+                self.blocks.push((Block::If { check }, self.line));
+                return Ok(());
+            }
+            "else" => {
+                expect_arguments(0)?;
+                let (check, line) = match self.blocks.pop() {
+                    Some((Block::If { check }, line)) => (check, line),
+                    _ => return Err(self.error("`else` without `if`")),
+                };
+                let skip = self.push(Instruction::Jump(0));
+                self.patch(check, skip + 1);
+                self.blocks.push((Block::Else { skip }, line));
+                return Ok(());
+            }
+            "loop" => {
+                expect_arguments(0)?;
+                let start = self.script.instructions.len();
+                self.blocks.push((Block::Loop { start }, self.line));
+                return Ok(());
+            }
+            "while" => {
+                let start = self.script.instructions.len();
+                let check = self.push(Instruction::JumpUnless(self.condition(rest)?, 0));
+                self.blocks.push((Block::While { start, check }, self.line));
+                return Ok(());
+            }
+            "repeat" => {
+                expect_arguments(1)?;
+                let times = self.number(arguments[0])? as u32;
+                let counter = self.script.counters;
+                self.script.counters += 1;
+                self.push(Instruction::SetCounter(counter, times));
+                let start = self.push(Instruction::Countdown(counter, 0));
+                self.blocks.push((Block::Repeat { start }, self.line));
+                return Ok(());
+            }
+            "end" => {
+                expect_arguments(0)?;
+                let (block, _) = self
+                    .blocks
+                    .pop()
+                    .ok_or_else(|| self.error("`end` without a block"))?;
+                match block {
+                    Block::If { check: jump } | Block::Else { skip: jump } => {
+                        self.patch(jump, self.script.instructions.len());
+                    }
+                    Block::Loop { start } => {
+                        self.push(Instruction::Jump(start));
+                    }
+                    Block::While { start, check } => {
+                        self.push(Instruction::Jump(start));
+                        self.patch(check, self.script.instructions.len());
+                    }
+                    Block::Repeat { start } => {
+                        self.push(Instruction::Jump(start));
+                        self.patch(start, self.script.instructions.len());
+                    }
+                }
+                return Ok(());
+            }
+            other => return Err(self.error(format!("unknown statement `{}`", other))),
+        };
+        self.push(instruction);
+        Ok(())
+    }
+
+    fn number(&self, text: &str) -> Result<u64, ScriptError> {
+        text.trim()
+            .parse()
+            .map_err(|_| self.error(format!("`{}` is not a number", text.trim())))
+    }
+
+    /// A duration in milliseconds, `500` or a random one like `200..400`.
+    fn duration(&self, text: &str) -> Result<ActionDuration, ScriptError> {
+        match text.split_once("..") {
+            Some((min, max)) => {
+                let (min, max) = (self.number(min)?, self.number(max)?);
+                if min >= max {
+                    return Err(self.error(format!("`{}` is an empty range", text)));
+                }
+                Ok(ActionDuration::Random(min..max))
+            }
+            None => Ok(ActionDuration::Fixed(self.number(text)?)),
+        }
+    }
+
+    fn condition(&self, text: &str) -> Result<Condition, ScriptError> {
+        let tokens = tokenize(text).map_err(|message| self.error(message))?;
+        if tokens.is_empty() {
+            return Err(self.error("missing condition"));
+        }
+        let mut parser = ConditionParser {
+            tokens,
+            position: 0,
+        };
+        let condition = parser.or().map_err(|message| self.error(message))?;
+        match parser.next() {
+            Some(token) => Err(self.error(format!("unexpected `{}`", token))),
+            None => Ok(condition),
+        }
+    }
+}
+
+/// Split a condition into names, numbers, operators and parentheses.
+fn tokenize(text: &str) -> Result<Vec<&str>, String> {
+    let mut tokens = vec![];
+    let mut rest = text.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let length = if first == '(' || first == ')' {
+            1
+        } else if ["<=", ">=", "==", "!="]
+            .iter()
+            .any(|op| rest.starts_with(op))
+        {
+            2
+        } else if first == '<' || first == '>' {
+            1
+        } else {
+            rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len())
+        };
+        if length == 0 {
+            return Err(format!("unexpected `{}`", first));
+        }
+        let (token, tail) = rest.split_at(length);
+        tokens.push(token);
+        rest = tail.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// `or` binds looser than `and`, which binds looser than `not`.
+struct ConditionParser<'t> {
+    tokens: Vec<&'t str>,
+    position: usize,
+}
+
+impl<'t> ConditionParser<'t> {
+    fn peek(&self) -> Option<&'t str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<&'t str> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.peek() == Some("or") {
+            self.next();
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.unary()?;
+        while self.peek() == Some("and") {
+            self.next();
+            condition = Condition::And(Box::new(condition), Box::new(self.unary()?));
+        }
+        Ok(condition)
+    }
+
+    fn unary(&mut self) -> Result<Condition, String> {
+        match self.peek() {
+            Some("not") => {
+                self.next();
+                Ok(Condition::Not(Box::new(self.unary()?)))
+            }
+            Some("(") => {
+                self.next();
+                let condition = self.or()?;
+                match self.next() {
+                    Some(")") => Ok(condition),
+                    _ => Err("missing `)`".to_string()),
+                }
+            }
+            _ => {
+                let left = self.operand()?;
+                match self.peek().and_then(Comparison::from_symbol) {
+                    Some(comparison) => {
+                        self.next();
+                        Ok(Condition::Compare(left, comparison, self.operand()?))
+                    }
+                    // A lone value is true when it isn't 0
+                    None => Ok(Condition::Compare(
+                        left,
+                        Comparison::NotEqual,
+                        Operand::Number(0),
+                    )),
+                }
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        let token = self.next().ok_or("incomplete condition")?;
+        match token {
+            "true" => Ok(Operand::Number(1)),
+            "false" => Ok(Operand::Number(0)),
+            _ => token
+                .parse()
+                .map(Operand::Number)
+                .or_else(|_| ScriptValue::from_name(token).map(Operand::Value).ok_or(()))
+                .map_err(|_| format!("unknown value `{}`", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        ipc::SlotType,
+        movement::ActionDuration,
+        script::program::{
+            Comparison, Condition, Instruction, Operand, Script, ScriptAction, ScriptValue,
+            ScriptValues,
+        },
+    };
+
+    #[test]
+    fn test_parse_script() {
+        let script = Script::parse(
+            "# Heal up, then attack three times
+            if hp < 50 and not (target or mobs == 0)
+                use Food
+            else
+                slot 2 3
+            end
+            repeat 3
+                attack
+                wait until target_hp == 0 timeout 5000
+            end
+            wait 200..400",
+        )
+        .unwrap();
+
+        let condition = match &script.instructions[0] {
+            Instruction::JumpUnless(condition, 3) => condition,
+            instruction => panic!("unexpected {:?}", instruction),
+        };
+        let values = |hp, target, mobs| ScriptValues {
+            hp,
+            target,
+            mobs,
+            ..Default::default()
+        };
+        assert!(condition.eval(&values(40, false, 2)));
+        assert!(!condition.eval(&values(40, true, 2)));
+        assert!(!condition.eval(&values(40, false, 0)));
+        assert!(!condition.eval(&values(60, false, 2)));
+
+        assert_eq!(
+            &script.instructions[1..],
+            &[
+                Instruction::Act(ScriptAction::UseSlot(SlotType::Food)),
+                Instruction::Jump(4),
+                Instruction::Act(ScriptAction::Slot(1, 3)),
+                Instruction::SetCounter(0, 3),
+                Instruction::Countdown(0, 9),
+                Instruction::Act(ScriptAction::Attack),
+                Instruction::WaitUntil(
+                    Condition::Compare(
+                        Operand::Value(ScriptValue::TargetHp),
+                        Comparison::Equal,
+                        Operand::Number(0)
+                    ),
+                    Some(Duration::from_millis(5000))
+                ),
+                Instruction::Jump(5),
+                Instruction::Act(ScriptAction::Wait(ActionDuration::Random(200..400))),
+            ]
+        );
+        assert_eq!(script.line(0), Some(2));
+        assert_eq!(script.line(9), Some(11));
+
+        let error = |source| Script::parse(source).unwrap_err().to_string();
+        assert_eq!(
+            error("loop\njump"),
+            "line 1: block is never closed with `end`"
+        );
+        assert_eq!(error("jump\nend"), "line 2: `end` without a block");
+        assert_eq!(error("move up 100"), "line 1: unknown direction `up`");
+        assert_eq!(
+            error("wait 400..200"),
+            "line 1: `400..200` is an empty range"
+        );
+        assert_eq!(error("if hp <"), "line 1: incomplete condition");
+        assert_eq!(error("while level > 5"), "line 1: unknown value `level`");
+        assert_eq!(error("use Potion"), "line 1: unknown slot type `Potion`");
+        assert_eq!(error("fly"), "line 1: unknown statement `fly`");
+        assert_eq!(error("play"), "line 1: `play` takes 1 argument(s)");
+
+        assert_eq!(
+            Script::parse("play loot_dance").unwrap().instructions,
+            vec![Instruction::Act(ScriptAction::PlaySequence(
+                "loot_dance".to_string()
+            ))]
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::{fmt, time::Duration};
+
+use crate::{
+    ipc::SlotType,
+    movement::{ActionDuration, Movement, MovementDirection, RotationDirection},
+};
+
+/// A game value scripts can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptValue {
+    Hp,
+    Mp,
+    Fp,
+    TargetHp,
+    TargetMp,
+    /// Number of mobs on screen
+    Mobs,
+    /// 1 if the target marker is shown
+    Target,
+}
+
+impl ScriptValue {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "hp" => Self::Hp,
+            "mp" => Self::Mp,
+            "fp" => Self::Fp,
+            "target_hp" => Self::TargetHp,
+            "target_mp" => Self::TargetMp,
+            "mobs" => Self::Mobs,
+            "target" => Self::Target,
+            _ => return None,
+        })
+    }
+}
+
+/// Values of the current frame, stats are in percent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptValues {
+    pub hp: u32,
+    pub mp: u32,
+    pub fp: u32,
+    pub target_hp: u32,
+    pub target_mp: u32,
+    pub mobs: u32,
+    pub target: bool,
+}
+
+impl ScriptValues {
+    pub fn get(&self, value: ScriptValue) -> i64 {
+        match value {
+            ScriptValue::Hp => self.hp as i64,
+            ScriptValue::Mp => self.mp as i64,
+            ScriptValue::Fp => self.fp as i64,
+            ScriptValue::TargetHp => self.target_hp as i64,
+            ScriptValue::TargetMp => self.target_mp as i64,
+            ScriptValue::Mobs => self.mobs as i64,
+            ScriptValue::Target => self.target as i64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Value(ScriptValue),
+    Number(i64),
+}
+
+impl Operand {
+    fn eval(&self, values: &ScriptValues) -> i64 {
+        match self {
+            Self::Value(value) => values.get(*value),
+            Self::Number(number) => *number,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "<" => Self::Less,
+            "<=" => Self::LessOrEqual,
+            ">" => Self::Greater,
+            ">=" => Self::GreaterOrEqual,
+            "==" => Self::Equal,
+            "!=" => Self::NotEqual,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Compare(Operand, Comparison, Operand),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+    pub fn eval(&self, values: &ScriptValues) -> bool {
+        match self {
+            Self::Compare(left, comparison, right) => {
+                let (left, right) = (left.eval(values), right.eval(values));
+                match comparison {
+                    Comparison::Less => left < right,
+                    Comparison::LessOrEqual => left <= right,
+                    Comparison::Greater => left > right,
+                    Comparison::GreaterOrEqual => left >= right,
+                    Comparison::Equal => left == right,
+                    Comparison::NotEqual => left != right,
+                }
+            }
+            Self::Not(condition) => !condition.eval(values),
+            Self::And(left, right) => left.eval(values) && right.eval(values),
+            Self::Or(left, right) => left.eval(values) || right.eval(values),
+        }
+    }
+}
+
+/// Something the behavior running the script has to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    Jump,
+    Move(MovementDirection, ActionDuration),
+    Rotate(RotationDirection, ActionDuration),
+    PressKey(String),
+    /// Hold a key for the duration, or until it is released without one
+    HoldKey(String, Option<ActionDuration>),
+    ReleaseKey(String),
+    Type(String),
+    Wait(ActionDuration),
+    /// Play a movement sequence of the profile
+    PlaySequence(String),
+    Slot(usize, usize),
+    /// Use the first slot of this type in the farming slot bars
+    UseSlot(SlotType),
+    /// Click the mob closest to the character
+    Attack,
+    Log(String),
+}
+
+impl ScriptAction {
+    /// The movement playing this action, if it is one.
+    pub fn movement(&self) -> Option<Movement<'_>> {
+        Some(match self {
+            Self::Jump => Movement::Jump,
+            Self::Move(direction, duration) => Movement::Move(*direction, duration.clone()),
+            Self::Rotate(direction, duration) => Movement::Rotate(*direction, duration.clone()),
+            Self::PressKey(key) => Movement::PressKey(key),
+            Self::HoldKey(key, Some(duration)) => Movement::HoldKeyFor(key, duration.clone()),
+            Self::HoldKey(key, None) => Movement::HoldKey(key),
+            Self::ReleaseKey(key) => Movement::ReleaseKey(key),
+            Self::Type(text) => Movement::Type(text.clone()),
+            Self::Wait(duration) => Movement::Wait(duration.clone()),
+            _ => return None,
+        })
+    }
+}
+
+/// Scripts are compiled to a flat list of instructions, blocks become jumps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Act(ScriptAction),
+    /// Wait for the condition over the next frames, at most for the timeout
+    WaitUntil(Condition, Option<Duration>),
+    JumpUnless(Condition, usize),
+    Jump(usize),
+    SetCounter(usize, u32),
+    /// Jump when the counter is 0, decrement it otherwise
+    Countdown(usize, usize),
+    Stop,
+}
+
+/// A parsed script.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Script {
+    pub(super) instructions: Vec<Instruction>,
+    /// Source line of each instruction
+    pub(super) lines: Vec<usize>,
+    pub(super) counters: usize,
+}
+
+impl Script {
+    /// Source line of an instruction, starting at 1.
+    pub fn line(&self, position: usize) -> Option<usize> {
+        self.lines.get(position).copied()
+    }
+}
+
+/// Why a script could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
@@ -0,0 +1,167 @@
+use std::time::Instant;
+
+use guard::guard;
+
+use super::program::{Instruction, Script, ScriptAction, ScriptValues};
+
+/// Instructions run per frame at most, so a script without waits can't freeze the bot.
+const MAX_STEP_INSTRUCTIONS: usize = 1000;
+
+/// Runs a script a frame at a time.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptRunner {
+    script: Script,
+    position: usize,
+    counters: Vec<u32>,
+    waiting_since: Option<Instant>,
+    stopped: bool,
+}
+
+impl ScriptRunner {
+    pub fn new(script: Script) -> Self {
+        Self {
+            counters: vec![0; script.counters],
+            script,
+            ..Default::default()
+        }
+    }
+
+    /// Start over from the first line.
+    pub fn restart(&mut self) {
+        self.position = 0;
+        self.counters.fill(0);
+        self.waiting_since = None;
+        self.stopped = false;
+    }
+
+    /// Whether the script ran into `stop`.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Source line of the next instruction.
+    pub fn line(&self) -> Option<usize> {
+        self.script.line(self.position)
+    }
+
+    /// Run until the script waits for the next frame and return what it did meanwhile.
+    /// Unmet `wait until` conditions and loops both wait, so conditions see new values.
+    pub fn step(&mut self, values: &ScriptValues, now: Instant) -> Vec<ScriptAction> {
+        let mut actions = vec![];
+        if self.stopped {
+            return actions;
+        }
+
+        for _ in 0..MAX_STEP_INSTRUCTIONS {
+            guard!(let Some(instruction) = self.script.instructions.get(self.position) else {
+                // Routines start over once they're done
+                self.restart();
+                break;
+            });
+            match instruction {
+                Instruction::Act(action) => {
+                    actions.push(action.clone());
+                    self.position += 1;
+                }
+                Instruction::WaitUntil(condition, timeout) => {
+                    let since = *self.waiting_since.get_or_insert(now);
+                    let timed_out = timeout.map_or(false, |timeout| {
+                        now.saturating_duration_since(since) >= timeout
+                    });
+                    if !condition.eval(values) && !timed_out {
+                        break;
+                    }
+                    self.waiting_since = None;
+                    self.position += 1;
+                }
+                Instruction::JumpUnless(condition, target) => {
+                    if condition.eval(values) {
+                        self.position += 1;
+                    } else {
+                        self.position = *target;
+                    }
+                }
+                Instruction::Jump(target) => {
+                    let backwards = *target <= self.position;
+                    self.position = *target;
+                    if backwards {
+                        break;
+                    }
+                }
+                Instruction::SetCounter(counter, times) => {
+                    self.counters[*counter] = *times;
+                    self.position += 1;
+                }
+                Instruction::Countdown(counter, target) => {
+                    if self.counters[*counter] == 0 {
+                        self.position = *target;
+                    } else {
+                        self.counters[*counter] -= 1;
+                        self.position += 1;
+                    }
+                }
+                Instruction::Stop => {
+                    self.stopped = true;
+                    break;
+                }
+            }
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ScriptRunner;
+    use crate::script::{Script, ScriptAction, ScriptValues};
+
+    #[test]
+    fn test_script_runner() {
+        let script = Script::parse(
+            "repeat 2
+                press Z
+                wait until mobs > 0 timeout 1000
+            end
+            while target
+                attack
+            end
+            stop",
+        )
+        .unwrap();
+        let mut runner = ScriptRunner::new(script);
+        let start = Instant::now();
+        let press = ScriptAction::PressKey("Z".to_string());
+        let values = |mobs, target| ScriptValues {
+            mobs,
+            target,
+            ..Default::default()
+        };
+
+        // Waits for mobs to show up
+        assert_eq!(runner.step(&values(0, false), start), vec![press.clone()]);
+        assert_eq!(runner.step(&values(0, false), start), vec![]);
+        assert_eq!(runner.line(), Some(3));
+        // Loops wait for the next frame
+        assert_eq!(runner.step(&values(1, false), start), vec![]);
+        assert_eq!(runner.step(&values(0, false), start), vec![press]);
+        // The wait timed out
+        let later = start + Duration::from_secs(1);
+        assert_eq!(runner.step(&values(0, true), later), vec![]);
+        assert_eq!(
+            runner.step(&values(0, true), later),
+            vec![ScriptAction::Attack]
+        );
+        assert_eq!(
+            runner.step(&values(0, true), later),
+            vec![ScriptAction::Attack]
+        );
+        assert!(!runner.is_stopped());
+        assert_eq!(runner.step(&values(0, false), later), vec![]);
+        assert!(runner.is_stopped());
+
+        runner.restart();
+        assert_eq!(runner.line(), Some(1));
+    }
+}
@@ -1,4 +1,9 @@
+mod clock;
 mod datetime;
 mod timer;
 
-pub use self::{datetime::DateTime, timer::Timer};
+pub use self::{
+    clock::{Clock, SystemClock, VirtualClock},
+    datetime::DateTime,
+    timer::Timer,
+};
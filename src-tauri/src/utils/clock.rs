@@ -0,0 +1,78 @@
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+/// Source of time for everything that measures cooldowns or waits.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Get the current instant.
+    fn now(&self) -> Instant;
+
+    /// Block for the given duration.
+    fn sleep(&self, duration: Duration);
+
+    /// Get the time elapsed since the given instant.
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+}
+
+/// Wall-clock time, sleeping with `std::thread::sleep`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Virtual time that only moves forward when slept on or advanced manually.
+#[derive(Debug)]
+pub struct VirtualClock {
+    start: Instant,
+    offset: Mutex<Duration>,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move time forward by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.lock() += duration;
+    }
+
+    /// Move time forward until `elapsed` has passed since the clock was created.
+    /// Does nothing if that point in time is already behind.
+    pub fn advance_to(&self, elapsed: Duration) {
+        let mut offset = self.offset.lock();
+        *offset = (*offset).max(elapsed);
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.offset.lock()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
@@ -9,13 +9,15 @@ import Tab from "./components/Tab"
 import ImageFarm from './assets/btn_leveling.png'
 import ImageSupport from './assets/btn_full_support.png'
 import ImageShout from './assets/btn_shout.png'
+import ImageScript from './assets/msc.png'
 
-import { BotConfigModel, ModeModel } from './models/BotConfig'
+import { BotConfigModel, CalibrationConfigModel, ModeModel } from './models/BotConfig'
 import FarmingConfig from "./components/behaviors/FarmingConfig"
 import SupportConfig from "./components/behaviors/SupportConfig"
 import ShoutConfig from "./components/behaviors/ShoutConfig"
+import ScriptConfig from "./components/behaviors/ScriptConfig"
 import Footer from "./components/Footer"
-import { FrontendInfoModel } from "./models/FrontendInfo"
+import { alertMessages, FrontendInfoModel } from "./models/FrontendInfo"
 import { invoke } from "@tauri-apps/api"
 import { useStopWatch } from "./components/utils/StopWatch"
 
@@ -77,6 +79,10 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
         emit('bot_config_c2s', newConfig)
     }
 
+    const calibrate = () => invoke<CalibrationConfigModel | null>("calibrate").then((calibration) => {
+        if (calibration) makeConfigUpdater('calibration_config')(calibration)
+    })
+
     const [lightMode, setLightMode] = useState(false)
     const minSizeFarm = [250, 200]
     const minSize = [250, 160]
@@ -96,20 +102,25 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
                             <Tab mode="Farming" image={ImageFarm} />
                             <Tab mode="Support" image={ImageSupport} />
                             <Tab mode="AutoShout" image={ImageShout} />
+                            <Tab mode="Scripted" image={ImageScript} />
                         </TabControl>
                         <div className="config-container">
                             {config?.mode === 'Farming' && (<FarmingConfig botState={farmingState} botStopWatch={farmStopWatch.watch} info={info} config={config.farming_config} onChange={makeConfigUpdater('farming_config')} />)}
                             {config?.mode === 'Support' && (<SupportConfig botState={supportState} botStopWatch={suppStopWatch.watch} info={info} config={config.support_config} onChange={makeConfigUpdater('support_config')} />)}
                             {config?.mode === 'AutoShout' && (<ShoutConfig config={config.shout_config} onChange={makeConfigUpdater('shout_config')} />)}
+                            {config?.mode === 'Scripted' && (<ScriptConfig config={config.script_config ?? {}} onChange={makeConfigUpdater('script_config')} />)}
                         </div>
                     </>
                 )}
 
 
+                {info?.alert && <div className="alert">{alertMessages[info.alert]}</div>}
+
                 <div className="footer">
                     <div className="sm footerCombinedBtn">
                         {!isNil(config?.mode) && <div className="btn sm" onClick={toogleLightMode}>Compact</div>}
                         {!isNil(config?.mode) && <div className="btn sm" onClick={() => {invoke("focus_client")}}>Focus</div>}
+                        {!isNil(config?.mode) && <div className="btn sm" onClick={calibrate}>Calibrate</div>}
                     </div>
                     {!isNil(config?.mode) && <div className="btn" onClick={setRunningToggle}>{config?.is_running ? 'Disengage' : 'Engage'}</div>}
                 </div>
@@ -118,7 +129,7 @@ const MissionControl = ({ className, lastVersion, currentVersion }: Props) => {
 
             {lightMode &&
                 <div className="lightmode">
-                       {config?.mode !== "AutoShout" && <div className={"stateDisplay"}>
+                       {(config?.mode === "Farming" || config?.mode === "Support") && <div className={"stateDisplay"}>
                             State: {config?.mode === "Farming"? farmingState : supportState}
                         </div>}
                     <div className="btn sm" id="back" onClick={toogleLightMode}>{"<-"}</div>
@@ -164,6 +175,13 @@ export default styled(MissionControl)`
         color: white;
     }
 
+    & .alert {
+        background: hsla(0, 70%, 35%, .85);
+        border-radius: 0.25rem;
+        padding: 5px 10px;
+        color: white;
+    }
+
     & .vstack {
         display: flex;
         flex-direction: column;
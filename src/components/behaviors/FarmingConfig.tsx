@@ -5,10 +5,11 @@ import ConfigLabel from '../config/ConfigLabel'
 import ConfigTable from '../config/ConfigTable'
 import ConfigTableRow from '../config/ConfigTableRow'
 import NumericInput from '../config/NumericInput'
+import TextInput from '../config/TextInput'
 import ColorSelector from '../config/ColorSelector'
 
 import SlotBar from '../SlotBar'
-import { createSlotBars, FarmingConfigModel } from '../../models/BotConfig'
+import { createSlotBars, deathPolicies, FarmingConfigModel, giantPolicies } from '../../models/BotConfig'
 import { useEffect, useRef, useState } from 'react'
 import { FrontendInfoModel } from '../../models/FrontendInfo'
 import Modal from '../Modal'
@@ -17,6 +18,8 @@ import YesNoModal from '../YesNoModal'
 import { MsFormat, StopWatchValues } from '../utils/StopWatch'
 import { DefaultValuesChecker } from '../utils/DefaultValuesChecker'
 import TimeInput from '../config/TimeInput'
+import StringList from '../config/StringList'
+import Select from 'react-select'
 
 type Props = {
     className?: string,
@@ -36,6 +39,7 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
     const mobsColorsDebugModal = useModal(mobsNameDebugModal)
     const resetSlotYesNo = useModal(debugModal)
     const obstacleAvoidanceDebugModal = useModal(debugModal)
+    const patrolModal = useModal(debugModal)
 
     const selectedMobType = useRef(0)
 
@@ -48,6 +52,17 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
         'obstacle_avoidance_max_try': 3,
         'min_mobs_name_width': 15,
         'max_mobs_name_width': 180,
+        'mobs_name_gap_x': 10,
+        'mobs_name_gap_y': 3,
+        'giant_policy': 'Attack',
+        'giant_mobs_names': ['Giant'],
+        'giant_min_name_width': 0,
+        'distance_weight': 1,
+        'aggressive_weight': 2,
+        'density_weight': 0.2,
+        'low_resources_weight': 1,
+        'avoided_weight': 0.5,
+        'density_radius': 150,
         'circle_pattern_rotation_duration': 30,
         'min_hp_attack': 30,
         'prevent_already_attacked': true,
@@ -56,6 +71,9 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
 
     DefaultValuesChecker(config, defaultValues, onChange)
 
+    const giantPolicyOptions = giantPolicies.map(policy => ({ value: policy, label: policy.replace(/([a-z])([A-Z])/g, '$1 $2') }))
+    const deathPolicyOptions = deathPolicies.map(policy => ({ value: policy, label: policy.replace(/([a-z])([A-Z])/g, '$1 $2') }))
+
     const colorsRefResetter = [
         () => onChange({...config, ...{passive_mobs_colors: defaultValues['passive_mobs_colors'], passive_tolerence: defaultValues['passive_tolerence']} }),
         () => onChange({...config, ...{aggressive_mobs_colors: defaultValues['aggressive_mobs_colors'], aggressive_tolerence: defaultValues['aggressive_tolerence']} })
@@ -69,7 +87,8 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
     }, [debugMode])
     // StopWatchs
     const searchMobStopWatch = MsFormat(info?.last_search_duration ?? 0),
-    fightStopWatch = MsFormat(info?.last_search_duration ?? 0)
+    fightStopWatch = MsFormat(info?.last_search_duration ?? 0),
+    timeToLevel = MsFormat(info?.exp_stats?.time_to_level ?? 0)
 
     const globalKPM = ((info?.enemy_kill_count?? 0) / Math.round(Number(botStopWatch?.timer ?? 0) / 60000)).toFixed(2)
     const globalKPH = (Number(globalKPM) * 60).toFixed(2)
@@ -91,6 +110,10 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         label={<ConfigLabel name="Avoidances" helpText="" />}
                         item={<button onClick={obstacleAvoidanceDebugModal.open}>⚙️</button>}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Patrol" helpText="" />}
+                        item={<button onClick={patrolModal.open}>⚙️</button>}
+                    />
                     <ConfigTableRow
                         label={<ConfigLabel name="On death event" helpText="" />}
                         item={<button onClick={onDeathModal.open}>⚙️</button>}
@@ -128,6 +151,30 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                     />
                 </ConfigTable>
             }/>
+            <Modal isShowing={patrolModal.isShown} hide={patrolModal.close} title={<h4>Patrol</h4>} body={
+                <ConfigTable>
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Route" helpText="One leg per line, walked when no mob is found. Either coordinates to walk to (x, z) or the name of a movement sequence of the profile" />}
+                        item={<StringList messages={config.patrol_route ?? []} onChange={value => onChange({...config, patrol_route: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Anchor" helpText="Center of the farming zone (x, z). Where the bot started if empty" />}
+                        item={<TextInput value={config.patrol_anchor} onChange={value => onChange({...config, patrol_anchor: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Radius" helpText="The bot walks back to the anchor when it gets further than this. Value of 0 disables it" />}
+                        item={<NumericInput value={config.patrol_radius} onChange={value => onChange({...config, patrol_radius: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Minimap scale" helpText="World units covered by a minimap pixel, depends on how far the minimap is zoomed. 2 if empty" />}
+                        item={<NumericInput min={0} value={config.minimap_units_per_pixel} onChange={value => onChange({...config, minimap_units_per_pixel: value})} />}
+                    />
+                </ConfigTable>
+            }/>
             <Modal isShowing={obstacleAvoidanceDebugModal.isShown} hide={obstacleAvoidanceDebugModal.close} title={<h4>Avoidances</h4>} body={
                 <ConfigTable>
                     <ConfigTableRow
@@ -145,6 +192,11 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         label={<ConfigLabel name="Obstacle avoidance max try" helpText="After this number of try it'll abort attack and search for another target" />}
                         item={<NumericInput unit='#' value={config.obstacle_avoidance_max_try} onChange={value => onChange({...config, obstacle_avoidance_max_try: value})} />}
                     />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Obstacle avoidance sequence" helpText="Name of a movement sequence of the profile's sequences folder to play instead of the default unstuck moves" />}
+                        item={<TextInput value={config.obstacle_avoidance_sequence} onChange={value => onChange({...config, obstacle_avoidance_sequence: value})} />}
+                    />
                 </ConfigTable>
             }/>
             <Modal isShowing={mobsNameDebugModal.isShown} hide={mobsNameDebugModal.close} title={<h4>Mobs detection</h4>} body={
@@ -177,12 +229,92 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                             label={<ConfigLabel name="Max mobs name width" helpText="" />}
                             item={<NumericInput unit='px' value={config.max_mobs_name_width} onChange={value => onChange({...config, max_mobs_name_width: value})} />}
                         />
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Mobs name horizontal gap" helpText="Max horizontal distance between pixels of the same name. Lower it if neighbouring names are merged" />}
+                            item={<NumericInput unit='px' value={config.mobs_name_gap_x} onChange={value => onChange({...config, mobs_name_gap_x: value})} />}
+                        />
+                        <ConfigTableRow
+                            layout="v"
+                            label={<ConfigLabel name="Mobs name vertical gap" helpText="Max vertical distance between pixels of the same name" />}
+                            item={<NumericInput unit='px' value={config.mobs_name_gap_y} onChange={value => onChange({...config, mobs_name_gap_y: value})} />}
+                        />
                     </> }
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Allowed mobs" helpText="One name per line. When set, only mobs whose name contains one of them are attacked" />}
+                        item={<StringList messages={config.allowed_mobs_names ?? []} onChange={value => onChange({...config, allowed_mobs_names: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Forbidden mobs" helpText="One name per line. Mobs whose name contains one of them are skipped" />}
+                        item={<StringList messages={config.forbidden_mobs_names ?? []} onChange={value => onChange({...config, forbidden_mobs_names: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Min mobs level" helpText="Mobs below this level are skipped. Value of 0 disables it" />}
+                        item={<NumericInput value={config.min_mobs_level ?? 0} onChange={value => onChange({...config, min_mobs_level: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Max mobs level" helpText="Mobs above this level are skipped. Value of 0 disables it" />}
+                        item={<NumericInput value={config.max_mobs_level ?? 0} onChange={value => onChange({...config, max_mobs_level: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Giant policy" helpText="What to do with giants: attack them, avoid them, attack only at full HP or flee when they hit you" />}
+                        item={<div style={{width:'100%', color: 'black'}}><Select options={giantPolicyOptions} onChange={value => onChange({...config, giant_policy: value?.value ?? 'Attack'})} value={giantPolicyOptions.find(x => x.value == (config.giant_policy ?? 'Attack'))}/></div>}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Giant mobs" helpText="One name per line. Mobs whose name contains one of them are treated as giants" />}
+                        item={<StringList messages={config.giant_mobs_names ?? []} onChange={value => onChange({...config, giant_mobs_names: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Giant min name width" helpText="Mobs with a wider name are treated as giants, their name is then not read. Value of 0 disables it" />}
+                        item={<NumericInput unit='px' value={config.giant_min_name_width ?? 0} onChange={value => onChange({...config, giant_min_name_width: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Distance weight" helpText="Penalty for far away mobs. Default : 1" />}
+                        item={<NumericInput value={config.distance_weight} onChange={value => onChange({...config, distance_weight: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Aggressive weight" helpText="Bonus for aggressive mobs, they are attacked first. Default : 2" />}
+                        item={<NumericInput value={config.aggressive_weight} onChange={value => onChange({...config, aggressive_weight: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Density weight" helpText="Penalty for each other mob around a target, to avoid pulling several at once. Default : 0.2" />}
+                        item={<NumericInput value={config.density_weight} onChange={value => onChange({...config, density_weight: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Low HP/MP weight" helpText="Extra penalty for each other mob around a target, growing as HP or MP drop. Default : 1" />}
+                        item={<NumericInput value={config.low_resources_weight} onChange={value => onChange({...config, low_resources_weight: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Avoided spot weight" helpText="Penalty for mobs next to a spot the bot recently gave up on. Default : 0.5" />}
+                        item={<NumericInput value={config.avoided_weight} onChange={value => onChange({...config, avoided_weight: value})} />}
+                    />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Density radius" helpText="Mobs closer than this to a target count as around it. Default : 150" />}
+                        item={<NumericInput unit='px' value={config.density_radius} onChange={value => onChange({...config, density_radius: value})} />}
+                    />
                     <ConfigTableRow
                         layout="v"
                         label={<ConfigLabel name="Circle pattern duration" helpText="The bot will try to move in a circle pattern to find targets. Value of 0 will stay in place. Lower the value to increase circle size. Default : 30" />}
                         item={<NumericInput value={config.circle_pattern_rotation_duration} onChange={value => onChange?.({ ...config, circle_pattern_rotation_duration: value })} />}
                     />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Circle pattern sequence" helpText="Name of a movement sequence of the profile's sequences folder to play instead of the circle pattern" />}
+                        item={<TextInput value={config.circle_pattern_sequence} onChange={value => onChange?.({ ...config, circle_pattern_sequence: value })} />}
+                    />
 
                     <ConfigTableRow
                         layout="v"
@@ -229,6 +361,10 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         label={<ConfigLabel name="Disconnect" helpText="Enable will automatically disconnect, otherwise we'll wait for revive" />}
                         item={<BooleanSlider value={config.on_death_disconnect ?? false} onChange={value => onChange?.({ ...config, on_death_disconnect: value })} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="After death" helpText="When not disconnecting: wait for someone to resurrect the character, revive at the lodestar, or stop and show an alert" />}
+                        item={<div style={{width:'100%', color: 'black'}}><Select options={deathPolicyOptions} onChange={value => onChange({...config, on_death: value?.value ?? 'ReviveAtLodestar'})} value={deathPolicyOptions.find(x => x.value == (config.on_death ?? 'ReviveAtLodestar'))}/></div>}
+                    />
                 </ConfigTable>
             }/>
             <Modal isShowing={statsModal.isShown} hide={statsModal.close}
@@ -253,6 +389,13 @@ const FarmingConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         <div>Global kills stats(approx): {globalKPM === "NaN" || globalKPM === "Infinity" ? 0 : globalKPM}/min
                         | {globalKPH === "NaN" || globalKPH === "Infinity" ? 0 : globalKPH}/hour</div>
                     </div>
+                    <div className="row">
+                        <div>EXP: {(info?.exp_stats?.current ?? 0).toFixed(2)}% | gained {(info?.exp_stats?.gained ?? 0).toFixed(2)}%
+                        | {(info?.exp_stats?.per_hour ?? 0).toFixed(2)}%/hour</div>
+                    </div>
+                    <div className="row">
+                        <div>Time to level(approx): {timeToLevel} | Level ups: {info?.exp_stats?.level_ups ?? 0}</div>
+                    </div>
                 </div>
             }/>
             {info && (
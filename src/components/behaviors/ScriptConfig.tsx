@@ -0,0 +1,32 @@
+import ConfigLabel from '../config/ConfigLabel'
+import ConfigPanel from '../config/ConfigPanel'
+
+import StringList from '../config/StringList'
+
+import { ScriptConfigModel } from '../../models/BotConfig'
+import ConfigTable from '../config/ConfigTable'
+import ConfigTableRow from '../config/ConfigTableRow'
+
+
+type Props = {
+    config: ScriptConfigModel,
+    onChange: (config: ScriptConfigModel) => void,
+}
+
+const ScriptConfig = ({ config, onChange }: Props) => {
+    return (
+        <>
+            <ConfigPanel>
+                <ConfigTable>
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="Script" helpText="One statement per line, see the README for the list of statements. Mobs and slots come from the farming settings." />}
+                        item={<StringList messages={config.script?.split('\n') ?? []} onChange={value => onChange?.({ ...config, script: value.join('\n') })} />}
+                    />
+                </ConfigTable>
+            </ConfigPanel>
+        </>
+    )
+}
+
+export default ScriptConfig;
@@ -1,4 +1,4 @@
-import { createSlotBars, SupportConfigModel } from "../../models/BotConfig"
+import { createSlotBars, deathPolicies, SupportConfigModel } from "../../models/BotConfig"
 import { FrontendInfoModel } from "../../models/FrontendInfo"
 
 import Modal from '../Modal'
@@ -16,6 +16,8 @@ import styled from "styled-components"
 import { DefaultValuesChecker } from "../utils/DefaultValuesChecker"
 import NumericInput from "../config/NumericInput"
 import TimeInput from "../config/TimeInput"
+import TextInput from "../config/TextInput"
+import Select from 'react-select'
 
 type Props = {
     className?: string,
@@ -40,6 +42,8 @@ const SupportConfig = ({ className, info, config, onChange, botStopWatch, botSta
 
     DefaultValuesChecker(config, defaultValues, onChange)
 
+    const deathPolicyOptions = deathPolicies.map(policy => ({ value: policy, label: policy.replace(/([a-z])([A-Z])/g, '$1 $2') }))
+
     return (
         <div className={className}>
             <SlotBar botMode="support" config={config} onChange={onChange} />
@@ -59,6 +63,10 @@ const SupportConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         label={<ConfigLabel name="Obstacle avoidance cooldown" helpText="Time before it tries to avoid obstacles, and start movement pattern" />}
                         item={<TimeInput value={config.obstacle_avoidance_cooldown} onChange={value => onChange?.({...config, obstacle_avoidance_cooldown: value})} />}
                     />
+                    <ConfigTableRow
+                        label={<ConfigLabel name="Obstacle avoidance sequence" helpText="Name of a movement sequence of the profile's sequences folder to play instead of the movement pattern" />}
+                        item={<TextInput value={config.obstacle_avoidance_sequence} onChange={value => onChange?.({...config, obstacle_avoidance_sequence: value})} />}
+                    />
                     <ConfigTableRow
                         label={<ConfigLabel name="Interval between buffs" helpText="" />}
                         item={<TimeInput value={config.interval_between_buffs} onChange={value => onChange({...config, interval_between_buffs: value})} />}
@@ -77,6 +85,11 @@ const SupportConfig = ({ className, info, config, onChange, botStopWatch, botSta
                         label={<ConfigLabel name="Disconnect" helpText="If enabled will automatically disconnect the dead character, otherwise we'll try to revive by pressing ENTER" />}
                         item={<BooleanSlider value={config.on_death_disconnect ?? false} onChange={value => onChange?.({ ...config, on_death_disconnect: value })} />}
                     />
+                    <ConfigTableRow
+                        layout="v"
+                        label={<ConfigLabel name="After death" helpText="When not disconnecting: wait for someone to resurrect the character, revive at the lodestar, or stop and show an alert" />}
+                        item={<div style={{width:'100%', color: 'black'}}><Select options={deathPolicyOptions} onChange={value => onChange({...config, on_death: value?.value ?? 'ReviveAtLodestar'})} value={deathPolicyOptions.find(x => x.value == (config.on_death ?? 'ReviveAtLodestar'))}/></div>}
+                    />
                 </ConfigTable>
             }/>
 
@@ -66,7 +66,7 @@ export type SlotBarHolder = {
 type SlotBarModel = FixedArray<SlotModel, 10>
 export type SlotBars = FixedArray<SlotBarHolder, 9>
 
-export type ModeModel = "Farming" | "Support" | "AutoShout"
+export type ModeModel = "Farming" | "Support" | "AutoShout" | "Scripted"
 
 export type FarmingConfigModel = Partial<{
     [key: string]: any;
@@ -75,6 +75,11 @@ export type FarmingConfigModel = Partial<{
     stay_in_area: boolean,
     slot_bars: SlotBars,
     circle_pattern_rotation_duration: number,
+    circle_pattern_sequence: string,
+    patrol_route: string[],
+    patrol_anchor: string,
+    patrol_radius: number,
+    minimap_units_per_pixel: number,
 
     passive_mobs_colors: number[];
     passive_tolerence: number;
@@ -86,24 +91,52 @@ export type FarmingConfigModel = Partial<{
 
     obstacle_avoidance_cooldown: number,
     obstacle_avoidance_max_try: number,
+    obstacle_avoidance_sequence: string,
 
     min_mobs_name_width: number,
     max_mobs_name_width: number,
+    mobs_name_gap_x: number,
+    mobs_name_gap_y: number,
+
+    allowed_mobs_names: string[],
+    forbidden_mobs_names: string[],
+    min_mobs_level: number,
+    max_mobs_level: number,
+
+    giant_policy: GiantPolicy,
+    giant_mobs_names: string[],
+    giant_min_name_width: number,
+
+    distance_weight: number,
+    aggressive_weight: number,
+    density_weight: number,
+    low_resources_weight: number,
+    avoided_weight: number,
+    density_radius: number,
 
     min_hp_attack: number,
     pickup_duration: number,
     hit_and_run: boolean,
     auto_bird_view: boolean,
     on_death_disconnect: boolean,
+    on_death: DeathPolicy,
     interval_between_buffs: number,
     mobs_timeout: number,
 }>
 
+export const deathPolicies = ["WaitForResurrection", "ReviveAtLodestar", "StopAndAlert"] as const
+export type DeathPolicy = typeof deathPolicies[number]
+
+export const giantPolicies = ["Attack", "Avoid", "AttackAtFullHp", "FleeIfAggroed"] as const
+export type GiantPolicy = typeof giantPolicies[number]
+
 export type SupportConfigModel = Partial<{
     [key: string]: any;
     slot_bars: SlotBars,
     obstacle_avoidance_cooldown: number,
+    obstacle_avoidance_sequence: string,
     on_death_disconnect: boolean,
+    on_death: DeathPolicy,
     interval_between_buffs: number,
 }>
 
@@ -113,6 +146,29 @@ export type ShoutConfigModel = Partial<{
     shout_messages: string[],
 }>
 
+export type ScriptConfigModel = Partial<{
+    [key: string]: any;
+    script: string,
+}>
+
+export type ConnectionConfigModel = Partial<{
+    auto_reconnect: boolean,
+    character_slot: number,
+    max_reconnect_attempts: number,
+    reconnect_delay: number,
+    max_reconnect_delay: number,
+    load_timeout: number,
+}>
+
+type BoundsModel = { x: number, y: number, w: number, h: number }
+
+export type CalibrationConfigModel = {
+    frame_width: number,
+    frame_height: number,
+    status_bars: { kind: string, region: BoundsModel, colors: { refs: number[] }[] }[],
+    areas: [string, BoundsModel][],
+}
+
 export type BotConfigModel = {
     change_id: number,
     is_running: boolean,
@@ -120,6 +176,9 @@ export type BotConfigModel = {
     farming_config: FarmingConfigModel,
     support_config: SupportConfigModel,
     shout_config: ShoutConfigModel,
+    script_config?: ScriptConfigModel,
+    connection_config?: ConnectionConfigModel,
+    calibration_config?: CalibrationConfigModel | null,
 }
 
-export type AnyConfig = FarmingConfigModel | SupportConfigModel | ShoutConfigModel
+export type AnyConfig = FarmingConfigModel | SupportConfigModel | ShoutConfigModel | ScriptConfigModel
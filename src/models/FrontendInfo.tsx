@@ -1,3 +1,18 @@
+export type ExperienceStatsModel = {
+    current: number,
+    gained: number,
+    per_hour: number,
+    time_to_level: number,
+    level_ups: number,
+}
+
+export type FrontendAlert = "Died" | "ConnectionLost"
+
+export const alertMessages: Record<FrontendAlert, string> = {
+    Died: "Character died, the bot stopped until it's revived",
+    ConnectionLost: "Could not reconnect, the retry limit was reached",
+}
+
 export type FrontendInfoModel = {
     /* enemy_bounds?: Bounds[],
     active_enemy_bounds?: Bounds, */
@@ -9,5 +24,7 @@ export type FrontendInfoModel = {
     last_search_duration: number,
     kill_min_avg: number,
     kill_hour_avg: number,
+    exp_stats?: ExperienceStatsModel,
+    alert?: FrontendAlert | null,
     version: number[],
 }